@@ -0,0 +1,455 @@
+// Rustimenator web UI. Uses only the public JSON API, so it doubles as a reference client.
+"use strict";
+
+const state = { tasks: [], tags: [], running: [], history: [] };
+
+// Filled from GET /ui/config.json before the first API call.
+let config = { event_stream: false, token_required: false };
+
+// With `auth.mode = "token"` the token is asked for once and kept in the browser.
+const TOKEN_KEY = "rustimenator-token";
+let token = localStorage.getItem(TOKEN_KEY) || "";
+
+function askForToken() {
+  const entered = window.prompt("API token");
+  if (entered === null) {
+    return false;
+  }
+  token = entered.trim();
+  localStorage.setItem(TOKEN_KEY, token);
+  return true;
+}
+
+// The server stores naive UTC timestamps such as "2025-06-22T12:00:00".
+const parseTime = (value) => (value ? new Date(value.replace(" ", "T") + "Z") : null);
+const toServerTime = (date) => date.toISOString().slice(0, 19);
+const toLocalInput = (date) => {
+  const local = new Date(date.getTime() - date.getTimezoneOffset() * 60000);
+  return local.toISOString().slice(0, 19);
+};
+
+function formatDuration(seconds) {
+  seconds = Math.max(0, Math.floor(seconds));
+  const h = Math.floor(seconds / 3600);
+  const m = Math.floor((seconds % 3600) / 60);
+  const s = seconds % 60;
+  return `${h}:${String(m).padStart(2, "0")}:${String(s).padStart(2, "0")}`;
+}
+
+function eventSeconds(event) {
+  const start = parseTime(event.created_at);
+  const stop = parseTime(event.stopped_at) || new Date();
+  return (stop - start) / 1000;
+}
+
+function element(tag, attributes = {}, ...children) {
+  const node = document.createElement(tag);
+  for (const [key, value] of Object.entries(attributes)) {
+    if (key.startsWith("on")) {
+      node.addEventListener(key.slice(2), value);
+    } else {
+      node.setAttribute(key, value);
+    }
+  }
+  for (const child of children) {
+    node.append(child);
+  }
+  return node;
+}
+
+function showError(message) {
+  const error = document.getElementById("error");
+  error.textContent = message;
+  error.hidden = !message;
+}
+
+async function api(method, path, body) {
+  const options = { method, headers: {} };
+  if (body !== undefined) {
+    options.headers["Content-Type"] = "application/json";
+    options.body = JSON.stringify(body);
+  }
+  const send = () => {
+    if (token) {
+      options.headers["Authorization"] = `Bearer ${token}`;
+    }
+    return fetch(path, options);
+  };
+  let response = await send();
+  // A missing or revoked token: ask for a new one and try once more.
+  if (response.status === 401 && askForToken()) {
+    response = await send();
+    if (response.ok) {
+      listenForChanges();
+    }
+  }
+  if (!response.ok) {
+    let message = `${method} ${path} failed: ${response.status}`;
+    try {
+      const details = await response.json();
+      if (details.errors) {
+        message += " – " + details.errors.map((e) => `${e.field} ${e.message}`).join(", ");
+      }
+    } catch (_) {
+      // Most error responses have no body.
+    }
+    throw new Error(message);
+  }
+  showError("");
+  return response.status === 204 ? null : response.json();
+}
+
+const taskName = (event) => (event.task ? event.task.task : `task #${event.task_id}`);
+const tagChips = (tags) => tags.map((tag) => element("span", { class: "chip" }, tag.name));
+
+function tagCheckboxes(container, selected) {
+  container.replaceChildren(
+    ...state.tags.map((tag) =>
+      element(
+        "label",
+        {},
+        Object.assign(element("input", { type: "checkbox", value: tag.id }), {
+          checked: selected.includes(tag.id),
+        }),
+        " " + tag.name,
+      ),
+    ),
+  );
+}
+
+const checkedTagIds = (container) =>
+  [...container.querySelectorAll("input:checked")].map((input) => Number(input.value));
+
+async function loadCatalog() {
+  const [tasks, tags] = await Promise.all([api("GET", "/tasks"), api("GET", "/tags")]);
+  state.tasks = tasks.tasks;
+  state.tags = tags.tags;
+  document
+    .getElementById("task-names")
+    .replaceChildren(...state.tasks.map((task) => element("option", { value: task.task })));
+  tagCheckboxes(document.getElementById("start-tags"), checkedTagIds(document.getElementById("start-tags")));
+}
+
+// Timers view
+
+async function loadRunning() {
+  state.running = (await api("GET", "/events")).events;
+  renderRunning();
+}
+
+function renderRunning() {
+  const list = document.getElementById("running");
+  list.replaceChildren(
+    ...state.running.map((event) =>
+      element(
+        "li",
+        {},
+        element("span", { class: "elapsed", "data-started": event.created_at }, formatDuration(eventSeconds(event))),
+        element("span", { class: "grow" }, taskName(event), " ", ...tagChips(event.tags || [])),
+        element("button", { onclick: () => stopEvent(event.id) }, "Stop"),
+      ),
+    ),
+  );
+  document.getElementById("running-empty").hidden = state.running.length > 0;
+}
+
+function tickRunning() {
+  for (const span of document.querySelectorAll("#running .elapsed")) {
+    span.textContent = formatDuration((new Date() - parseTime(span.dataset.started)) / 1000);
+  }
+}
+
+async function loadCombinations() {
+  const { combinations } = await api("GET", "/events/combinations?limit=6");
+  document.getElementById("combinations").replaceChildren(
+    ...combinations.map((combination) =>
+      element(
+        "li",
+        {},
+        element(
+          "span",
+          { class: "grow" },
+          combination.task ? combination.task.task : `task #${combination.task_id}`,
+          " ",
+          ...tagChips(combination.tags),
+        ),
+        element("span", { class: "muted" }, `${combination.uses}×`),
+        element(
+          "button",
+          {
+            class: "secondary",
+            onclick: () => startEvent(combination.task_id, combination.tag_ids),
+          },
+          "Start",
+        ),
+      ),
+    ),
+  );
+}
+
+async function findOrCreateTask(name) {
+  const existing = state.tasks.find((task) => task.task.toLowerCase() === name.toLowerCase());
+  if (existing) {
+    return existing.id;
+  }
+  return (await api("POST", "/task", { task: name })).id;
+}
+
+async function startEvent(taskId, tagIds) {
+  try {
+    await api("POST", "/events/start", { task_id: taskId, tag_ids: tagIds });
+    await refreshAll();
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+async function stopEvent(id) {
+  try {
+    await api("POST", `/events/stop/${id}`);
+    await refreshAll();
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+async function submitStart(submit) {
+  submit.preventDefault();
+  try {
+    const name = document.getElementById("start-task").value.trim();
+    const taskId = await findOrCreateTask(name);
+    const tagIds = checkedTagIds(document.getElementById("start-tags"));
+    const newTag = document.getElementById("start-new-tag");
+    if (newTag.value.trim()) {
+      tagIds.push((await api("POST", "/tag", { name: newTag.value.trim() })).id);
+      newTag.value = "";
+    }
+    await loadCatalog();
+    await startEvent(taskId, tagIds);
+    document.getElementById("start-task").value = "";
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+// History view
+
+function sinceDays(days) {
+  return toServerTime(new Date(Date.now() - days * 86400000));
+}
+
+async function fetchEventsSince(days) {
+  const since = encodeURIComponent(sinceDays(days));
+  return (await api("GET", `/events?status=all&since=${since}`)).events;
+}
+
+async function loadHistory() {
+  const days = Number(document.getElementById("history-days").value);
+  state.history = await fetchEventsSince(days);
+  document.getElementById("history-rows").replaceChildren(
+    ...state.history.map((event) => {
+      const start = parseTime(event.created_at);
+      const stop = parseTime(event.stopped_at);
+      const actions = [element("button", { class: "secondary", onclick: () => openEditor(event) }, "Edit")];
+      if (stop) {
+        actions.push(element("button", { class: "secondary", onclick: () => continueEvent(event.id) }, "Continue"));
+      }
+      actions.push(element("button", { class: "secondary", onclick: () => deleteEvent(event.id) }, "Delete"));
+      return element(
+        "tr",
+        {},
+        element("td", {}, start.toLocaleString()),
+        element("td", {}, stop ? stop.toLocaleString() : "running"),
+        element("td", {}, formatDuration(eventSeconds(event))),
+        element("td", {}, taskName(event)),
+        element("td", {}, ...tagChips(event.tags || [])),
+        element("td", { class: "actions" }, ...actions),
+      );
+    }),
+  );
+}
+
+async function continueEvent(id) {
+  try {
+    await api("POST", `/events/${id}/continue`);
+    await refreshAll();
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+async function deleteEvent(id) {
+  if (!confirm("Delete this event?")) {
+    return;
+  }
+  try {
+    await api("DELETE", `/events/${id}`);
+    await refreshAll();
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+function openEditor(event) {
+  const dialog = document.getElementById("edit-dialog");
+  const task = document.getElementById("edit-task");
+  task.replaceChildren(...state.tasks.map((t) => element("option", { value: t.id }, t.task)));
+  task.value = event.task_id;
+  document.getElementById("edit-start").value = toLocalInput(parseTime(event.created_at));
+  const stop = parseTime(event.stopped_at);
+  document.getElementById("edit-stop").value = stop ? toLocalInput(stop) : "";
+  tagCheckboxes(document.getElementById("edit-tags"), (event.tags || []).map((tag) => tag.id));
+  dialog.dataset.eventId = event.id;
+  dialog.showModal();
+}
+
+async function saveEdit() {
+  const dialog = document.getElementById("edit-dialog");
+  if (dialog.returnValue !== "save") {
+    return;
+  }
+  const update = {
+    task_id: Number(document.getElementById("edit-task").value),
+    tag_ids: checkedTagIds(document.getElementById("edit-tags")),
+    created_at: toServerTime(new Date(document.getElementById("edit-start").value)),
+  };
+  const stop = document.getElementById("edit-stop").value;
+  if (stop) {
+    update.stopped_at = toServerTime(new Date(stop));
+  }
+  try {
+    await api("PATCH", `/events/${dialog.dataset.eventId}`, update);
+    await refreshAll();
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+// Summary view
+
+function barChart(container, totals) {
+  const max = Math.max(1, ...totals.map(([, seconds]) => seconds));
+  container.replaceChildren(
+    ...totals.map(([name, seconds]) =>
+      element(
+        "div",
+        { class: "row" },
+        element("span", {}, name),
+        element("div", { class: "bar", style: `width: ${(seconds / max) * 100}%` }),
+        element("span", { class: "muted" }, formatDuration(seconds)),
+      ),
+    ),
+  );
+}
+
+function columnChart(container, totals) {
+  const max = Math.max(1, ...totals.map(([, seconds]) => seconds));
+  container.replaceChildren(
+    ...totals.map(([label, seconds]) =>
+      element(
+        "div",
+        { class: "column", title: formatDuration(seconds) },
+        element("div", { class: "bar", style: `height: ${(seconds / max) * 100}%` }),
+        element("span", { class: "muted" }, label),
+      ),
+    ),
+  );
+}
+
+const sortedTotals = (map) => [...map.entries()].sort((a, b) => b[1] - a[1]);
+
+async function loadSummary() {
+  const days = Number(document.getElementById("summary-days").value);
+  const events = await fetchEventsSince(days);
+  const perTask = new Map();
+  const perTag = new Map();
+  const perDay = new Map();
+  for (let i = days - 1; i >= 0; i--) {
+    perDay.set(new Date(Date.now() - i * 86400000).toLocaleDateString(), 0);
+  }
+  let total = 0;
+  for (const event of events) {
+    const seconds = eventSeconds(event);
+    total += seconds;
+    perTask.set(taskName(event), (perTask.get(taskName(event)) || 0) + seconds);
+    for (const tag of event.tags || []) {
+      perTag.set(tag.name, (perTag.get(tag.name) || 0) + seconds);
+    }
+    const day = parseTime(event.created_at).toLocaleDateString();
+    if (perDay.has(day)) {
+      perDay.set(day, perDay.get(day) + seconds);
+    }
+  }
+  document.getElementById("summary-total").textContent = `${formatDuration(total)} tracked`;
+  columnChart(document.getElementById("chart-days"), [...perDay.entries()]);
+  barChart(document.getElementById("chart-tasks"), sortedTotals(perTask));
+  barChart(document.getElementById("chart-tags"), sortedTotals(perTag));
+}
+
+// Wiring
+
+let currentView = "timers";
+
+async function refreshAll() {
+  try {
+    await loadCatalog();
+    await loadRunning();
+    if (currentView === "timers") {
+      await loadCombinations();
+    } else if (currentView === "history") {
+      await loadHistory();
+    } else if (currentView === "summary") {
+      await loadSummary();
+    }
+  } catch (error) {
+    showError(error.message);
+  }
+}
+
+function showView(name) {
+  currentView = name;
+  for (const view of document.querySelectorAll(".view")) {
+    view.hidden = view.id !== name;
+  }
+  for (const button of document.querySelectorAll("nav button")) {
+    button.classList.toggle("active", button.dataset.view === name);
+  }
+  refreshAll();
+}
+
+// Without the event stream the UI only refreshes on its own actions and view changes.
+let stream = null;
+
+function listenForChanges() {
+  if (!config.event_stream) {
+    return;
+  }
+  if (stream) {
+    stream.close();
+  }
+  // EventSource cannot send headers, so the stream takes the token as a query parameter.
+  stream = new EventSource(token ? `/events/stream?token=${encodeURIComponent(token)}` : "/events/stream");
+  for (const change of ["started", "stopped", "edited", "deleted", "lagged"]) {
+    stream.addEventListener(change, () => refreshAll());
+  }
+}
+
+document.querySelectorAll("nav button").forEach((button) =>
+  button.addEventListener("click", () => showView(button.dataset.view)),
+);
+document.getElementById("start-form").addEventListener("submit", submitStart);
+document.getElementById("history-days").addEventListener("change", () => loadHistory().catch((e) => showError(e.message)));
+document.getElementById("summary-days").addEventListener("change", () => loadSummary().catch((e) => showError(e.message)));
+document.getElementById("edit-dialog").addEventListener("close", saveEdit);
+
+setInterval(tickRunning, 1000);
+async function init() {
+  config = await fetch("/ui/config.json").then((response) => response.json());
+  if (config.token_required && !token) {
+    askForToken();
+  }
+  await refreshAll();
+  listenForChanges();
+}
+
+init().catch((e) => showError(e.message));
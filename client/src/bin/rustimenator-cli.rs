@@ -0,0 +1,444 @@
+use std::collections::BTreeMap;
+
+use clap::{Parser, Subcommand};
+use reqwest::StatusCode;
+use rustimenator::{
+    CreateTagRequest, CreateTaskRequest, CreateTimedEventRequest, EventStatus, GetEventsQuery,
+    StopEventResponse, Tag, Task, TimedEvent,
+};
+use rustimenator_client::{Client, Error};
+use serde::Serialize;
+
+type CliResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Command-line client for the Rustimenator HTTP API.
+#[derive(Parser)]
+#[command(name = "rustimenator-cli", version)]
+struct Cli {
+    /// Base URL of the Rustimenator server.
+    #[arg(
+        long,
+        env = "RUSTIMENATOR_URL",
+        default_value = "http://localhost:8080",
+        global = true
+    )]
+    server: String,
+
+    /// Bearer token for servers running with `auth.mode = "token"`.
+    #[arg(
+        long,
+        env = "RUSTIMENATOR_TOKEN",
+        hide_env_values = true,
+        global = true
+    )]
+    token: Option<String>,
+
+    /// Print JSON instead of human-readable output.
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start an event for a task, optionally tagged.
+    Start {
+        /// Task name.
+        task: String,
+        /// Tag name; may be repeated.
+        #[arg(long = "tag", short = 't')]
+        tags: Vec<String>,
+        /// Create the task and tags if they do not exist yet.
+        #[arg(long)]
+        create: bool,
+    },
+    /// Stop a running event, or every running event when no id is given.
+    Stop { id: Option<i64> },
+    /// Show running events.
+    Status,
+    /// Show recent events, running and stopped.
+    Log {
+        /// How many days back to look.
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+        /// Maximum number of events to show.
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// List tags.
+    Tags,
+    /// List tasks.
+    Tasks,
+    /// Show time tracked per task and tag.
+    Report {
+        /// How many days back to look.
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+    },
+}
+
+struct Api {
+    client: Client,
+}
+
+impl Api {
+    fn new(base_url: &str, token: Option<&str>) -> CliResult<Self> {
+        let mut builder = Client::builder(base_url);
+        if let Some(token) = token {
+            builder = builder.token(token);
+        }
+        Ok(Self {
+            client: builder.build()?,
+        })
+    }
+
+    async fn tasks(&self) -> CliResult<Vec<Task>> {
+        Ok(self.client.tasks().await.map_err(explain)?.tasks)
+    }
+
+    async fn tags(&self) -> CliResult<Vec<Tag>> {
+        Ok(self.client.tags().await.map_err(explain)?.tags)
+    }
+
+    async fn events(&self, query: &GetEventsQuery) -> CliResult<Vec<TimedEvent>> {
+        Ok(self.client.events(query).await.map_err(explain)?.events)
+    }
+
+    async fn resolve_task(&self, name: &str, create: bool) -> CliResult<i64> {
+        let tasks = self.tasks().await?;
+        if let Some(id) = find_by_name(&tasks, name, |task| &task.task, |task| task.id) {
+            return Ok(id);
+        }
+        if !create {
+            return Err(format!("unknown task '{}' (use --create to add it)", name).into());
+        }
+        let request = CreateTaskRequest {
+            task: name.to_string(),
+            estimate_seconds: None,
+        };
+        Ok(self.client.create_task(&request).await.map_err(explain)?.id)
+    }
+
+    async fn resolve_tags(&self, names: &[String], create: bool) -> CliResult<Vec<i64>> {
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let tags = self.tags().await?;
+        let mut ids = Vec::with_capacity(names.len());
+        for name in names {
+            if let Some(id) = find_by_name(&tags, name, |tag| &tag.name, |tag| tag.id) {
+                ids.push(id);
+                continue;
+            }
+            if !create {
+                return Err(format!("unknown tag '{}' (use --create to add it)", name).into());
+            }
+            let request = CreateTagRequest { name: name.clone() };
+            ids.push(self.client.create_tag(&request).await.map_err(explain)?.id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Turns a failed call into a message naming the status and what it most likely means here.
+fn explain(error: Error) -> Box<dyn std::error::Error> {
+    let Some(status) = error.status() else {
+        return error.into();
+    };
+    let detail = match &error {
+        Error::Validation(validation) => validation
+            .errors
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => describe_status(status).to_string(),
+    };
+    format!("server returned {}: {}", status, detail).into()
+}
+
+/// Exact match first, then case-insensitive, since the server may normalise tag case.
+fn find_by_name<T>(
+    items: &[T],
+    name: &str,
+    item_name: impl Fn(&T) -> &String,
+    item_id: impl Fn(&T) -> i64,
+) -> Option<i64> {
+    let name = name.trim();
+    items
+        .iter()
+        .find(|item| item_name(item) == name)
+        .or_else(|| {
+            items
+                .iter()
+                .find(|item| item_name(item).eq_ignore_ascii_case(name))
+        })
+        .map(item_id)
+}
+
+fn describe_status(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "unknown task or tag",
+        StatusCode::NOT_FOUND => "not found or not running",
+        StatusCode::CONFLICT => "already exists",
+        _ => status.canonical_reason().unwrap_or("request failed"),
+    }
+}
+
+fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+fn now() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+fn event_seconds(event: &TimedEvent) -> i64 {
+    let Some(started) = event.created_at else {
+        return 0;
+    };
+    (event.stopped_at.unwrap_or_else(now) - started).num_seconds()
+}
+
+fn describe_event(event: &TimedEvent) -> String {
+    let task = event
+        .task
+        .as_ref()
+        .map(|task| task.task.clone())
+        .unwrap_or_else(|| format!("task #{}", event.task_id));
+    let tags = if event.tags.is_empty() {
+        String::new()
+    } else {
+        let names: Vec<&str> = event.tags.iter().map(|tag| tag.name.as_str()).collect();
+        format!(" [{}]", names.join(", "))
+    };
+    format!("{}{}", task, tags)
+}
+
+fn print_json(value: &impl Serialize) -> CliResult<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ReportLine {
+    name: String,
+    seconds: i64,
+}
+
+#[derive(Serialize)]
+struct Report {
+    since: chrono::NaiveDateTime,
+    total_seconds: i64,
+    tasks: Vec<ReportLine>,
+    tags: Vec<ReportLine>,
+}
+
+fn build_report(since: chrono::NaiveDateTime, events: &[TimedEvent]) -> Report {
+    let mut tasks: BTreeMap<String, i64> = BTreeMap::new();
+    let mut tags: BTreeMap<String, i64> = BTreeMap::new();
+    let mut total_seconds = 0;
+
+    for event in events {
+        let seconds = event_seconds(event);
+        total_seconds += seconds;
+        let task = event
+            .task
+            .as_ref()
+            .map(|task| task.task.clone())
+            .unwrap_or_else(|| format!("task #{}", event.task_id));
+        *tasks.entry(task).or_default() += seconds;
+        for tag in &event.tags {
+            *tags.entry(tag.name.clone()).or_default() += seconds;
+        }
+    }
+
+    let into_lines = |totals: BTreeMap<String, i64>| {
+        let mut lines: Vec<ReportLine> = totals
+            .into_iter()
+            .map(|(name, seconds)| ReportLine { name, seconds })
+            .collect();
+        lines.sort_by_key(|line| std::cmp::Reverse(line.seconds));
+        lines
+    };
+
+    Report {
+        since,
+        total_seconds,
+        tasks: into_lines(tasks),
+        tags: into_lines(tags),
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    let api = Api::new(&cli.server, cli.token.as_deref())?;
+
+    match cli.command {
+        Command::Start { task, tags, create } => {
+            let task_id = api.resolve_task(&task, create).await?;
+            let tag_ids = api.resolve_tags(&tags, create).await?;
+            let started = api
+                .client
+                .start_event(&CreateTimedEventRequest { task_id, tag_ids })
+                .await
+                .map_err(explain)?;
+            if cli.json {
+                return print_json(&started);
+            }
+            for stopped in &started.stopped_events {
+                println!(
+                    "Stopped event {} after {}",
+                    stopped.id,
+                    format_duration(stopped.duration_seconds)
+                );
+            }
+            println!("Started event {} for {}", started.id, task);
+        }
+        Command::Stop { id } => {
+            let ids = match id {
+                Some(id) => vec![id],
+                None => api
+                    .events(&GetEventsQuery::default())
+                    .await?
+                    .into_iter()
+                    .map(|event| event.id)
+                    .collect(),
+            };
+            let mut stopped = Vec::new();
+            for id in ids {
+                let response: StopEventResponse =
+                    api.client.stop_event(id).await.map_err(explain)?;
+                stopped.push(response);
+            }
+            if cli.json {
+                return print_json(&stopped);
+            }
+            if stopped.is_empty() {
+                println!("No running events");
+            }
+            for response in stopped {
+                println!(
+                    "Stopped event {} after {}",
+                    response.id,
+                    format_duration(response.duration_seconds)
+                );
+            }
+        }
+        Command::Status => {
+            let events = api.events(&GetEventsQuery::default()).await?;
+            if cli.json {
+                return print_json(&events);
+            }
+            if events.is_empty() {
+                println!("No running events");
+            }
+            for event in events {
+                println!(
+                    "#{:<6} {:>12}  {}",
+                    event.id,
+                    format_duration(event_seconds(&event)),
+                    describe_event(&event)
+                );
+            }
+        }
+        Command::Log { days, limit } => {
+            let since = now() - chrono::Duration::days(days);
+            let events = api
+                .events(&GetEventsQuery {
+                    status: Some(EventStatus::All),
+                    since: Some(since),
+                    limit: Some(limit),
+                    ..GetEventsQuery::default()
+                })
+                .await?;
+            if cli.json {
+                return print_json(&events);
+            }
+            for event in events {
+                let started = event
+                    .created_at
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                let state = if event.stopped_at.is_some() {
+                    ""
+                } else {
+                    " (running)"
+                };
+                println!(
+                    "#{:<6} {}  {:>12}  {}{}",
+                    event.id,
+                    started,
+                    format_duration(event_seconds(&event)),
+                    describe_event(&event),
+                    state
+                );
+            }
+        }
+        Command::Tags => {
+            let tags = api.tags().await?;
+            if cli.json {
+                return print_json(&tags);
+            }
+            for tag in tags {
+                println!("#{:<6} {}", tag.id, tag.name);
+            }
+        }
+        Command::Tasks => {
+            let tasks = api.tasks().await?;
+            if cli.json {
+                return print_json(&tasks);
+            }
+            for task in tasks {
+                println!("#{:<6} {}", task.id, task.task);
+            }
+        }
+        Command::Report { days } => {
+            let since = now() - chrono::Duration::days(days);
+            let events = api
+                .events(&GetEventsQuery {
+                    status: Some(EventStatus::All),
+                    since: Some(since),
+                    ..GetEventsQuery::default()
+                })
+                .await?;
+            let report = build_report(since, &events);
+            if cli.json {
+                return print_json(&report);
+            }
+            println!(
+                "Since {}: {}",
+                report.since.format("%Y-%m-%d %H:%M"),
+                format_duration(report.total_seconds)
+            );
+            println!("\nTasks");
+            for line in &report.tasks {
+                println!("  {:>12}  {}", format_duration(line.seconds), line.name);
+            }
+            if !report.tags.is_empty() {
+                println!("\nTags");
+                for line in &report.tags {
+                    println!("  {:>12}  {}", format_duration(line.seconds), line.name);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(cli).await {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
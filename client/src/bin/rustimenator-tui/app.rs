@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    widgets::TableState,
+};
+use rustimenator::{EventStatus, GetEventsQuery, TimedEvent};
+
+use crate::backend::Backend;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    Running,
+    Recent,
+}
+
+pub enum Mode {
+    Normal,
+    /// Typing `task name #tag #tag` for a new event.
+    Input(String),
+}
+
+pub struct App {
+    pub backend: Backend,
+    pub running: Vec<TimedEvent>,
+    /// Events started today, newest first, running or stopped.
+    pub today: Vec<TimedEvent>,
+    pub focus: Focus,
+    pub running_state: TableState,
+    pub recent_state: TableState,
+    pub mode: Mode,
+    pub status: String,
+    pub should_quit: bool,
+}
+
+/// Tracked seconds per task or tag name.
+pub type Totals = Vec<(String, i64)>;
+
+pub fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+/// Seconds an event has run so far; running events count up to now.
+pub fn elapsed(event: &TimedEvent) -> i64 {
+    let Some(started) = event.created_at else {
+        return 0;
+    };
+    (event.stopped_at.unwrap_or_else(now) - started)
+        .num_seconds()
+        .max(0)
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    format!(
+        "{:>2}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+pub fn task_name(event: &TimedEvent) -> String {
+    event
+        .task
+        .as_ref()
+        .map(|task| task.task.clone())
+        .unwrap_or_else(|| format!("task #{}", event.task_id))
+}
+
+pub fn tag_names(event: &TimedEvent) -> String {
+    event
+        .tags
+        .iter()
+        .map(|tag| tag.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Local midnight expressed in UTC, which is how the server stores timestamps.
+fn start_of_today() -> NaiveDateTime {
+    let midnight = Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|local| local.naive_utc())
+        .unwrap_or(midnight)
+}
+
+/// Splits `task name #tag1 #tag2` into the task and its tags.
+fn parse_start_input(input: &str) -> Option<(String, Vec<String>)> {
+    let mut task_words = Vec::new();
+    let mut tags = Vec::new();
+    for word in input.split_whitespace() {
+        match word.strip_prefix('#') {
+            Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+            _ => task_words.push(word),
+        }
+    }
+    if task_words.is_empty() {
+        return None;
+    }
+    Some((task_words.join(" "), tags))
+}
+
+impl App {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            running: Vec::new(),
+            today: Vec::new(),
+            focus: Focus::Running,
+            running_state: TableState::default(),
+            recent_state: TableState::default(),
+            mode: Mode::Normal,
+            status: "Press ? for help".to_string(),
+            should_quit: false,
+        }
+    }
+
+    pub async fn refresh(&mut self) {
+        let running = self.backend.events(&GetEventsQuery::default()).await;
+        let today = self
+            .backend
+            .events(&GetEventsQuery {
+                status: Some(EventStatus::All),
+                since: Some(start_of_today()),
+                ..GetEventsQuery::default()
+            })
+            .await;
+
+        match (running, today) {
+            (Ok(running), Ok(today)) => {
+                self.running = running;
+                self.today = today;
+            }
+            (Err(error), _) | (_, Err(error)) => self.status = error,
+        }
+
+        let recent = self.recent().len();
+        clamp_selection(&mut self.running_state, self.running.len());
+        clamp_selection(&mut self.recent_state, recent);
+    }
+
+    /// Stopped events from today, the candidates for continuing.
+    pub fn recent(&self) -> Vec<&TimedEvent> {
+        self.today
+            .iter()
+            .filter(|event| event.stopped_at.is_some())
+            .collect()
+    }
+
+    /// Today's tracked seconds per task and per tag, largest first. Running events that
+    /// started before midnight are counted in full.
+    pub fn totals(&self) -> (Totals, Totals) {
+        let mut tasks: BTreeMap<String, i64> = BTreeMap::new();
+        let mut tags: BTreeMap<String, i64> = BTreeMap::new();
+
+        let earlier_running = self
+            .running
+            .iter()
+            .filter(|running| !self.today.iter().any(|event| event.id == running.id));
+        for event in self.today.iter().chain(earlier_running) {
+            let seconds = elapsed(event);
+            *tasks.entry(task_name(event)).or_default() += seconds;
+            for tag in &event.tags {
+                *tags.entry(tag.name.clone()).or_default() += seconds;
+            }
+        }
+
+        let sorted = |totals: BTreeMap<String, i64>| {
+            let mut totals: Totals = totals.into_iter().collect();
+            totals.sort_by_key(|(_, seconds)| std::cmp::Reverse(*seconds));
+            totals
+        };
+        (sorted(tasks), sorted(tags))
+    }
+
+    pub async fn handle_key(&mut self, key: KeyEvent) {
+        if let Mode::Input(input) = &mut self.mode {
+            match key.code {
+                KeyCode::Enter => {
+                    let input = std::mem::take(input);
+                    self.mode = Mode::Normal;
+                    self.start(&input).await;
+                }
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    self.status = "Cancelled".to_string();
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true
+            }
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Running => Focus::Recent,
+                    Focus::Recent => Focus::Running,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Char('s') | KeyCode::Char('n') => self.mode = Mode::Input(String::new()),
+            KeyCode::Char('x') => self.stop_selected().await,
+            KeyCode::Char('c') => self.continue_selected().await,
+            KeyCode::Char('r') => {
+                self.refresh().await;
+                self.status = "Refreshed".to_string();
+            }
+            KeyCode::Char('?') => {
+                self.status =
+                    "s start · x stop · c continue · tab switch · r refresh · q quit".to_string()
+            }
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, delta: i64) {
+        let (state, len) = match self.focus {
+            Focus::Running => (&mut self.running_state, self.running.len()),
+            Focus::Recent => {
+                let len = self.recent().len();
+                (&mut self.recent_state, len)
+            }
+        };
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as i64;
+        let next = (current + delta).rem_euclid(len as i64) as usize;
+        state.select(Some(next));
+    }
+
+    async fn start(&mut self, input: &str) {
+        let Some((task, tags)) = parse_start_input(input) else {
+            self.status = "Type a task name, optionally followed by #tags".to_string();
+            return;
+        };
+        self.status = match self.backend.start(&task, &tags).await {
+            Ok(started) => format!("Started event {} for {}", started.id, task),
+            Err(error) => error,
+        };
+        self.refresh().await;
+    }
+
+    async fn stop_selected(&mut self) {
+        let selected = self
+            .running_state
+            .selected()
+            .and_then(|index| self.running.get(index));
+        let Some(event) = selected else {
+            self.status = "Select a running event to stop".to_string();
+            return;
+        };
+        self.status = match self.backend.stop(event.id).await {
+            Ok(stopped) => format!(
+                "Stopped event {} after {}",
+                stopped.id,
+                format_duration(stopped.duration_seconds).trim_start()
+            ),
+            Err(error) => error,
+        };
+        self.refresh().await;
+    }
+
+    async fn continue_selected(&mut self) {
+        let recent = self.recent();
+        let selected = match self.focus {
+            Focus::Recent => self
+                .recent_state
+                .selected()
+                .and_then(|index| recent.get(index).copied()),
+            Focus::Running => None,
+        };
+        let Some(event) = selected else {
+            self.status = "Select a stopped event under Today to continue".to_string();
+            return;
+        };
+        let (id, name) = (event.id, task_name(event));
+        self.status = match self.backend.continue_event(id).await {
+            Ok(started) => format!("Started event {} for {}", started.id, name),
+            Err(error) => error,
+        };
+        self.refresh().await;
+    }
+}
+
+fn clamp_selection(state: &mut TableState, len: usize) {
+    match state.selected() {
+        _ if len == 0 => state.select(None),
+        Some(index) if index >= len => state.select(Some(len - 1)),
+        None => state.select(Some(0)),
+        Some(_) => {}
+    }
+}
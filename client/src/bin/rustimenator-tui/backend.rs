@@ -0,0 +1,118 @@
+use rustimenator::{
+    CreateTagRequest, CreateTaskRequest, CreateTimedEventRequest, CreateTimedEventResponse,
+    GetEventsQuery, StopEventResponse, TimedEvent, create_app, create_database_pool,
+};
+use rustimenator_client::Client;
+
+pub type BackendResult<T> = Result<T, String>;
+
+/// Where the dashboard reads and writes events, always through the JSON API. With a local
+/// database the server's router is served in-process on a loopback port, so the same rules
+/// apply.
+pub struct Backend {
+    client: Client,
+    description: String,
+}
+
+impl Backend {
+    pub fn http(base_url: &str, token: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = Client::builder(base_url);
+        if let Some(token) = token {
+            builder = builder.token(token);
+        }
+        Ok(Backend {
+            client: builder.build()?,
+            description: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub async fn local(database: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let url = if database.starts_with("sqlite:") {
+            database.to_string()
+        } else {
+            format!("sqlite:{}", database)
+        };
+        let pool = create_database_pool(&url).await?;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, create_app(pool)).await });
+        Ok(Backend {
+            client: Client::new(base_url)?,
+            description: format!("sqlite {}", database),
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    pub async fn events(&self, query: &GetEventsQuery) -> BackendResult<Vec<TimedEvent>> {
+        let response = self.client.events(query).await.map_err(failed("events"))?;
+        Ok(response.events)
+    }
+
+    pub async fn stop(&self, event_id: i64) -> BackendResult<StopEventResponse> {
+        self.client
+            .stop_event(event_id)
+            .await
+            .map_err(failed("stop"))
+    }
+
+    pub async fn continue_event(&self, event_id: i64) -> BackendResult<CreateTimedEventResponse> {
+        self.client
+            .continue_event(event_id)
+            .await
+            .map_err(failed("continue"))
+    }
+
+    /// Starts an event by task and tag names, creating whichever do not exist yet.
+    pub async fn start(
+        &self,
+        task: &str,
+        tags: &[String],
+    ) -> BackendResult<CreateTimedEventResponse> {
+        let tasks = self.client.tasks().await.map_err(failed("tasks"))?;
+        let task_id = match tasks
+            .tasks
+            .iter()
+            .find(|existing| existing.task.eq_ignore_ascii_case(task))
+        {
+            Some(existing) => existing.id,
+            None => {
+                let request = CreateTaskRequest {
+                    task: task.to_string(),
+                    estimate_seconds: None,
+                };
+                let created = self.client.create_task(&request).await;
+                created.map_err(failed("task"))?.id
+            }
+        };
+
+        let existing_tags = self.client.tags().await.map_err(failed("tags"))?;
+        let mut tag_ids = Vec::with_capacity(tags.len());
+        for name in tags {
+            match existing_tags
+                .tags
+                .iter()
+                .find(|existing| existing.name.eq_ignore_ascii_case(name))
+            {
+                Some(existing) => tag_ids.push(existing.id),
+                None => {
+                    let request = CreateTagRequest { name: name.clone() };
+                    let created = self.client.create_tag(&request).await;
+                    tag_ids.push(created.map_err(failed("tag"))?.id);
+                }
+            }
+        }
+
+        self.client
+            .start_event(&CreateTimedEventRequest { task_id, tag_ids })
+            .await
+            .map_err(failed("start"))
+    }
+}
+
+/// Describes a failed call for the status line.
+fn failed(what: &'static str) -> impl Fn(rustimenator_client::Error) -> String {
+    move |error| format!("{} failed: {}", what, error)
+}
@@ -0,0 +1,77 @@
+mod app;
+mod backend;
+mod ui;
+
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+
+use app::App;
+use backend::Backend;
+
+const TICK: Duration = Duration::from_millis(250);
+const REFRESH: Duration = Duration::from_secs(2);
+
+/// Terminal dashboard for running timers and today's totals.
+#[derive(Parser)]
+#[command(name = "rustimenator-tui", version)]
+struct Args {
+    /// Base URL of the Rustimenator server.
+    #[arg(
+        long,
+        env = "RUSTIMENATOR_URL",
+        default_value = "http://localhost:8080"
+    )]
+    server: String,
+
+    /// Bearer token for servers running with `auth.mode = "token"`.
+    #[arg(long, env = "RUSTIMENATOR_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// Work directly on this SQLite database file instead of talking to a server.
+    #[arg(long)]
+    database: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let backend = match &args.database {
+        Some(database) => Backend::local(database).await?,
+        None => Backend::http(&args.server, args.token.as_deref())?,
+    };
+
+    let mut app = App::new(backend);
+    app.refresh().await;
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut last_refresh = Instant::now();
+
+    while !app.should_quit {
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if event::poll(TICK)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key).await;
+        }
+
+        if last_refresh.elapsed() >= REFRESH {
+            app.refresh().await;
+            last_refresh = Instant::now();
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Cell, Paragraph, Row, Table},
+};
+
+use crate::app::{App, Focus, Mode, elapsed, format_duration, tag_names, task_name};
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [events, totals] =
+        Layout::vertical([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(body);
+    let [running, recent] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(events);
+    let [task_totals, tag_totals] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(totals);
+
+    draw_header(frame, header, app);
+    draw_running(frame, running, app);
+    draw_recent(frame, recent, app);
+    let (tasks, tags) = app.totals();
+    draw_totals(frame, task_totals, "Today by task", &tasks);
+    draw_totals(frame, tag_totals, "Today by tag", &tags);
+    draw_footer(frame, footer, app);
+}
+
+fn panel(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(title);
+    if focused {
+        block.border_style(Style::new().cyan())
+    } else {
+        block
+    }
+}
+
+fn highlight() -> Style {
+    Style::new().add_modifier(Modifier::REVERSED)
+}
+
+fn draw_header(frame: &mut Frame, area: Rect, app: &App) {
+    let line = Line::from(vec![
+        " rustimenator ".bold(),
+        format!("· {} ", app.backend.describe()).into(),
+        format!("· {} running", app.running.len()).into(),
+    ]);
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+fn draw_running(frame: &mut Frame, area: Rect, app: &mut App) {
+    let rows = app.running.iter().map(|event| {
+        Row::new(vec![
+            Cell::from(format_duration(elapsed(event))).green(),
+            Cell::from(task_name(event)),
+            Cell::from(tag_names(event)).dark_gray(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(9),
+            Constraint::Percentage(55),
+            Constraint::Percentage(45),
+        ],
+    )
+    .header(Row::new(vec!["Elapsed", "Task", "Tags"]).bold())
+    .block(panel("Running", app.focus == Focus::Running))
+    .row_highlight_style(highlight());
+    frame.render_stateful_widget(table, area, &mut app.running_state);
+}
+
+fn draw_recent(frame: &mut Frame, area: Rect, app: &mut App) {
+    let rows: Vec<Row> = app
+        .recent()
+        .into_iter()
+        .map(|event| {
+            let started = event
+                .created_at
+                .map(|at| {
+                    at.and_utc()
+                        .with_timezone(&chrono::Local)
+                        .format("%H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            Row::new(vec![
+                Cell::from(started),
+                Cell::from(format_duration(elapsed(event))),
+                Cell::from(task_name(event)),
+                Cell::from(tag_names(event)).dark_gray(),
+            ])
+        })
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Percentage(55),
+            Constraint::Percentage(45),
+        ],
+    )
+    .header(Row::new(vec!["Start", "Duration", "Task", "Tags"]).bold())
+    .block(panel("Today", app.focus == Focus::Recent))
+    .row_highlight_style(highlight());
+    frame.render_stateful_widget(table, area, &mut app.recent_state);
+}
+
+fn draw_totals(frame: &mut Frame, area: Rect, title: &str, totals: &[(String, i64)]) {
+    let rows = totals.iter().map(|(name, seconds)| {
+        Row::new(vec![
+            Cell::from(format_duration(*seconds)),
+            Cell::from(name.clone()),
+        ])
+    });
+    let table =
+        Table::new(rows, [Constraint::Length(9), Constraint::Min(0)]).block(panel(title, false));
+    frame.render_widget(table, area);
+}
+
+fn draw_footer(frame: &mut Frame, area: Rect, app: &App) {
+    let line = match &app.mode {
+        Mode::Input(input) => Line::from(vec![
+            " start: ".bold(),
+            input.clone().into(),
+            "▏".into(),
+            "  (task name #tag #tag, enter to start, esc to cancel)".dark_gray(),
+        ]),
+        Mode::Normal => Line::from(format!(" {}", app.status)),
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use rustimenator::ValidationErrors;
+use tokio_tungstenite::tungstenite;
+
+/// Why a [`Client`](crate::Client) call failed.
+#[derive(Debug)]
+pub enum Error {
+    /// `400`: a malformed request, or a task or tag id that does not exist.
+    BadRequest,
+    /// `401`: the server requires a token and none or a wrong one was sent.
+    Unauthorized,
+    /// `404`: the addressed tag, task or event does not exist, or the event is not running.
+    NotFound,
+    /// `409`: the name is taken, the event is still running, or the idempotency key was still
+    /// in use after every retry.
+    Conflict,
+    /// `422`: the request failed validation.
+    Validation(ValidationErrors),
+    /// Any other unsuccessful status, after retries when the call is retried.
+    Status(StatusCode),
+    /// The base URL or token given to the [`ClientBuilder`](crate::ClientBuilder) is unusable.
+    InvalidConfiguration(String),
+    /// The request could not be sent or the response could not be read.
+    Http(reqwest::Error),
+    WebSocket(Box<tungstenite::Error>),
+    /// The server sent something that is not what the API documents.
+    Decode(serde_json::Error),
+}
+
+impl Error {
+    /// The HTTP status behind the error, if the server answered.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::BadRequest => Some(StatusCode::BAD_REQUEST),
+            Error::Unauthorized => Some(StatusCode::UNAUTHORIZED),
+            Error::NotFound => Some(StatusCode::NOT_FOUND),
+            Error::Conflict => Some(StatusCode::CONFLICT),
+            Error::Validation(_) => Some(StatusCode::UNPROCESSABLE_ENTITY),
+            Error::Status(status) => Some(*status),
+            Error::Http(error) => error.status(),
+            Error::InvalidConfiguration(_) | Error::WebSocket(_) | Error::Decode(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::BadRequest => write!(f, "bad request"),
+            Error::Unauthorized => write!(f, "missing or invalid token"),
+            Error::NotFound => write!(f, "not found"),
+            Error::Conflict => write!(f, "conflict"),
+            Error::Status(status) => write!(f, "server responded with {status}"),
+            Error::Validation(errors) => {
+                write!(f, "validation failed:")?;
+                for error in &errors.errors {
+                    write!(f, " {} {};", error.field, error.message)?;
+                }
+                Ok(())
+            }
+            Error::InvalidConfiguration(message) => write!(f, "invalid configuration: {message}"),
+            Error::Http(error) => write!(f, "request failed: {error}"),
+            Error::WebSocket(error) => write!(f, "websocket error: {error}"),
+            Error::Decode(error) => write!(f, "unexpected response: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Http(error) => Some(error),
+            Error::WebSocket(error) => Some(error.as_ref()),
+            Error::Decode(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Http(error)
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(error: tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(error))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Decode(error)
+    }
+}
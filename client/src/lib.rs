@@ -0,0 +1,503 @@
+//! Typed async client for the Rustimenator HTTP API.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), rustimenator_client::Error> {
+//! use rustimenator_client::Client;
+//! use rustimenator::CreateTimedEventRequest;
+//!
+//! let client = Client::builder("http://localhost:8080").token("secret").build()?;
+//! let started = client
+//!     .start_event(&CreateTimedEventRequest { task_id: 1, tag_ids: vec![2] })
+//!     .await?;
+//! client.stop_event(started.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use reqwest::{
+    Method, RequestBuilder, Response, StatusCode,
+    header::{self, HeaderMap, HeaderValue},
+};
+use rustimenator::{
+    Budget, BudgetStatus, CreateBudgetRequest, CreateTagRequest, CreateTagResponse,
+    CreateTaskRequest, CreateTaskResponse, CreateTimedEventRequest, CreateTimedEventResponse,
+    EventCombinationsQuery, GetBudgetQuery, GetBudgetsQuery, GetBudgetsResponse,
+    GetEventCombinationsResponse, GetEventsQuery, GetEventsResponse, GetIdleGapsQuery,
+    GetIdleGapsResponse, GetOverlapsQuery, GetOverlapsResponse, GetTagsResponse, GetTasksResponse,
+    HealthResponse, HeartbeatResponse, IDEMPOTENCY_KEY, JoinEventsRequest, JoinEventsResponse,
+    ReadinessResponse, ResolveIdleGapRequest, ResolveIdleGapResponse, ResolveOverlapsRequest,
+    ResolveOverlapsResponse, SetTaskEstimateRequest, SplitEventRequest, SplitEventResponse,
+    StopEventResponse, Tag, Task, TaskEstimate, TimedEvent, UpdateTimedEventRequest,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+mod error;
+mod socket;
+mod stream;
+
+pub use error::Error;
+pub use socket::EventSocket;
+pub use stream::{EventStream, StreamEvent};
+
+/// Statuses worth another attempt: the server was overloaded, restarting or hit a database
+/// error, so the request did not take effect.
+const RETRY_STATUSES: [StatusCode; 5] = [
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+pub struct ClientBuilder {
+    base_url: String,
+    token: Option<String>,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl ClientBuilder {
+    /// Sends `Authorization: Bearer <token>` with every request, for servers in token mode.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Gives up on a request after `timeout`. Does not apply to the event stream.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How often a failed call is retried; 2 by default.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The wait before the first retry, doubled for each further one; 100ms by default.
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let base_url = self.base_url.trim_end_matches('/').to_string();
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(Error::InvalidConfiguration(format!(
+                "base URL `{base_url}` must start with http:// or https://"
+            )));
+        }
+
+        let authorization = self
+            .token
+            .map(|token| HeaderValue::from_str(&format!("Bearer {token}")))
+            .transpose()
+            .map_err(|_| Error::InvalidConfiguration("token is not a valid header value".into()))?;
+        let mut headers = HeaderMap::new();
+        if let Some(authorization) = &authorization {
+            headers.insert(header::AUTHORIZATION, authorization.clone());
+        }
+
+        let mut http = reqwest::Client::builder().default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            http = http.timeout(timeout);
+        }
+        Ok(Client {
+            http: http.build()?,
+            base_url,
+            authorization,
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+        })
+    }
+}
+
+/// A client for one Rustimenator server. Cloning is cheap and shares the connection pool.
+///
+/// Calls that fail to connect, time out or get a `429`, `500`, `502`, `503` or `504` are
+/// retried, after the server's `Retry-After` when it sends one. Each `POST` carries a fresh
+/// `Idempotency-Key`, so a retry after a lost response gets the stored response instead of
+/// repeating the change, and a retry that finds the first attempt still in progress (a `409`
+/// with `Retry-After`) waits and tries again. Reads and `PATCH` are idempotent. A `DELETE`
+/// retried after a timeout that gets `404` counts as done, as the first attempt may have
+/// deleted it.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    authorization: Option<HeaderValue>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl Client {
+    /// A client with the default settings for the server at `base_url`.
+    pub fn new(base_url: impl Into<String>) -> Result<Self, Error> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            token: None,
+            timeout: None,
+            retries: 2,
+            retry_delay: Duration::from_millis(100),
+        }
+    }
+
+    pub async fn create_tag(&self, request: &CreateTagRequest) -> Result<CreateTagResponse, Error> {
+        self.post("/tag", Some(request)).await
+    }
+
+    pub async fn tag(&self, id: i64) -> Result<Tag, Error> {
+        self.get(&format!("/tag/{id}")).await
+    }
+
+    pub async fn tags(&self) -> Result<GetTagsResponse, Error> {
+        self.get("/tags").await
+    }
+
+    pub async fn create_task(
+        &self,
+        request: &CreateTaskRequest,
+    ) -> Result<CreateTaskResponse, Error> {
+        self.post("/task", Some(request)).await
+    }
+
+    pub async fn task(&self, id: i64) -> Result<Task, Error> {
+        self.get(&format!("/task/{id}")).await
+    }
+
+    pub async fn tasks(&self) -> Result<GetTasksResponse, Error> {
+        self.get("/tasks").await
+    }
+
+    pub async fn task_estimate(&self, id: i64) -> Result<TaskEstimate, Error> {
+        self.get(&format!("/task/{id}/estimate")).await
+    }
+
+    pub async fn set_task_estimate(
+        &self,
+        id: i64,
+        request: &SetTaskEstimateRequest,
+    ) -> Result<Task, Error> {
+        let body = serde_json::to_vec(request)?;
+        let response = self
+            .send(Method::PUT, &format!("/task/{id}/estimate"), |request| {
+                json_body(request, &body)
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn create_budget(&self, request: &CreateBudgetRequest) -> Result<Budget, Error> {
+        self.post("/budgets", Some(request)).await
+    }
+
+    pub async fn budgets(&self, query: &GetBudgetsQuery) -> Result<GetBudgetsResponse, Error> {
+        let response = self
+            .send(Method::GET, "/budgets", |request| request.query(query))
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn budget(&self, id: i64, query: &GetBudgetQuery) -> Result<BudgetStatus, Error> {
+        let response = self
+            .send(Method::GET, &format!("/budgets/{id}"), |request| {
+                request.query(query)
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn delete_budget(&self, id: i64) -> Result<(), Error> {
+        self.delete(&format!("/budgets/{id}")).await
+    }
+
+    pub async fn start_event(
+        &self,
+        request: &CreateTimedEventRequest,
+    ) -> Result<CreateTimedEventResponse, Error> {
+        self.post("/events/start", Some(request)).await
+    }
+
+    pub async fn events(&self, query: &GetEventsQuery) -> Result<GetEventsResponse, Error> {
+        let response = self
+            .send(Method::GET, "/events", |request| request.query(query))
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn event(&self, id: i64) -> Result<TimedEvent, Error> {
+        self.get(&format!("/events/{id}")).await
+    }
+
+    pub async fn update_event(
+        &self,
+        id: i64,
+        request: &UpdateTimedEventRequest,
+    ) -> Result<TimedEvent, Error> {
+        let body = serde_json::to_vec(request)?;
+        let response = self
+            .send(Method::PATCH, &format!("/events/{id}"), |request| {
+                json_body(request, &body)
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn delete_event(&self, id: i64) -> Result<(), Error> {
+        self.delete(&format!("/events/{id}")).await
+    }
+
+    /// Starts a new event with the task and tags of a stopped one.
+    pub async fn continue_event(&self, id: i64) -> Result<CreateTimedEventResponse, Error> {
+        self.post(&format!("/events/{id}/continue"), None::<&()>)
+            .await
+    }
+
+    pub async fn split_event(
+        &self,
+        id: i64,
+        request: &SplitEventRequest,
+    ) -> Result<SplitEventResponse, Error> {
+        self.post(&format!("/events/{id}/split"), Some(request))
+            .await
+    }
+
+    pub async fn join_events(
+        &self,
+        request: &JoinEventsRequest,
+    ) -> Result<JoinEventsResponse, Error> {
+        self.post("/events/join", Some(request)).await
+    }
+
+    pub async fn stop_event(&self, id: i64) -> Result<StopEventResponse, Error> {
+        self.post(&format!("/events/stop/{id}"), None::<&()>).await
+    }
+
+    /// Pairs of events covering the same time.
+    pub async fn overlaps(&self, query: &GetOverlapsQuery) -> Result<GetOverlapsResponse, Error> {
+        let response = self
+            .send(Method::GET, "/events/overlaps", |request| {
+                request.query(query)
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn resolve_overlaps(
+        &self,
+        request: &ResolveOverlapsRequest,
+    ) -> Result<ResolveOverlapsResponse, Error> {
+        self.post("/events/overlaps/resolve", Some(request)).await
+    }
+
+    /// Reports that the user of a running event is active. Desktop clients send this
+    /// periodically while tracking.
+    pub async fn heartbeat(&self, id: i64) -> Result<HeartbeatResponse, Error> {
+        self.post(&format!("/events/{id}/heartbeat"), None::<&()>)
+            .await
+    }
+
+    pub async fn idle_gaps(&self, query: &GetIdleGapsQuery) -> Result<GetIdleGapsResponse, Error> {
+        let response = self
+            .send(Method::GET, "/idle-gaps", |request| request.query(query))
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    pub async fn resolve_idle_gap(
+        &self,
+        id: i64,
+        request: &ResolveIdleGapRequest,
+    ) -> Result<ResolveIdleGapResponse, Error> {
+        self.post(&format!("/idle-gaps/{id}/resolve"), Some(request))
+            .await
+    }
+
+    pub async fn event_combinations(
+        &self,
+        query: &EventCombinationsQuery,
+    ) -> Result<GetEventCombinationsResponse, Error> {
+        let response = self
+            .send(Method::GET, "/events/combinations", |request| {
+                request.query(query)
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Follows `GET /events/stream`.
+    pub async fn event_stream(&self) -> Result<EventStream, Error> {
+        let response = self
+            .send(Method::GET, "/events/stream", |request| request)
+            .await?;
+        Ok(EventStream::new(check(response).await?))
+    }
+
+    /// Opens a WebSocket to `GET /ws`.
+    pub async fn websocket(&self) -> Result<EventSocket, Error> {
+        let url = format!("ws{}/ws", self.base_url.trim_start_matches("http"));
+        let mut request = url.into_client_request()?;
+        if let Some(authorization) = &self.authorization {
+            request
+                .headers_mut()
+                .insert(header::AUTHORIZATION, authorization.clone());
+        }
+        let (stream, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(EventSocket::new(stream))
+    }
+
+    pub async fn health(&self) -> Result<HealthResponse, Error> {
+        self.get("/healthz").await
+    }
+
+    /// The readiness checks. A server that is not ready answers `503` with the failed checks,
+    /// which is returned rather than treated as an error.
+    pub async fn readiness(&self) -> Result<ReadinessResponse, Error> {
+        let response = self.send(Method::GET, "/readyz", |request| request).await?;
+        if response.status() == StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(response.json().await?);
+        }
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// The Prometheus metrics, in the text exposition format.
+    pub async fn metrics(&self) -> Result<String, Error> {
+        let response = self
+            .send(Method::GET, "/metrics", |request| request)
+            .await?;
+        Ok(check(response).await?.text().await?)
+    }
+
+    /// The OpenAPI document describing the server's routes.
+    pub async fn openapi(&self) -> Result<serde_json::Value, Error> {
+        self.get("/openapi.json").await
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let response = self.send(Method::GET, path, |request| request).await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Option<&impl Serialize>,
+    ) -> Result<T, Error> {
+        let body = body.map(serde_json::to_vec).transpose()?;
+        let response = self
+            .send(Method::POST, path, |request| match &body {
+                Some(body) => json_body(request, body),
+                None => request,
+            })
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Deletes `path`; a `404` after an attempt that timed out means that attempt deleted it.
+    async fn delete(&self, path: &str) -> Result<(), Error> {
+        let (response, timed_out) = self
+            .send_retrying(Method::DELETE, path, |request| request)
+            .await?;
+        if !(timed_out && response.status() == StatusCode::NOT_FOUND) {
+            check(response).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the request built by `build`, retrying as described on [`Client`].
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<Response, Error> {
+        let (response, _) = self.send_retrying(method, path, build).await?;
+        Ok(response)
+    }
+
+    /// Like [`Client::send`], also telling whether an earlier attempt timed out, so that it may
+    /// have taken effect without its response arriving.
+    async fn send_retrying(
+        &self,
+        method: Method,
+        path: &str,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> Result<(Response, bool), Error> {
+        let url = format!("{}{}", self.base_url, path);
+        let key = (method == Method::POST).then(|| uuid::Uuid::new_v4().to_string());
+        let mut attempt = 0;
+        let mut timed_out = false;
+        loop {
+            let mut request = build(self.http.request(method.clone(), &url));
+            if let Some(key) = &key {
+                request = request.header(IDEMPOTENCY_KEY, key);
+            }
+            let result = request.send().await;
+            let retry = match &result {
+                Ok(response) => {
+                    RETRY_STATUSES.contains(&response.status())
+                        || (key.is_some() && still_in_progress(response))
+                }
+                Err(error) => error.is_connect() || error.is_timeout(),
+            };
+            if !retry || attempt >= self.retries {
+                return Ok((result?, timed_out));
+            }
+            timed_out |= result.as_ref().is_err_and(reqwest::Error::is_timeout);
+            let delay = result
+                .as_ref()
+                .ok()
+                .and_then(retry_after)
+                .unwrap_or(self.retry_delay * 2u32.pow(attempt));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Whether the server answered that a request with the same idempotency key is still being
+/// processed, which it marks with `Retry-After` to tell it from other conflicts.
+fn still_in_progress(response: &Response) -> bool {
+    response.status() == StatusCode::CONFLICT
+        && response.headers().contains_key(header::RETRY_AFTER)
+}
+
+/// The wait a `Retry-After` header asks for, if it is given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    seconds.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn json_body(request: RequestBuilder, body: &[u8]) -> RequestBuilder {
+    request
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.to_vec())
+}
+
+/// Maps an unsuccessful status to the matching [`Error`].
+async fn check(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(match status {
+        StatusCode::BAD_REQUEST => Error::BadRequest,
+        StatusCode::UNAUTHORIZED => Error::Unauthorized,
+        StatusCode::NOT_FOUND => Error::NotFound,
+        StatusCode::CONFLICT => Error::Conflict,
+        StatusCode::UNPROCESSABLE_ENTITY => match response.json().await {
+            Ok(errors) => Error::Validation(errors),
+            Err(_) => Error::Status(status),
+        },
+        status => Error::Status(status),
+    })
+}
@@ -0,0 +1,42 @@
+use futures_util::{SinkExt, StreamExt};
+use rustimenator::{ClientMessage, ServerMessage};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, tungstenite::Message};
+
+use crate::Error;
+
+/// A connection to `GET /ws`, which starts and stops events and can subscribe to changes.
+pub struct EventSocket {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl EventSocket {
+    pub(crate) fn new(stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Self {
+        Self { stream }
+    }
+
+    pub async fn send(&mut self, message: &ClientMessage) -> Result<(), Error> {
+        let text = serde_json::to_string(message)?;
+        self.stream.send(Message::text(text)).await?;
+        Ok(())
+    }
+
+    /// The next message from the server, or `None` once the connection is closed.
+    pub async fn next(&mut self) -> Option<Result<ServerMessage, Error>> {
+        loop {
+            match self.stream.next().await? {
+                Ok(Message::Text(text)) => {
+                    return Some(serde_json::from_str(&text).map_err(Error::from));
+                }
+                Ok(Message::Close(_)) => return None,
+                Ok(_) => continue,
+                Err(error) => return Some(Err(error.into())),
+            }
+        }
+    }
+
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
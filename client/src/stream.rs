@@ -0,0 +1,83 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{Stream, StreamExt, stream::BoxStream};
+use reqwest::Response;
+use rustimenator::{EventChange, EventNotification, TimedEvent};
+
+use crate::Error;
+
+/// A message from `GET /events/stream`.
+#[derive(Clone)]
+pub enum StreamEvent {
+    Changed(EventNotification),
+    /// Notifications were dropped because the client fell behind; refetch the events.
+    Lagged,
+}
+
+/// The server-sent events of `GET /events/stream`. Ends when the server closes the stream,
+/// for example on shutdown.
+pub struct EventStream {
+    inner: BoxStream<'static, Result<StreamEvent, Error>>,
+}
+
+impl EventStream {
+    pub(crate) fn new(response: Response) -> Self {
+        let state = (response.bytes_stream(), Vec::new());
+        let inner = futures_util::stream::unfold(state, |(mut bytes, mut buffer)| async move {
+            loop {
+                if let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                    let block: Vec<u8> = buffer.drain(..end + 2).collect();
+                    match parse_block(&String::from_utf8_lossy(&block)) {
+                        Some(event) => return Some((event, (bytes, buffer))),
+                        None => continue,
+                    }
+                }
+                match bytes.next().await? {
+                    Ok(chunk) => buffer.extend_from_slice(&chunk),
+                    Err(error) => return Some((Err(error.into()), (bytes, buffer))),
+                }
+            }
+        });
+        Self {
+            inner: inner.boxed(),
+        }
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<StreamEvent, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+/// Parses one server-sent event. Keep-alive comments and unknown event names yield `None`.
+fn parse_block(block: &str) -> Option<Result<StreamEvent, Error>> {
+    let mut name = None;
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = Some(value.trim_start());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+
+    let change = match name? {
+        "started" => EventChange::Started,
+        "stopped" => EventChange::Stopped,
+        "edited" => EventChange::Edited,
+        "deleted" => EventChange::Deleted,
+        "lagged" => return Some(Ok(StreamEvent::Lagged)),
+        _ => return None,
+    };
+    Some(
+        serde_json::from_str::<TimedEvent>(&data.join("\n"))
+            .map(|event| StreamEvent::Changed(EventNotification { change, event }))
+            .map_err(Error::from),
+    )
+}
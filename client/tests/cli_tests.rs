@@ -0,0 +1,112 @@
+use rustimenator::{
+    CreateTimedEventResponse, GetEventsQuery, StopEventResponse, Tag, Task, TimedEvent, create_app,
+    create_database_pool,
+};
+use rustimenator_client::Client;
+use serde::de::DeserializeOwned;
+use tokio::process::Command;
+
+/// Serves a fresh in-memory server on an ephemeral local port and returns its base URL.
+async fn serve() -> String {
+    let app = create_app(create_database_pool(":memory:").await.unwrap());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{address}")
+}
+
+async fn run_cli(server: &str, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_rustimenator-cli"))
+        .arg("--server")
+        .arg(server)
+        .args(args)
+        .output()
+        .await
+        .unwrap()
+}
+
+async fn run_cli_json<T: DeserializeOwned>(server: &str, args: &[&str]) -> T {
+    let mut args = args.to_vec();
+    args.push("--json");
+    let output = run_cli(server, &args).await;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[tokio::test]
+async fn test_cli_start_status_stop() {
+    let server = serve().await;
+
+    let output = run_cli(&server, &["start", "Write docs", "--tag", "docs"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown task 'Write docs'"));
+
+    let started: CreateTimedEventResponse = run_cli_json(
+        &server,
+        &[
+            "start",
+            "Write docs",
+            "--tag",
+            "docs",
+            "-t",
+            "rust",
+            "--create",
+        ],
+    )
+    .await;
+    assert_eq!(started.message, "Event started successfully");
+
+    let tasks: Vec<Task> = run_cli_json(&server, &["tasks"]).await;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].task, "Write docs");
+    let tags: Vec<Tag> = run_cli_json(&server, &["tags"]).await;
+    assert_eq!(tags.len(), 2);
+
+    let running: Vec<TimedEvent> = run_cli_json(&server, &["status"]).await;
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].id, started.id);
+    assert_eq!(running[0].tags.len(), 2);
+
+    let again: CreateTimedEventResponse =
+        run_cli_json(&server, &["start", "Write docs", "--tag", "docs"]).await;
+    assert_ne!(again.id, started.id);
+
+    let stopped: Vec<StopEventResponse> = run_cli_json(&server, &["stop"]).await;
+    assert_eq!(stopped.len(), 2);
+
+    let client = Client::new(&server).unwrap();
+    let events = client.events(&GetEventsQuery::default()).await.unwrap();
+    assert_eq!(events.count, 0);
+
+    let log: Vec<TimedEvent> = run_cli_json(&server, &["log"]).await;
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|event| event.stopped_at.is_some()));
+}
+
+#[tokio::test]
+async fn test_cli_report_and_human_output() {
+    let server = serve().await;
+
+    let output = run_cli(&server, &["start", "Review", "-t", "team", "--create"]).await;
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Started event 1 for Review"));
+
+    let output = run_cli(&server, &["status"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Review [team]"));
+
+    let output = run_cli(&server, &["stop", "1"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Stopped event 1"));
+
+    let report: serde_json::Value = run_cli_json(&server, &["report", "--days", "1"]).await;
+    assert_eq!(report["tasks"][0]["name"], "Review");
+    assert_eq!(report["tags"][0]["name"], "team");
+
+    let output = run_cli(&server, &["stop", "1"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("404"));
+}
@@ -0,0 +1,432 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    extract::Request,
+    http::{Method, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use rustimenator::{
+    AppState, AuthConfig, AuthMode, BudgetPeriod, ClientMessage, CreateBudgetRequest,
+    CreateTagRequest, CreateTaskRequest, CreateTimedEventRequest, EventChange,
+    EventCombinationsQuery, EventStatus, GetBudgetQuery, GetBudgetsQuery, GetEventsQuery,
+    GetIdleGapsQuery, GetOverlapsQuery, IdleResolution, JoinEventsRequest, OverlapAction,
+    ResolveIdleGapRequest, ResolveOverlapsRequest, ServerMessage, SetTaskEstimateRequest,
+    SplitEventRequest, TimerMode, UpdateTimedEventRequest, create_app_with_state,
+    create_database_pool,
+};
+use rustimenator_client::{Client, Error, StreamEvent};
+
+async fn state() -> AppState {
+    AppState::new(create_database_pool(":memory:").await.unwrap())
+}
+
+/// Serves `app` on an ephemeral local port and returns its base URL.
+async fn serve(app: Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{address}")
+}
+
+fn client(base_url: &str) -> Client {
+    Client::builder(base_url)
+        .retry_delay(Duration::from_millis(1))
+        .build()
+        .unwrap()
+}
+
+fn tag(name: &str) -> CreateTagRequest {
+    CreateTagRequest {
+        name: name.to_string(),
+    }
+}
+
+fn task(task: &str) -> CreateTaskRequest {
+    CreateTaskRequest {
+        task: task.to_string(),
+        estimate_seconds: None,
+    }
+}
+
+#[tokio::test]
+async fn test_client_covers_every_route() {
+    let state = state().await.with_timer_mode(TimerMode::Single);
+    let client = client(&serve(create_app_with_state(state)).await);
+
+    let work = client.create_tag(&tag("work")).await.unwrap();
+    assert_eq!(client.tag(work.id).await.unwrap().name, "work");
+    assert_eq!(client.tags().await.unwrap().count, 1);
+    let review = client.create_task(&task("review")).await.unwrap();
+    assert_eq!(client.task(review.id).await.unwrap().task, "review");
+    assert_eq!(client.tasks().await.unwrap().count, 1);
+    let estimated = client
+        .set_task_estimate(
+            review.id,
+            &SetTaskEstimateRequest {
+                estimate_seconds: Some(3600),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(estimated.estimate_seconds, Some(3600));
+    let estimate = client.task_estimate(review.id).await.unwrap();
+    assert_eq!(estimate.remaining_seconds, Some(3600));
+    let budget = client
+        .create_budget(&CreateBudgetRequest {
+            tag_id: work.id,
+            period: BudgetPeriod::Week,
+            seconds: 7200,
+        })
+        .await
+        .unwrap();
+    let status = client
+        .budget(budget.id, &GetBudgetQuery::default())
+        .await
+        .unwrap();
+    assert!(!status.over_budget);
+    let budgets = client.budgets(&GetBudgetsQuery::default()).await.unwrap();
+    assert_eq!(budgets.count, 1);
+    client.delete_budget(budget.id).await.unwrap();
+
+    let mut stream = client.event_stream().await.unwrap();
+    let first = client
+        .start_event(&CreateTimedEventRequest {
+            task_id: review.id,
+            tag_ids: vec![work.id],
+        })
+        .await
+        .unwrap();
+    match stream.next().await.unwrap().unwrap() {
+        StreamEvent::Changed(notification) => {
+            assert_eq!(notification.change, EventChange::Started);
+            assert_eq!(notification.event.id, first.id);
+            assert_eq!(notification.event.tags[0].name, "work");
+        }
+        StreamEvent::Lagged => panic!("unexpected lag"),
+    }
+
+    let event = client.event(first.id).await.unwrap();
+    assert_eq!(event.task.unwrap().id, review.id);
+    let running = client.events(&GetEventsQuery::default()).await.unwrap();
+    assert_eq!(running.count, 1);
+
+    let stopped = client.stop_event(first.id).await.unwrap();
+    assert_eq!(stopped.id, first.id);
+    let continued = client.continue_event(first.id).await.unwrap();
+    let updated = client
+        .update_event(
+            continued.id,
+            &UpdateTimedEventRequest {
+                tag_ids: Some(vec![]),
+                ..UpdateTimedEventRequest::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(updated.tags.is_empty());
+
+    let hour = Duration::from_secs(3600);
+    let now = event.created_at.unwrap();
+    client
+        .update_event(
+            first.id,
+            &UpdateTimedEventRequest {
+                created_at: Some(now - 2 * hour),
+                stopped_at: Some(now - hour),
+                ..UpdateTimedEventRequest::default()
+            },
+        )
+        .await
+        .unwrap();
+    let split = client
+        .split_event(
+            first.id,
+            &SplitEventRequest {
+                at: now - hour - hour / 2,
+                task_id: None,
+                tag_ids: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(split.second.tags[0].id, work.id);
+    let joined = client
+        .join_events(&JoinEventsRequest {
+            event_ids: vec![first.id, split.second.id],
+        })
+        .await
+        .unwrap();
+    assert_eq!(joined.event.stopped_at, Some(now - hour));
+    assert_eq!(joined.deleted_event_ids, [split.second.id]);
+    let overlaps = client.overlaps(&GetOverlapsQuery::default()).await.unwrap();
+    assert_eq!(overlaps.count, 0);
+    let resolved = client
+        .resolve_overlaps(&ResolveOverlapsRequest {
+            action: OverlapAction::Trim,
+            tag_id: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(resolved.count, 0);
+    let heartbeat = client.heartbeat(continued.id).await.unwrap();
+    assert_eq!(heartbeat.event_id, continued.id);
+    assert!(heartbeat.idle_gap.is_none());
+    let idle_gaps = client
+        .idle_gaps(&GetIdleGapsQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(idle_gaps.count, 0);
+
+    let combinations = client
+        .event_combinations(&EventCombinationsQuery {
+            limit: Some(5),
+            days: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(combinations.count, 2);
+
+    client.delete_event(continued.id).await.unwrap();
+    let all = client
+        .events(&GetEventsQuery {
+            status: Some(EventStatus::All),
+            ..GetEventsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(all.count, 1);
+
+    let mut socket = client.websocket().await.unwrap();
+    socket
+        .send(&ClientMessage::Start {
+            request_id: Some("start".to_string()),
+            task_id: review.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    match socket.next().await.unwrap().unwrap() {
+        ServerMessage::Ack {
+            request_id, status, ..
+        } => {
+            assert_eq!(request_id.as_deref(), Some("start"));
+            assert_eq!(status, 201);
+        }
+        message => panic!("unexpected message {message:?}"),
+    }
+    socket.close().await.unwrap();
+
+    assert_eq!(client.health().await.unwrap().status, "ok");
+    assert_eq!(client.readiness().await.unwrap().status, "ready");
+    assert!(
+        client
+            .metrics()
+            .await
+            .unwrap()
+            .contains("rustimenator_running_events 1")
+    );
+    assert!(client.openapi().await.unwrap()["paths"]["/events/start"].is_object());
+}
+
+#[tokio::test]
+async fn test_client_maps_statuses_to_errors() {
+    let state = state().await.with_auth(AuthConfig {
+        mode: AuthMode::Token,
+        tokens: vec!["secret".to_string()],
+    });
+    let base_url = serve(create_app_with_state(state)).await;
+
+    assert!(matches!(
+        client(&base_url).tags().await,
+        Err(Error::Unauthorized)
+    ));
+    let client = Client::builder(&base_url).token("secret").build().unwrap();
+
+    client.create_tag(&tag("work")).await.unwrap();
+    assert!(matches!(
+        client.create_tag(&tag("work")).await,
+        Err(Error::Conflict)
+    ));
+    match client.create_tag(&tag("")).await {
+        Err(Error::Validation(errors)) => assert_eq!(errors.errors[0].field, "name"),
+        other => panic!("expected a validation error, got {:?}", other.err()),
+    }
+    assert!(matches!(client.tag(99).await, Err(Error::NotFound)));
+    assert!(matches!(client.stop_event(99).await, Err(Error::NotFound)));
+    assert!(matches!(client.heartbeat(99).await, Err(Error::NotFound)));
+    assert!(matches!(
+        client
+            .resolve_idle_gap(
+                99,
+                &ResolveIdleGapRequest {
+                    resolution: IdleResolution::Keep
+                }
+            )
+            .await,
+        Err(Error::NotFound)
+    ));
+
+    let task = client.create_task(&task("review")).await.unwrap();
+    let Err(error) = client
+        .start_event(&CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![99],
+        })
+        .await
+    else {
+        panic!("an unknown tag was accepted");
+    };
+    assert!(matches!(error, Error::BadRequest));
+    assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+
+    assert!(matches!(
+        Client::new("localhost:8080"),
+        Err(Error::InvalidConfiguration(_))
+    ));
+}
+
+/// Counts requests to `POST /events/start` and `GET /tags`, and fails the first of each with
+/// `503` — the start only after the server has handled it, as if the response was lost.
+async fn flaky(requests: Arc<AtomicUsize>, request: Request, next: Next) -> Response {
+    let route = (request.method().clone(), request.uri().path().to_string());
+    let attempt = requests.fetch_add(1, Ordering::SeqCst);
+    match route {
+        (Method::POST, path) if path == "/events/start" => {
+            let response = next.run(request).await;
+            if attempt == 0 {
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+            response
+        }
+        (Method::GET, path) if path == "/tags" && attempt == 0 => {
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+        _ => next.run(request).await,
+    }
+}
+
+#[tokio::test]
+async fn test_client_retries_idempotent_calls() {
+    let state = state().await;
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let app = create_app_with_state(state).layer(middleware::from_fn(move |request, next| {
+        flaky(counter.clone(), request, next)
+    }));
+    let base_url = serve(app).await;
+    let client = client(&base_url);
+
+    // The retry of the lost start carries the same key, so the server replays it.
+    let task = client.create_task(&task("review")).await.unwrap();
+    requests.store(0, Ordering::SeqCst);
+    let started = client
+        .start_event(&CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    let running = client.events(&GetEventsQuery::default()).await.unwrap();
+    assert_eq!(running.count, 1);
+    assert_eq!(running.events[0].id, started.id);
+
+    requests.store(0, Ordering::SeqCst);
+    assert_eq!(client.tags().await.unwrap().count, 0);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Client errors are not retried.
+    requests.store(1, Ordering::SeqCst);
+    assert!(matches!(client.tag(99).await, Err(Error::NotFound)));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Without retries the first failure is returned.
+    let impatient = Client::builder(&base_url).retries(0).build().unwrap();
+    requests.store(0, Ordering::SeqCst);
+    assert!(matches!(
+        impatient.tags().await,
+        Err(Error::Status(StatusCode::SERVICE_UNAVAILABLE))
+    ));
+}
+
+/// Counts requests to `POST /events/start` and `DELETE /events/{id}`. The first start is
+/// handled but answered with `504`, and the second is told the first is still in progress. The
+/// first delete is handled but answered too late for the client's timeout.
+async fn lost(requests: Arc<AtomicUsize>, request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if method == Method::POST && path == "/events/start" {
+        match requests.fetch_add(1, Ordering::SeqCst) {
+            0 => {
+                next.run(request).await;
+                return StatusCode::GATEWAY_TIMEOUT.into_response();
+            }
+            1 => return (StatusCode::CONFLICT, [(header::RETRY_AFTER, "0")]).into_response(),
+            _ => {}
+        }
+    } else if method == Method::DELETE && requests.fetch_add(1, Ordering::SeqCst) == 0 {
+        let response = next.run(request).await;
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        return response;
+    }
+    next.run(request).await
+}
+
+#[tokio::test]
+async fn test_client_retries_after_lost_responses() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let app =
+        create_app_with_state(state().await).layer(middleware::from_fn(move |request, next| {
+            lost(counter.clone(), request, next)
+        }));
+    let base_url = serve(app).await;
+    let client = Client::builder(&base_url)
+        .timeout(Duration::from_millis(200))
+        .retry_delay(Duration::from_millis(1))
+        .build()
+        .unwrap();
+
+    // A retry that finds the key still in progress waits and gets the stored response.
+    let review = client.create_task(&task("review")).await.unwrap();
+    let started = client
+        .start_event(&CreateTimedEventRequest {
+            task_id: review.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+    let running = client.events(&GetEventsQuery::default()).await.unwrap();
+    assert_eq!(running.count, 1);
+    assert_eq!(running.events[0].id, started.id);
+
+    // A conflict without `Retry-After` is not retried.
+    assert!(matches!(
+        client.create_task(&task("review")).await,
+        Err(Error::Conflict)
+    ));
+
+    // The first delete took effect, so the `404` of its retry is not an error.
+    client.stop_event(started.id).await.unwrap();
+    requests.store(0, Ordering::SeqCst);
+    client.delete_event(started.id).await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    assert!(matches!(
+        client.event(started.id).await,
+        Err(Error::NotFound)
+    ));
+    assert!(matches!(
+        client.delete_event(started.id).await,
+        Err(Error::NotFound)
+    ));
+}
@@ -50,7 +50,7 @@
           preBuild = ''
             export DATABASE_URL="sqlite:./temp.db"
             ${pkgs.sqlx-cli}/bin/sqlx database create
-            ${pkgs.sqlx-cli}/bin/sqlx migrate run
+            ${pkgs.sqlx-cli}/bin/sqlx migrate run --source migrations/sqlite
           '';
 
           postInstall = ''
@@ -90,7 +90,7 @@
           if [ ! -f "/data/rustimenator.db" ]; then
             sqlx database create
           fi
-          sqlx migrate run --source /share/rustimenator/migrations
+          sqlx migrate run --source /share/rustimenator/migrations/sqlite
 
           # Start the app
           exec rustimenator
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS tasks (
+    id BIGSERIAL PRIMARY KEY,
+    task TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT (now() AT TIME ZONE 'UTC')
+);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    task_id BIGINT NOT NULL REFERENCES tasks(id),
+    created_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'UTC'),
+    stopped_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS event_tags (
+    event_id BIGINT NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    tag_id BIGINT NOT NULL REFERENCES tags(id),
+    PRIMARY KEY (event_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_stopped_at ON events(stopped_at);
+CREATE INDEX IF NOT EXISTS idx_events_task_id ON events(task_id);
+CREATE INDEX IF NOT EXISTS idx_event_tags_event_id ON event_tags(event_id);
+CREATE INDEX IF NOT EXISTS idx_event_tags_tag_id ON event_tags(tag_id);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS event_checkpoints (
+    event_id BIGINT PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'UTC')
+);
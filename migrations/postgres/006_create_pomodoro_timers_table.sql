@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS pomodoro_timers (
+    event_id BIGINT PRIMARY KEY REFERENCES events(id) ON DELETE CASCADE,
+    phase TEXT NOT NULL,
+    ends_at TIMESTAMP NOT NULL,
+    state TEXT NOT NULL DEFAULT 'pending'
+);
@@ -0,0 +1 @@
+ALTER TABLE events ADD COLUMN auto_stop_reason TEXT;
@@ -0,0 +1,11 @@
+ALTER TABLE events ADD COLUMN last_heartbeat_at TIMESTAMP;
+
+CREATE TABLE IF NOT EXISTS idle_gaps (
+    id BIGSERIAL PRIMARY KEY,
+    event_id BIGINT NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP,
+    resolution TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_idle_gaps_event_id ON idle_gaps(event_id);
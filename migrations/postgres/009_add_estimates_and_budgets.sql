@@ -0,0 +1,10 @@
+ALTER TABLE tasks ADD COLUMN estimate_seconds BIGINT;
+
+CREATE TABLE IF NOT EXISTS budgets (
+    id BIGSERIAL PRIMARY KEY,
+    tag_id BIGINT NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    period TEXT NOT NULL,
+    seconds BIGINT NOT NULL,
+    created_at TIMESTAMP DEFAULT (now() AT TIME ZONE 'UTC'),
+    UNIQUE (tag_id, period)
+);
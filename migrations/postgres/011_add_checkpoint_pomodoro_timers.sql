@@ -0,0 +1,2 @@
+ALTER TABLE event_checkpoints ADD COLUMN pomodoro_phase TEXT;
+ALTER TABLE event_checkpoints ADD COLUMN pomodoro_seconds BIGINT;
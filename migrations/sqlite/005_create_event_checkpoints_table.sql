@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS event_checkpoints (
+    event_id INTEGER PRIMARY KEY,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+);
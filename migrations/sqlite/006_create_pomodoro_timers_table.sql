@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS pomodoro_timers (
+    event_id INTEGER PRIMARY KEY,
+    phase TEXT NOT NULL,
+    ends_at DATETIME NOT NULL,
+    state TEXT NOT NULL DEFAULT 'pending',
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+);
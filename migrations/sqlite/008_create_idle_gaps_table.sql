@@ -0,0 +1,12 @@
+ALTER TABLE events ADD COLUMN last_heartbeat_at DATETIME;
+
+CREATE TABLE IF NOT EXISTS idle_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    event_id INTEGER NOT NULL,
+    started_at DATETIME NOT NULL,
+    ended_at DATETIME,
+    resolution TEXT,
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_idle_gaps_event_id ON idle_gaps(event_id);
@@ -0,0 +1,11 @@
+ALTER TABLE tasks ADD COLUMN estimate_seconds INTEGER;
+
+CREATE TABLE IF NOT EXISTS budgets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tag_id INTEGER NOT NULL,
+    period TEXT NOT NULL,
+    seconds INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (tag_id, period),
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
@@ -0,0 +1,60 @@
+use axum::{
+    extract::{Query, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::AppState;
+
+/// The one path that also takes the token as a `token` query parameter, as browsers cannot send
+/// headers with an `EventSource`.
+const QUERY_TOKEN_PATH: &str = "/events/stream";
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: String,
+}
+
+/// Rejects requests without a configured bearer token when `auth.mode` is `token`.
+pub async fn require_token(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let header_token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let token = header_token.or_else(|| {
+        (request.uri().path() == QUERY_TOKEN_PATH)
+            .then(|| Query::<TokenQuery>::try_from_uri(request.uri()).ok())
+            .flatten()
+            .map(|Query(query)| query.token)
+    });
+    match token {
+        Some(token) if is_configured(&state.auth.tokens, &token) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response(),
+    }
+}
+
+/// Whether `token` is one of `tokens`, taking the same time wherever and whether it matches:
+/// digests of equal length are compared in constant time, and every token is checked.
+fn is_configured(tokens: &[String], token: &str) -> bool {
+    let digest = Sha256::digest(token.as_bytes());
+    tokens
+        .iter()
+        .fold(subtle::Choice::from(0), |found, configured| {
+            found | Sha256::digest(configured.as_bytes()).ct_eq(&digest)
+        })
+        .into()
+}
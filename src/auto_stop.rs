@@ -0,0 +1,150 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::TimedEvent;
+
+/// Limits after which running events are stopped by the server, so forgotten timers do not
+/// run overnight. The top-level limits apply to every event; each rule adds limits for the
+/// events of one task or tag.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoStopConfig {
+    /// How often running events are checked, in seconds.
+    pub interval_seconds: u64,
+    pub max_duration_seconds: Option<u64>,
+    pub daily_cutoff: Option<DailyCutoff>,
+    pub rules: Vec<AutoStopRule>,
+}
+
+impl Default for AutoStopConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 60,
+            max_duration_seconds: None,
+            daily_cutoff: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Limits for the events of `task`, of `tag`, or, with both set, of `task` tagged `tag`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoStopRule {
+    pub task: Option<String>,
+    pub tag: Option<String>,
+    pub max_duration_seconds: Option<u64>,
+    pub daily_cutoff: Option<DailyCutoff>,
+}
+
+impl AutoStopRule {
+    fn matches(&self, event: &TimedEvent) -> bool {
+        let task = self
+            .task
+            .as_ref()
+            .is_none_or(|name| event.task.as_ref().is_some_and(|task| &task.task == name));
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|name| event.tags.iter().any(|tag| &tag.name == name));
+        task && tag
+    }
+}
+
+/// A time of day, in UTC, written `HH:MM`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DailyCutoff(pub NaiveTime);
+
+impl DailyCutoff {
+    /// The first cutoff after `started`, or `None` when it does not fit in the calendar.
+    fn after(&self, started: NaiveDateTime) -> Option<NaiveDateTime> {
+        let same_day = started.date().and_time(self.0);
+        if same_day > started {
+            Some(same_day)
+        } else {
+            same_day.checked_add_signed(Duration::days(1))
+        }
+    }
+}
+
+impl FromStr for DailyCutoff {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .map(DailyCutoff)
+            .map_err(|_| format!("invalid daily cutoff `{value}`, expected HH:MM"))
+    }
+}
+
+impl TryFrom<String> for DailyCutoff {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<DailyCutoff> for String {
+    fn from(cutoff: DailyCutoff) -> Self {
+        cutoff.to_string()
+    }
+}
+
+impl fmt::Display for DailyCutoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+/// Which limit stopped an event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum AutoStopReason {
+    MaxDuration,
+    DailyCutoff,
+}
+
+impl AutoStopConfig {
+    /// Whether any limit is configured, so that the background job has something to do.
+    pub fn is_enabled(&self) -> bool {
+        let limits =
+            |max: &Option<u64>, cutoff: &Option<DailyCutoff>| max.is_some() || cutoff.is_some();
+        limits(&self.max_duration_seconds, &self.daily_cutoff)
+            || self
+                .rules
+                .iter()
+                .any(|rule| limits(&rule.max_duration_seconds, &rule.daily_cutoff))
+    }
+
+    /// When the limits applying to `event` stop it, and which one does, if any applies. The
+    /// earliest limit wins; limits that would fall past the end of the calendar are ignored.
+    pub fn deadline(&self, event: &TimedEvent) -> Option<(NaiveDateTime, AutoStopReason)> {
+        let started = event.created_at?;
+        let global = (self.max_duration_seconds, self.daily_cutoff);
+        let rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(event))
+            .map(|rule| (rule.max_duration_seconds, rule.daily_cutoff));
+
+        std::iter::once(global)
+            .chain(rules)
+            .flat_map(|(max_duration, cutoff)| {
+                let max_duration = max_duration
+                    .and_then(|seconds| Duration::try_seconds(i64::try_from(seconds).ok()?))
+                    .and_then(|duration| started.checked_add_signed(duration))
+                    .map(|deadline| (deadline, AutoStopReason::MaxDuration));
+                let cutoff = cutoff
+                    .and_then(|cutoff| cutoff.after(started))
+                    .map(|deadline| (deadline, AutoStopReason::DailyCutoff));
+                max_duration.into_iter().chain(cutoff)
+            })
+            .min_by_key(|(deadline, _)| *deadline)
+    }
+}
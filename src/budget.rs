@@ -0,0 +1,174 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// The stretch of time a budget applies to, in UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Day,
+    /// Monday to Sunday.
+    Week,
+    Month,
+    /// All time, for budgets of a whole project.
+    Total,
+}
+
+impl BudgetPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetPeriod::Day => "day",
+            BudgetPeriod::Week => "week",
+            BudgetPeriod::Month => "month",
+            BudgetPeriod::Total => "total",
+        }
+    }
+
+    /// The start and end of the period containing `at`; both are empty for
+    /// [`BudgetPeriod::Total`]. `None` when the period does not fit in the calendar.
+    pub fn bounds(
+        self,
+        at: NaiveDateTime,
+    ) -> Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> {
+        let date = at.date();
+        let (start, end) = match self {
+            BudgetPeriod::Day => (date, date.checked_add_days(Days::new(1))?),
+            BudgetPeriod::Week => {
+                let monday = Days::new(date.weekday().num_days_from_monday().into());
+                let start = date.checked_sub_days(monday)?;
+                (start, start.checked_add_days(Days::new(7))?)
+            }
+            BudgetPeriod::Month => {
+                let start = NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?;
+                (start, start.checked_add_months(Months::new(1))?)
+            }
+            BudgetPeriod::Total => return Some((None, None)),
+        };
+        Some((
+            Some(start.and_time(chrono::NaiveTime::MIN)),
+            Some(end.and_time(chrono::NaiveTime::MIN)),
+        ))
+    }
+}
+
+/// Time planned for the events carrying a tag in each period. Tags double as projects, so
+/// this is also how a project gets its budget.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, sqlx::FromRow)]
+pub struct Budget {
+    pub id: i64,
+    pub tag_id: i64,
+    pub period: BudgetPeriod,
+    pub seconds: i64,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CreateBudgetRequest {
+    pub tag_id: i64,
+    pub period: BudgetPeriod,
+    pub seconds: i64,
+}
+
+/// A budget with the time tracked against it in one period.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BudgetStatus {
+    pub budget: Budget,
+    /// Empty for [`BudgetPeriod::Total`].
+    pub period_start: Option<NaiveDateTime>,
+    pub period_end: Option<NaiveDateTime>,
+    /// Time of the tag's events within the period; running events count until now.
+    pub consumed_seconds: i64,
+    /// Zero once the budget is used up.
+    pub remaining_seconds: i64,
+    pub over_budget: bool,
+}
+
+impl BudgetStatus {
+    pub fn new(
+        budget: Budget,
+        (period_start, period_end): (Option<NaiveDateTime>, Option<NaiveDateTime>),
+        consumed_seconds: i64,
+    ) -> Self {
+        Self {
+            period_start,
+            period_end,
+            consumed_seconds,
+            remaining_seconds: (budget.seconds - consumed_seconds).max(0),
+            over_budget: consumed_seconds > budget.seconds,
+            budget,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetBudgetQuery {
+    /// Report the periods containing this time instead of the current ones.
+    pub at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetBudgetsQuery {
+    /// Report the periods containing this time instead of the current ones.
+    pub at: Option<NaiveDateTime>,
+    /// Only budgets that are (`true`) or are not (`false`) exceeded.
+    pub over_budget: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetBudgetsResponse {
+    pub budgets: Vec<BudgetStatus>,
+    pub count: usize,
+}
+
+/// Sets or, when empty, clears a task's estimate.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SetTaskEstimateRequest {
+    #[serde(default)]
+    pub estimate_seconds: Option<i64>,
+}
+
+/// A task's estimate against the time tracked on it so far.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct TaskEstimate {
+    pub task_id: i64,
+    pub estimate_seconds: Option<i64>,
+    /// Time of all the task's events; running events count until now.
+    pub consumed_seconds: i64,
+    /// Zero once the estimate is used up; empty without an estimate.
+    pub remaining_seconds: Option<i64>,
+    pub over_estimate: bool,
+}
+
+impl TaskEstimate {
+    pub fn new(task_id: i64, estimate_seconds: Option<i64>, consumed_seconds: i64) -> Self {
+        Self {
+            task_id,
+            estimate_seconds,
+            consumed_seconds,
+            remaining_seconds: estimate_seconds
+                .map(|estimate| (estimate - consumed_seconds).max(0)),
+            over_estimate: estimate_seconds.is_some_and(|estimate| consumed_seconds > estimate),
+        }
+    }
+}
+
+/// Seconds of `spans` within `from`..`to`, with running spans ending at `now`. Events that
+/// overlap are each counted in full.
+pub(crate) fn tracked_seconds(
+    spans: &[(NaiveDateTime, Option<NaiveDateTime>)],
+    (from, to): (Option<NaiveDateTime>, Option<NaiveDateTime>),
+    now: NaiveDateTime,
+) -> i64 {
+    spans
+        .iter()
+        .map(|&(created_at, stopped_at)| {
+            let start = from.map_or(created_at, |from| created_at.max(from));
+            let end = stopped_at.unwrap_or(now);
+            let end = to.map_or(end, |to| end.min(to));
+            (end - start).num_seconds().max(0)
+        })
+        .sum()
+}
@@ -0,0 +1,158 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{DatabasePool, Storage, StorageError};
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct HealthResponse {
+    pub status: String,
+}
+
+/// Outcome of a single readiness check.
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadinessCheck {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ReadinessCheck {
+    fn from_result<T>(result: Result<T, StorageError>) -> Self {
+        match result {
+            Ok(_) => Self {
+                ok: true,
+                error: None,
+            },
+            Err(error) => Self {
+                ok: false,
+                error: Some(error.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct MigrationsCheck {
+    pub ok: bool,
+    /// Migrations embedded in this build.
+    pub expected: usize,
+    /// Of those, migrations recorded as successfully applied with a matching checksum.
+    pub applied: usize,
+    /// Versions that are missing, failed or were applied with different contents.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadinessChecks {
+    /// The database answers queries.
+    pub database: ReadinessCheck,
+    /// The database accepts writes, e.g. the file is not read-only.
+    pub writable: ReadinessCheck,
+    pub migrations: MigrationsCheck,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReadinessResponse {
+    /// `ready` when every check passed, otherwise `unavailable`.
+    pub status: String,
+    pub checks: ReadinessChecks,
+}
+
+/// Liveness: the process is up and serving requests. Does not touch the database.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "monitoring",
+    summary = "Liveness check",
+    security(()),
+    responses(
+        (status = 200, description = "The server is up", body = HealthResponse),
+    )
+)]
+pub async fn healthz() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+    })
+}
+
+/// Readiness: the database is reachable and writable and every embedded migration has been
+/// applied. Responds `503 Service Unavailable` with the same body when any check fails.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "monitoring",
+    summary = "Readiness check",
+    security(()),
+    responses(
+        (status = 200, description = "Ready to serve requests", body = ReadinessResponse),
+        (status = 503, description = "A check failed", body = ReadinessResponse),
+    )
+)]
+pub async fn readyz(State(pool): State<DatabasePool>) -> (StatusCode, Json<ReadinessResponse>) {
+    let database = ReadinessCheck::from_result(pool.ping().await);
+    let writable = ReadinessCheck::from_result(pool.check_writable().await);
+    let migrations = check_migrations(&pool).await;
+
+    let ready = database.ok && writable.ok && migrations.ok;
+    if !ready {
+        tracing::warn!(?database, ?writable, ?migrations, "not ready");
+    }
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let response = ReadinessResponse {
+        status: if ready { "ready" } else { "unavailable" }.to_string(),
+        checks: ReadinessChecks {
+            database,
+            writable,
+            migrations,
+        },
+    };
+    (status, Json(response))
+}
+
+async fn check_migrations(pool: &DatabasePool) -> MigrationsCheck {
+    let expected: Vec<_> = pool
+        .migrator()
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .collect();
+
+    let applied = match pool.applied_migrations().await {
+        Ok(applied) => applied,
+        Err(error) => {
+            return MigrationsCheck {
+                ok: false,
+                expected: expected.len(),
+                applied: 0,
+                pending: expected.iter().map(|migration| migration.version).collect(),
+                error: Some(error.to_string()),
+            };
+        }
+    };
+
+    let pending: Vec<i64> = expected
+        .iter()
+        .filter(|migration| {
+            !applied.iter().any(|applied| {
+                applied.version == migration.version
+                    && applied.success
+                    && *applied.checksum == *migration.checksum
+            })
+        })
+        .map(|migration| migration.version)
+        .collect();
+    MigrationsCheck {
+        ok: pending.is_empty(),
+        expected: expected.len(),
+        applied: expected.len() - pending.len(),
+        pending,
+        error: None,
+    }
+}
@@ -0,0 +1,207 @@
+use axum::{
+    body::{Body, to_bytes},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{ApiError, AppState, DatabasePool, Storage, StoredResponse, ValidationErrors};
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+const MAX_KEY_LENGTH: usize = 255;
+/// Request bodies are read in full to be hashed; this matches axum's default body limit.
+const MAX_BODY_LENGTH: usize = 2 * 1024 * 1024;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    /// How long a stored response is replayed for the same key, in seconds.
+    pub window_seconds: i64,
+    /// How long a key stays claimed by a request that never finished, for example because the
+    /// server crashed, in seconds. After that a retry with the key is processed again.
+    pub pending_seconds: i64,
+    /// How often keys older than the window are deleted, in seconds.
+    pub purge_interval_seconds: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            window_seconds: 24 * 60 * 60,
+            pending_seconds: 60,
+            purge_interval_seconds: 60 * 60,
+        }
+    }
+}
+
+/// A claimed key that is released when dropped, so that a request cancelled before its
+/// response is stored, e.g. by a client disconnect, does not block retries.
+struct Reservation {
+    claim: Option<(DatabasePool, String, String)>,
+}
+
+impl Reservation {
+    /// Releases the key now, for responses that are not stored.
+    async fn release(mut self) {
+        if let Some((pool, key, route)) = self.claim.take() {
+            release(&pool, &key, &route).await;
+        }
+    }
+
+    /// Keeps the key claimed once its response is stored.
+    fn keep(mut self) {
+        self.claim = None;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some((pool, key, route)) = self.claim.take()
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            runtime.spawn(async move { release(&pool, &key, &route).await });
+        }
+    }
+}
+
+async fn release(pool: &DatabasePool, key: &str, route: &str) {
+    if let Err(error) = pool.release_idempotency_key(key, route).await {
+        tracing::warn!(%error, key, "could not release idempotency key");
+    }
+}
+
+/// Replays the stored response when a request repeats an `Idempotency-Key` seen on the same
+/// route within the configured window. Only successful responses are stored, so a failed
+/// request can be retried with the same key. Reusing a key with a different body is rejected.
+pub async fn idempotency(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(key) = request.headers().get(&IDEMPOTENCY_KEY) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+    let route = format!("{} {}", request.method(), request.uri().path());
+    let pool = &state.pool;
+
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_LENGTH).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let request_hash = format!("{:x}", Sha256::digest(&body));
+    let request = Request::from_parts(parts, Body::from(body));
+
+    match pool
+        .reserve_idempotency_key(
+            &key,
+            &route,
+            &request_hash,
+            state.idempotency.pending_seconds,
+            state.idempotency.window_seconds,
+        )
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return replay(&state, &key, &route, &request_hash).await,
+        Err(error) => return ApiError::from(error).into_response(),
+    }
+    let reservation = Reservation {
+        claim: Some((pool.clone(), key.clone(), route.clone())),
+    };
+
+    let response = next.run(request).await;
+
+    if !response.status().is_success() {
+        reservation.release().await;
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, usize::MAX).await else {
+        reservation.release().await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let stored = StoredResponse {
+        status: Some(parts.status.as_u16()),
+        location: parts
+            .headers
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+        body: bytes.to_vec(),
+        request_hash: None,
+    };
+    match pool.store_idempotent_response(&key, &route, &stored).await {
+        Ok(()) => reservation.keep(),
+        Err(error) => {
+            tracing::warn!(%error, key, "could not store idempotent response");
+            reservation.release().await;
+        }
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+async fn replay(state: &AppState, key: &str, route: &str, request_hash: &str) -> Response {
+    let stored = match state
+        .pool
+        .idempotent_response(key, route, state.idempotency.window_seconds)
+        .await
+    {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return in_progress(),
+        Err(error) => return ApiError::from(error).into_response(),
+    };
+
+    if stored
+        .request_hash
+        .as_deref()
+        .is_some_and(|stored| stored != request_hash)
+    {
+        return ValidationErrors::single(
+            IDEMPOTENCY_KEY.as_str(),
+            "was already used with a different request body",
+        )
+        .into_response();
+    }
+
+    // The first request holding this key is still being processed.
+    let Some(status) = stored
+        .status
+        .and_then(|status| StatusCode::from_u16(status).ok())
+    else {
+        return in_progress();
+    };
+
+    let mut response = Response::new(Body::from(stored.body));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    headers.insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    if let Some(location) = stored
+        .location
+        .and_then(|location| HeaderValue::from_str(&location).ok())
+    {
+        headers.insert(header::LOCATION, location);
+    }
+    response
+}
+
+/// The answer for a key whose first request has not finished, or was just released. It carries
+/// `Retry-After`, which clients use to tell it from conflicts that a retry cannot resolve.
+fn in_progress() -> Response {
+    (
+        StatusCode::CONFLICT,
+        [(header::RETRY_AFTER, HeaderValue::from_static("1"))],
+    )
+        .into_response()
+}
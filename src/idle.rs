@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::TimedEvent;
+
+/// When a running event counts as idle. Only events that have received a heartbeat are
+/// watched, so clients that never send one are not affected.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleConfig {
+    /// Seconds without a heartbeat after which the event is flagged as idle.
+    pub timeout_seconds: u64,
+    /// How often running events are checked, in seconds.
+    pub interval_seconds: u64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 300,
+            interval_seconds: 60,
+        }
+    }
+}
+
+/// A stretch of a running event without heartbeats, from the last heartbeat before it to
+/// the first one after it. `ended_at` is empty while the client is still silent.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, sqlx::FromRow)]
+pub struct IdleGap {
+    pub id: i64,
+    pub event_id: i64,
+    pub started_at: chrono::NaiveDateTime,
+    pub ended_at: Option<chrono::NaiveDateTime>,
+    /// How the gap was resolved; empty until it is.
+    pub resolution: Option<IdleResolution>,
+}
+
+/// What to do with the time of an idle gap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum IdleResolution {
+    /// Drop the idle time: the event ends where the gap starts, and the time after the gap,
+    /// if any, continues as a new event with the same task and tags.
+    Discard,
+    /// Count the idle time as part of the event.
+    Keep,
+    /// Like `Discard`, and the idle time becomes an event of its own, to be edited or
+    /// deleted separately.
+    Split,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct HeartbeatResponse {
+    pub event_id: i64,
+    pub last_heartbeat_at: chrono::NaiveDateTime,
+    /// The idle gap this heartbeat ended, for the client to offer its resolutions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_gap: Option<IdleGap>,
+}
+
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetIdleGapsQuery {
+    /// Resolved (`true`) or unresolved (`false`, the default) gaps.
+    pub resolved: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetIdleGapsResponse {
+    pub idle_gaps: Vec<IdleGap>,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolveIdleGapRequest {
+    pub resolution: IdleResolution,
+}
+
+/// What [`Storage::resolve_idle_gap`](crate::Storage::resolve_idle_gap) changed.
+#[derive(Clone, Debug)]
+pub struct ResolvedIdleGap {
+    pub idle_gap: IdleGap,
+    /// Whether the gap's event was running and has been stopped.
+    pub stopped: bool,
+    /// Events created for the idle time and the time after it.
+    pub created_event_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolveIdleGapResponse {
+    pub idle_gap: IdleGap,
+    /// The gap's event followed by any events the resolution created.
+    pub events: Vec<TimedEvent>,
+}
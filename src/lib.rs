@@ -1,362 +1,1114 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    routing::{get, post},
+    extract::{FromRef, Path, Query, State},
+    http::{StatusCode, header},
+    middleware,
+    routing::get,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Sqlite, SqlitePool};
+use utoipa::{IntoParams, Modify, ToSchema};
+use utoipa_axum::{
+    router::{OpenApiRouter, UtoipaMethodRouterExt},
+    routes,
+};
+
+mod auth;
+mod auto_stop;
+mod budget;
+mod config;
+mod health;
+mod idempotency;
+mod idle;
+mod metrics;
+mod notifications;
+mod openapi;
+mod overlap;
+mod pomodoro;
+mod service;
+mod shutdown;
+mod split;
+mod storage;
+mod trace;
+mod validation;
+mod web;
+mod websocket;
+
+pub use auto_stop::{AutoStopConfig, AutoStopReason, AutoStopRule, DailyCutoff};
+pub use budget::{
+    Budget, BudgetPeriod, BudgetStatus, CreateBudgetRequest, GetBudgetQuery, GetBudgetsQuery,
+    GetBudgetsResponse, SetTaskEstimateRequest, TaskEstimate,
+};
+pub use config::{
+    AuthConfig, AuthMode, Config, ConfigError, CorsConfig, DatabaseConfig, EventsConfig,
+    FeatureConfig, LogConfig, LogFormat, ServerConfig, ShutdownConfig,
+};
+pub use health::{
+    HealthResponse, MigrationsCheck, ReadinessCheck, ReadinessChecks, ReadinessResponse,
+};
+pub use idempotency::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED, IdempotencyConfig};
+pub use idle::{
+    GetIdleGapsQuery, GetIdleGapsResponse, HeartbeatResponse, IdleConfig, IdleGap, IdleResolution,
+    ResolveIdleGapRequest, ResolveIdleGapResponse, ResolvedIdleGap,
+};
+pub use metrics::Metrics;
+pub use notifications::{EventChange, EventNotification, EventNotifier};
+pub use overlap::{
+    GetOverlapsQuery, GetOverlapsResponse, Overlap, OverlapAction, OverlapPolicy,
+    ResolveOverlapsRequest, ResolveOverlapsResponse, ResolvedOverlaps,
+};
+pub use pomodoro::{PomodoroConfig, PomodoroPhase, PomodoroTimer};
+pub use service::{TimeTracker, TrackerError};
+pub use shutdown::{
+    RunningEventsOnShutdown, Shutdown, finish_running_events, resume_checkpointed_events,
+};
+pub use split::{JoinEventsRequest, JoinEventsResponse, SplitEventRequest, SplitEventResponse};
+pub use storage::{
+    AppliedMigration, DatabasePool, EventTotals, PostgresStorage, SqliteStorage, Storage,
+    StorageError, StoredResponse,
+};
+pub use trace::REQUEST_ID;
+pub use validation::{ApiError, FieldError, TagCase, ValidationConfig, ValidationErrors};
+pub use websocket::{ClientMessage, ServerMessage};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTagRequest {
     pub name: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTaskRequest {
     pub task: String,
+    /// Planned time for the task, in seconds.
+    #[serde(default)]
+    pub estimate_seconds: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTagResponse {
     pub id: i64,
     pub name: String,
     pub message: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTaskResponse {
     pub id: i64,
     pub task: String,
     pub message: String,
 }
 
-#[derive(Serialize, Deserialize, sqlx::FromRow, Clone)]
+#[derive(Serialize, Deserialize, sqlx::FromRow, Clone, Debug, ToSchema)]
 pub struct Tag {
     pub id: i64,
     pub name: String,
     pub created_at: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Serialize, Deserialize, sqlx::FromRow, Clone)]
+#[derive(Serialize, Deserialize, sqlx::FromRow, Clone, Debug, ToSchema)]
 pub struct Task {
     pub id: i64,
     pub task: String,
     pub created_at: Option<chrono::NaiveDateTime>,
+    /// Planned time for the task, in seconds.
+    #[serde(default)]
+    pub estimate_seconds: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct GetTagsResponse {
     pub tags: Vec<Tag>,
     pub count: usize,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct GetTasksResponse {
     pub tasks: Vec<Task>,
     pub count: usize,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTimedEventRequest {
     pub tag_ids: Vec<i64>,
     pub task_id: i64,
 }
 
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
+/// Partial update of an event; omitted fields keep their current value.
+#[derive(Serialize, Deserialize, Default, ToSchema)]
+pub struct UpdateTimedEventRequest {
+    pub task_id: Option<i64>,
+    /// Replaces the event's tags when present.
+    pub tag_ids: Option<Vec<i64>>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub stopped_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow, Clone, Debug, ToSchema)]
 pub struct TimedEvent {
     pub id: i64,
     pub task_id: i64,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[sqlx(skip)]
     pub task: Option<Task>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[sqlx(skip)]
     pub tags: Vec<Tag>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub stopped_at: Option<chrono::NaiveDateTime>,
+    /// Set when the server stopped the event because it hit an auto-stop limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_stop_reason: Option<AutoStopReason>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTimedEventResponse {
     pub id: i64,
     pub message: String,
+    /// Events stopped to make room for this one in [`TimerMode::Single`] and
+    /// [`TimerMode::Pomodoro`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stopped_events: Vec<StoppedEvent>,
+    /// When the server stops this event, in [`TimerMode::Pomodoro`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pomodoro: Option<PomodoroTimer>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct StoppedEvent {
+    pub id: i64,
+    pub duration_seconds: i64,
+}
+
+/// Whether several timers may run at the same time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerMode {
+    /// Any number of events may be running at once.
+    #[default]
+    Parallel,
+    /// Starting an event stops every running event in the same transaction.
+    Single,
+    /// Like `Single`, and every event is a Pomodoro work interval that the server stops after
+    /// `pomodoro.work_seconds`, optionally followed by a break event.
+    Pomodoro,
+}
+
+/// Which events `GET /events` lists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EventStatus {
+    #[default]
+    Running,
+    Stopped,
+    All,
+}
+
+impl EventStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventStatus::Running => "running",
+            EventStatus::Stopped => "stopped",
+            EventStatus::All => "all",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetEventsQuery {
+    pub status: Option<EventStatus>,
+    /// Only events started at or after this time.
+    pub since: Option<chrono::NaiveDateTime>,
+    /// Only events started before this time.
+    pub until: Option<chrono::NaiveDateTime>,
+    /// Only events the server did (`true`) or did not (`false`) stop by an auto-stop limit.
+    pub auto_stopped: Option<bool>,
+    pub limit: Option<i64>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct GetEventsResponse {
     pub events: Vec<TimedEvent>,
     pub count: usize,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventCombinationsQuery {
+    pub limit: Option<i64>,
+    pub days: Option<i64>,
+}
+
+/// A task and tag set that events were started with, and how often.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EventCombination {
+    pub task_id: i64,
+    pub tag_ids: Vec<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<Task>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    pub uses: i64,
+    pub last_used_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetEventCombinationsResponse {
+    pub combinations: Vec<EventCombination>,
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct StopEventResponse {
     pub id: i64,
     pub message: String,
     pub duration_seconds: i64,
 }
 
-pub fn create_app(pool: Pool<Sqlite>) -> Router {
-    Router::new()
-        .route("/tag", post(create_tag))
-        .route("/tags", get(get_tags))
-        .route("/task", post(create_task))
-        .route("/tasks", get(get_tasks))
-        .route("/events", get(get_events))
-        .route("/events/start", post(create_event))
-        .route("/events/stop/{id}", post(stop_event))
-        .with_state(pool)
+/// Shared state handed to every handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: DatabasePool,
+    pub validation: ValidationConfig,
+    pub idempotency: IdempotencyConfig,
+    pub timer_mode: TimerMode,
+    pub overlap_policy: OverlapPolicy,
+    pub pomodoro: PomodoroConfig,
+    pub auto_stop: AutoStopConfig,
+    pub idle: IdleConfig,
+    pub notifier: EventNotifier,
+    pub features: FeatureConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub metrics: Metrics,
+    pub shutdown: Shutdown,
 }
 
-async fn create_tag(
-    State(pool): State<Pool<Sqlite>>,
-    Json(payload): Json<CreateTagRequest>,
-) -> Result<Json<CreateTagResponse>, StatusCode> {
-    println!("create tag");
-    let result = sqlx::query!("INSERT INTO tags (name) VALUES (?)", payload.name)
-        .execute(&pool)
-        .await;
-
-    match result {
-        Ok(result) => {
-            let response = CreateTagResponse {
-                id: result.last_insert_rowid(),
-                name: payload.name,
-                message: "Tag created successfully".to_string(),
-            };
-            Ok(Json(response))
+impl AppState {
+    pub fn new(pool: impl Into<DatabasePool>) -> Self {
+        Self {
+            pool: pool.into(),
+            validation: ValidationConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            timer_mode: TimerMode::default(),
+            overlap_policy: OverlapPolicy::default(),
+            pomodoro: PomodoroConfig::default(),
+            auto_stop: AutoStopConfig::default(),
+            idle: IdleConfig::default(),
+            notifier: EventNotifier::default(),
+            features: FeatureConfig::default(),
+            auth: AuthConfig::default(),
+            cors: CorsConfig::default(),
+            metrics: Metrics::default(),
+            shutdown: Shutdown::default(),
         }
-        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
-            Err(StatusCode::CONFLICT)
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+
+    /// State for a server started from `config`.
+    pub fn from_config(pool: impl Into<DatabasePool>, config: &Config) -> Self {
+        Self::new(pool)
+            .with_validation(config.validation.clone())
+            .with_idempotency(config.idempotency.clone())
+            .with_timer_mode(config.events.timer_mode)
+            .with_overlap_policy(config.events.overlap_policy)
+            .with_pomodoro(config.pomodoro.clone())
+            .with_auto_stop(config.auto_stop.clone())
+            .with_idle(config.idle.clone())
+            .with_features(config.features.clone())
+            .with_auth(config.auth.clone())
+            .with_cors(config.cors.clone())
+    }
+
+    pub fn with_validation(mut self, validation: ValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn with_idempotency(mut self, idempotency: IdempotencyConfig) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
+    pub fn with_timer_mode(mut self, timer_mode: TimerMode) -> Self {
+        self.timer_mode = timer_mode;
+        self
+    }
+
+    pub fn with_overlap_policy(mut self, overlap_policy: OverlapPolicy) -> Self {
+        self.overlap_policy = overlap_policy;
+        self
+    }
+
+    pub fn with_pomodoro(mut self, pomodoro: PomodoroConfig) -> Self {
+        self.pomodoro = pomodoro;
+        self
+    }
+
+    pub fn with_auto_stop(mut self, auto_stop: AutoStopConfig) -> Self {
+        self.auto_stop = auto_stop;
+        self
+    }
+
+    pub fn with_idle(mut self, idle: IdleConfig) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    pub fn with_features(mut self, features: FeatureConfig) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// The service the handlers call, configured from this state.
+    pub fn tracker(&self) -> TimeTracker {
+        TimeTracker::new(self.pool.clone())
+            .with_validation(self.validation.clone())
+            .with_timer_mode(self.timer_mode)
+            .with_overlap_policy(self.overlap_policy)
+            .with_pomodoro(self.pomodoro.clone())
+            .with_auto_stop(self.auto_stop.clone())
+            .with_idle(self.idle.clone())
+            .with_idempotency(self.idempotency.clone())
+            .with_notifier(self.notifier.clone())
+            .with_shutdown(self.shutdown.clone())
+    }
+}
+
+impl FromRef<AppState> for DatabasePool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
     }
 }
 
+impl FromRef<AppState> for TimeTracker {
+    fn from_ref(state: &AppState) -> Self {
+        state.tracker()
+    }
+}
+
+impl FromRef<AppState> for EventNotifier {
+    fn from_ref(state: &AppState) -> Self {
+        state.notifier.clone()
+    }
+}
+
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+pub fn create_app(pool: impl Into<DatabasePool>) -> Router {
+    create_app_with_state(AppState::new(pool))
+}
+
+pub fn create_app_with_state(state: AppState) -> Router {
+    let idempotent = || middleware::from_fn_with_state(state.clone(), idempotency::idempotency);
+
+    let mut router = OpenApiRouter::with_openapi(openapi::ApiDoc::document())
+        .routes(routes!(create_tag).layer(idempotent()))
+        .routes(routes!(get_tag))
+        .routes(routes!(get_tags))
+        .routes(routes!(create_task).layer(idempotent()))
+        .routes(routes!(get_task))
+        .routes(routes!(get_tasks))
+        .routes(routes!(get_task_estimate, set_task_estimate))
+        .routes(routes!(get_budgets))
+        .routes(routes!(create_budget).layer(idempotent()))
+        .routes(routes!(get_budget, delete_budget))
+        .routes(routes!(get_events))
+        .routes(routes!(get_event, update_event, delete_event))
+        .routes(routes!(continue_event).layer(idempotent()))
+        .routes(routes!(split_event).layer(idempotent()))
+        .routes(routes!(join_events).layer(idempotent()))
+        .routes(routes!(get_event_combinations))
+        .routes(routes!(get_overlaps))
+        .routes(routes!(resolve_overlaps).layer(idempotent()))
+        .routes(routes!(create_event).layer(idempotent()))
+        .routes(routes!(stop_event).layer(idempotent()))
+        .routes(routes!(heartbeat))
+        .routes(routes!(get_idle_gaps))
+        .routes(routes!(resolve_idle_gap).layer(idempotent()));
+    if state.features.event_stream {
+        router = router.routes(routes!(notifications::event_stream));
+    }
+    if state.features.websocket {
+        router = router.routes(routes!(websocket::websocket));
+    }
+    if state.auth.mode == AuthMode::Token {
+        router = router.route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_token,
+        ));
+        openapi::BearerAuth.modify(router.get_openapi_mut());
+    }
+    // Routes added after the auth layer are public: the web page and its assets (its API calls
+    // are not), the API docs, health checks and metrics.
+    if state.features.web_ui {
+        router = router
+            .route("/", get(web::index))
+            .route("/ui/app.js", get(web::app_js))
+            .route("/ui/config.json", get(web::ui_config))
+            .route("/ui/style.css", get(web::style_css));
+    }
+    router = router
+        .routes(routes!(health::healthz))
+        .routes(routes!(health::readyz));
+    if state.features.metrics {
+        router = router.routes(routes!(metrics::metrics));
+    }
+
+    let (mut router, api) = router.split_for_parts();
+    if state.features.api_docs {
+        router = router.merge(openapi::router(api));
+    }
+    if state.features.metrics {
+        router = router.layer(middleware::from_fn_with_state(
+            state.clone(),
+            metrics::track,
+        ));
+    }
+
+    let cors = state.cors.layer();
+    let router = trace::layer(router.with_state(state));
+    match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// Logs a database error that is about to be reported to the client as a bare 500.
+pub(crate) fn internal_error(error: sqlx::Error) -> StatusCode {
+    tracing::error!(%error, "database error");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+type Created<T> = (StatusCode, [(header::HeaderName, String); 1], Json<T>);
+
+fn created<T>(location: String, body: T) -> Created<T> {
+    (
+        StatusCode::CREATED,
+        [(header::LOCATION, location)],
+        Json(body),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/tag",
+    tag = "tags",
+    summary = "Create a tag",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = CreateTagRequest,
+    responses(
+        (status = 201, description = "Tag created", body = CreateTagResponse, headers(("Location" = String, description = "URL of the new tag"))),
+        (status = 400, description = "Malformed request body or idempotency key"),
+        (status = 409, description = "A tag with this name exists, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn create_tag(
+    State(tracker): State<TimeTracker>,
+    Json(payload): Json<CreateTagRequest>,
+) -> Result<Created<CreateTagResponse>, ApiError> {
+    let response = tracker.create_tag(payload).await?;
+    Ok(created(format!("/tag/{}", response.id), response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/task",
+    tag = "tasks",
+    summary = "Create a task",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = CreateTaskRequest,
+    responses(
+        (status = 201, description = "Task created", body = CreateTaskResponse, headers(("Location" = String, description = "URL of the new task"))),
+        (status = 400, description = "Malformed request body or idempotency key"),
+        (status = 409, description = "A task with this name exists, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
 async fn create_task(
-    State(pool): State<Pool<Sqlite>>,
+    State(tracker): State<TimeTracker>,
     Json(payload): Json<CreateTaskRequest>,
-) -> Result<Json<CreateTaskResponse>, StatusCode> {
-    println!("create task");
-    let result = sqlx::query!("INSERT INTO tasks (task) VALUES (?)", payload.task)
-        .execute(&pool)
-        .await;
-
-    match result {
-        Ok(result) => {
-            let response = CreateTaskResponse {
-                id: result.last_insert_rowid(),
-                task: payload.task,
-                message: "Task created successfully".to_string(),
-            };
-            Ok(Json(response))
-        }
-        Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
-            Err(StatusCode::CONFLICT)
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+) -> Result<Created<CreateTaskResponse>, ApiError> {
+    let response = tracker.create_task(payload).await?;
+    Ok(created(format!("/task/{}", response.id), response))
 }
 
-async fn get_tags(State(pool): State<Pool<Sqlite>>) -> Result<Json<GetTagsResponse>, StatusCode> {
-    println!("get tags");
-    let result = sqlx::query_as!(
-        Tag,
-        "SELECT id, name, created_at FROM tags ORDER BY created_at DESC"
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    summary = "List tags, newest first",
+    responses(
+        (status = 200, description = "All tags", body = GetTagsResponse),
+        (status = 500, description = "Database error"),
     )
-    .fetch_all(&pool)
-    .await;
-
-    match result {
-        Ok(tags) => {
-            let count = tags.len();
-            let response = GetTagsResponse { tags, count };
-            Ok(Json(response))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+)]
+async fn get_tags(State(tracker): State<TimeTracker>) -> Result<Json<GetTagsResponse>, ApiError> {
+    let tags = tracker.tags().await?;
+    let count = tags.len();
+    Ok(Json(GetTagsResponse { tags, count }))
 }
 
-async fn get_tasks(State(pool): State<Pool<Sqlite>>) -> Result<Json<GetTasksResponse>, StatusCode> {
-    println!("get tasks");
-    let result = sqlx::query_as!(
-        Task,
-        "SELECT id, task, created_at FROM tasks ORDER BY created_at DESC"
+#[utoipa::path(
+    get,
+    path = "/tag/{id}",
+    tag = "tags",
+    summary = "Get a tag",
+    params(("id" = i64, Path, description = "Tag id")),
+    responses(
+        (status = 200, description = "The tag", body = Tag),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No tag with this id"),
+        (status = 500, description = "Database error"),
     )
-    .fetch_all(&pool)
-    .await;
-
-    match result {
-        Ok(tasks) => {
-            let count = tasks.len();
-            let response = GetTasksResponse { tasks, count };
-            Ok(Json(response))
-        }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
-    }
+)]
+async fn get_tag(
+    State(tracker): State<TimeTracker>,
+    Path(tag_id): Path<i64>,
+) -> Result<Json<Tag>, ApiError> {
+    Ok(Json(tracker.tag(tag_id).await?))
 }
 
-async fn get_events(
-    State(pool): State<Pool<Sqlite>>,
-) -> Result<Json<GetEventsResponse>, StatusCode> {
-    println!("get events");
-
-    let events = sqlx::query!(
-        "SELECT id as \"id!\", task_id as \"task_id!\", created_at, stopped_at
-         FROM events
-         WHERE stopped_at IS NULL
-         ORDER BY created_at DESC"
+#[utoipa::path(
+    get,
+    path = "/tasks",
+    tag = "tasks",
+    summary = "List tasks, newest first",
+    responses(
+        (status = 200, description = "All tasks", body = GetTasksResponse),
+        (status = 500, description = "Database error"),
     )
-    .fetch_all(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let mut events_with_details = Vec::new();
-
-    for event in events {
-        let task = sqlx::query_as!(
-            Task,
-            "SELECT id as \"id!\", task as \"task!\", created_at FROM tasks WHERE id = ?",
-            event.task_id
-        )
-        .fetch_optional(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        let tags = sqlx::query_as!(
-            Tag,
-            "SELECT t.id as \"id!\", t.name as \"name!\", t.created_at
-             FROM tags t
-             JOIN event_tags et ON et.tag_id = t.id
-             WHERE et.event_id = ?",
-            event.id
-        )
-        .fetch_all(&pool)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        let timed_event = TimedEvent {
-            id: event.id,
-            task_id: event.task_id,
-            task,
-            tags,
-            created_at: Some(event.created_at),
-            stopped_at: event.stopped_at,
-        };
-
-        events_with_details.push(timed_event);
-    }
+)]
+async fn get_tasks(State(tracker): State<TimeTracker>) -> Result<Json<GetTasksResponse>, ApiError> {
+    let tasks = tracker.tasks().await?;
+    let count = tasks.len();
+    Ok(Json(GetTasksResponse { tasks, count }))
+}
 
-    let count = events_with_details.len();
-    Ok(Json(GetEventsResponse {
-        events: events_with_details,
-        count,
-    }))
+#[utoipa::path(
+    get,
+    path = "/task/{id}",
+    tag = "tasks",
+    summary = "Get a task",
+    params(("id" = i64, Path, description = "Task id")),
+    responses(
+        (status = 200, description = "The task", body = Task),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No task with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_task(
+    State(tracker): State<TimeTracker>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<Task>, ApiError> {
+    Ok(Json(tracker.task(task_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/task/{id}/estimate",
+    tag = "tasks",
+    summary = "Compare a task's estimate with the time tracked on it",
+    params(("id" = i64, Path, description = "Task id")),
+    responses(
+        (status = 200, description = "The estimate and the time tracked so far", body = TaskEstimate),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No task with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_task_estimate(
+    State(tracker): State<TimeTracker>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<TaskEstimate>, ApiError> {
+    Ok(Json(tracker.task_estimate(task_id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/task/{id}/estimate",
+    tag = "tasks",
+    summary = "Set or clear a task's estimate",
+    params(("id" = i64, Path, description = "Task id")),
+    request_body = SetTaskEstimateRequest,
+    responses(
+        (status = 200, description = "The updated task", body = Task),
+        (status = 400, description = "Invalid id or malformed request body"),
+        (status = 404, description = "No task with this id"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn set_task_estimate(
+    State(tracker): State<TimeTracker>,
+    Path(task_id): Path<i64>,
+    Json(payload): Json<SetTaskEstimateRequest>,
+) -> Result<Json<Task>, ApiError> {
+    Ok(Json(tracker.set_task_estimate(task_id, payload).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    summary = "List events, newest first",
+    params(GetEventsQuery),
+    responses(
+        (status = 200, description = "Matching events with their task and tags", body = GetEventsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_events(
+    State(tracker): State<TimeTracker>,
+    Query(params): Query<GetEventsQuery>,
+) -> Result<Json<GetEventsResponse>, ApiError> {
+    let events = tracker.list_events(&params).await?;
+    let count = events.len();
+    Ok(Json(GetEventsResponse { events, count }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/events/{id}",
+    tag = "events",
+    summary = "Get an event",
+    params(("id" = i64, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "The event with its task and tags", body = TimedEvent),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No event with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+) -> Result<Json<TimedEvent>, ApiError> {
+    Ok(Json(tracker.event(event_id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/events/start",
+    tag = "events",
+    summary = "Start an event",
+    description = "In `single` timer mode, running events are stopped first and returned in `stopped_events`.",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = CreateTimedEventRequest,
+    responses(
+        (status = 201, description = "Event started", body = CreateTimedEventResponse, headers(("Location" = String, description = "URL of the new event"))),
+        (status = 400, description = "Malformed request body or idempotency key"),
+        (status = 409, description = "Another event is running and overlaps are rejected, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
 async fn create_event(
-    State(pool): State<Pool<Sqlite>>,
+    State(tracker): State<TimeTracker>,
     Json(payload): Json<CreateTimedEventRequest>,
-) -> Result<Json<CreateTimedEventResponse>, StatusCode> {
-    println!("create event");
+) -> Result<Created<CreateTimedEventResponse>, ApiError> {
+    let response = tracker.start_event(payload).await?;
+    Ok(created(format!("/events/{}", response.id), response))
+}
 
-    let mut tx = pool
-        .begin()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[utoipa::path(
+    post,
+    path = "/events/{id}/continue",
+    tag = "events",
+    summary = "Start a new event with a stopped event's task and tags",
+    params(("id" = i64, Path, description = "Event id"), ("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    responses(
+        (status = 201, description = "Event started", body = CreateTimedEventResponse, headers(("Location" = String, description = "URL of the new event"))),
+        (status = 400, description = "Invalid id or idempotency key"),
+        (status = 404, description = "No event with this id"),
+        (status = 409, description = "The event is still running, another event is running and overlaps are rejected, or the idempotency key is in use"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn continue_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+) -> Result<Created<CreateTimedEventResponse>, ApiError> {
+    let response = tracker.continue_event(event_id).await?;
+    Ok(created(format!("/events/{}", response.id), response))
+}
 
-    let task_exists = sqlx::query!("SELECT id FROM tasks WHERE id = ?", payload.task_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[utoipa::path(
+    get,
+    path = "/events/combinations",
+    tag = "events",
+    summary = "Most used task and tag combinations",
+    params(EventCombinationsQuery),
+    responses(
+        (status = 200, description = "Combinations, most used first", body = GetEventCombinationsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_event_combinations(
+    State(tracker): State<TimeTracker>,
+    Query(params): Query<EventCombinationsQuery>,
+) -> Result<Json<GetEventCombinationsResponse>, ApiError> {
+    let combinations = tracker.event_combinations(&params).await?;
+    let count = combinations.len();
+    Ok(Json(GetEventCombinationsResponse {
+        combinations,
+        count,
+    }))
+}
 
-    if task_exists.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+#[utoipa::path(
+    patch,
+    path = "/events/{id}",
+    tag = "events",
+    summary = "Update an event",
+    params(("id" = i64, Path, description = "Event id")),
+    request_body = UpdateTimedEventRequest,
+    responses(
+        (status = 200, description = "The updated event", body = TimedEvent),
+        (status = 400, description = "Invalid id or malformed request body"),
+        (status = 404, description = "No event with this id"),
+        (status = 409, description = "The new times overlap another event and overlaps are rejected"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn update_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+    Json(payload): Json<UpdateTimedEventRequest>,
+) -> Result<Json<TimedEvent>, ApiError> {
+    Ok(Json(tracker.update_event(event_id, payload).await?))
+}
 
-    for tag_id in &payload.tag_ids {
-        let tag_exists = sqlx::query!("SELECT id FROM tags WHERE id = ?", tag_id)
-            .fetch_optional(&mut *tx)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[utoipa::path(
+    delete,
+    path = "/events/{id}",
+    tag = "events",
+    summary = "Delete an event",
+    params(("id" = i64, Path, description = "Event id")),
+    responses(
+        (status = 204, description = "Event deleted"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No event with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn delete_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    tracker.delete_event(event_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
-        if tag_exists.is_none() {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    }
+#[utoipa::path(
+    post,
+    path = "/events/stop/{id}",
+    tag = "events",
+    summary = "Stop a running event",
+    params(("id" = i64, Path, description = "Event id"), ("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    responses(
+        (status = 200, description = "Event stopped", body = StopEventResponse),
+        (status = 400, description = "Invalid id or idempotency key"),
+        (status = 404, description = "No running event with this id"),
+        (status = 409, description = "The idempotency key is in use"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn stop_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+) -> Result<Json<StopEventResponse>, ApiError> {
+    Ok(Json(tracker.stop_event(event_id).await?))
+}
 
-    let event_result = sqlx::query!(
-        "INSERT INTO events (task_id, created_at, stopped_at) VALUES (?, datetime('now'), NULL)",
-        payload.task_id
+#[utoipa::path(
+    post,
+    path = "/events/{id}/split",
+    tag = "events",
+    summary = "Split an event in two at a point in time",
+    params(("id" = i64, Path, description = "Event id"), ("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = SplitEventRequest,
+    responses(
+        (status = 200, description = "Both halves of the event", body = SplitEventResponse),
+        (status = 400, description = "Invalid id, malformed request body or idempotency key, or unknown task or tag"),
+        (status = 404, description = "No event with this id"),
+        (status = 409, description = "A half overlaps another event and overlaps are rejected, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "The split point is outside the event, or the request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
     )
-    .execute(&mut *tx)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let event_id = event_result.last_insert_rowid();
-
-    for tag_id in payload.tag_ids {
-        sqlx::query!(
-            "INSERT INTO event_tags (event_id, tag_id) VALUES (?, ?)",
-            event_id,
-            tag_id
-        )
-        .execute(&mut *tx)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    }
+)]
+async fn split_event(
+    State(tracker): State<TimeTracker>,
+    Path(event_id): Path<i64>,
+    Json(payload): Json<SplitEventRequest>,
+) -> Result<Json<SplitEventResponse>, ApiError> {
+    Ok(Json(tracker.split_event(event_id, payload).await?))
+}
 
-    tx.commit()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+#[utoipa::path(
+    post,
+    path = "/events/join",
+    tag = "events",
+    summary = "Join consecutive events of one task",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = JoinEventsRequest,
+    responses(
+        (status = 200, description = "The joined event", body = JoinEventsResponse),
+        (status = 400, description = "Malformed request body or idempotency key"),
+        (status = 404, description = "One of the events does not exist"),
+        (status = 409, description = "The joined event would overlap another event and overlaps are rejected, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "The events cannot be joined, or the request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn join_events(
+    State(tracker): State<TimeTracker>,
+    Json(payload): Json<JoinEventsRequest>,
+) -> Result<Json<JoinEventsResponse>, ApiError> {
+    Ok(Json(tracker.join_events(payload).await?))
+}
 
-    Ok(Json(CreateTimedEventResponse {
-        id: event_id,
-        message: "Event started successfully".to_string(),
+#[utoipa::path(
+    get,
+    path = "/events/overlaps",
+    tag = "events",
+    summary = "Find events that cover the same time",
+    params(GetOverlapsQuery),
+    responses(
+        (status = 200, description = "Overlapping pairs of events, by the start of the overlap", body = GetOverlapsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_overlaps(
+    State(tracker): State<TimeTracker>,
+    Query(params): Query<GetOverlapsQuery>,
+) -> Result<Json<GetOverlapsResponse>, ApiError> {
+    let overlaps = tracker.overlaps(&params).await?;
+    let count = overlaps.len();
+    let overlapping_seconds = overlaps.iter().map(|overlap| overlap.overlap_seconds).sum();
+    Ok(Json(GetOverlapsResponse {
+        overlaps,
+        count,
+        overlapping_seconds,
     }))
 }
 
-async fn stop_event(
-    State(pool): State<Pool<Sqlite>>,
+#[utoipa::path(
+    post,
+    path = "/events/overlaps/resolve",
+    tag = "events",
+    summary = "Trim or split overlapping events",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = ResolveOverlapsRequest,
+    responses(
+        (status = 200, description = "The events the resolution changed or created", body = ResolveOverlapsResponse),
+        (status = 400, description = "Malformed request body or idempotency key"),
+        (status = 409, description = "Two events start at the same time, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Unknown action"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn resolve_overlaps(
+    State(tracker): State<TimeTracker>,
+    Json(payload): Json<ResolveOverlapsRequest>,
+) -> Result<Json<ResolveOverlapsResponse>, ApiError> {
+    let events = tracker.resolve_overlaps(payload).await?;
+    let count = events.len();
+    Ok(Json(ResolveOverlapsResponse { events, count }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/events/{id}/heartbeat",
+    tag = "events",
+    summary = "Report that the user of a running event is active",
+    params(("id" = i64, Path, description = "Event id")),
+    responses(
+        (status = 200, description = "Heartbeat recorded, with the idle gap it ended", body = HeartbeatResponse),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No running event with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn heartbeat(
+    State(tracker): State<TimeTracker>,
     Path(event_id): Path<i64>,
-) -> Result<Json<StopEventResponse>, StatusCode> {
-    println!("stop event {}", event_id);
+) -> Result<Json<HeartbeatResponse>, ApiError> {
+    Ok(Json(tracker.heartbeat(event_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/idle-gaps",
+    tag = "events",
+    summary = "List idle gaps",
+    params(GetIdleGapsQuery),
+    responses(
+        (status = 200, description = "Idle gaps, oldest first", body = GetIdleGapsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_idle_gaps(
+    State(tracker): State<TimeTracker>,
+    Query(params): Query<GetIdleGapsQuery>,
+) -> Result<Json<GetIdleGapsResponse>, ApiError> {
+    let idle_gaps = tracker.idle_gaps(&params).await?;
+    let count = idle_gaps.len();
+    Ok(Json(GetIdleGapsResponse { idle_gaps, count }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/idle-gaps/{id}/resolve",
+    tag = "events",
+    summary = "Discard, keep or split off the time of an idle gap",
+    params(("id" = i64, Path, description = "Idle gap id"), ("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = ResolveIdleGapRequest,
+    responses(
+        (status = 200, description = "The resolved gap and the events it changed or created", body = ResolveIdleGapResponse),
+        (status = 400, description = "Invalid id, malformed request body or idempotency key"),
+        (status = 404, description = "No idle gap with this id"),
+        (status = 409, description = "The gap is already resolved, an event it creates overlaps another event and overlaps are rejected, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Unknown resolution"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn resolve_idle_gap(
+    State(tracker): State<TimeTracker>,
+    Path(gap_id): Path<i64>,
+    Json(payload): Json<ResolveIdleGapRequest>,
+) -> Result<Json<ResolveIdleGapResponse>, ApiError> {
+    Ok(Json(tracker.resolve_idle_gap(gap_id, payload).await?))
+}
 
-    let event = sqlx::query!(
-        "SELECT id as \"id!\", created_at as \"created_at!\" FROM events WHERE id = ? AND stopped_at IS NULL",
-        event_id
+#[utoipa::path(
+    post,
+    path = "/budgets",
+    tag = "budgets",
+    summary = "Create a budget for a tag",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response for repeated requests with the same key")),
+    request_body = CreateBudgetRequest,
+    responses(
+        (status = 201, description = "Budget created", body = Budget, headers(("Location" = String, description = "URL of the new budget"))),
+        (status = 400, description = "Malformed request body or idempotency key, or unknown tag"),
+        (status = 409, description = "The tag has a budget for this period, or the idempotency key is in use"),
+        (status = 415, description = "Request body is not JSON"),
+        (status = 422, description = "Request body failed validation", body = ValidationErrors),
+        (status = 500, description = "Database error"),
     )
-    .fetch_optional(&pool)
+)]
+async fn create_budget(
+    State(tracker): State<TimeTracker>,
+    Json(payload): Json<CreateBudgetRequest>,
+) -> Result<Created<Budget>, ApiError> {
+    let budget = tracker.create_budget(payload).await?;
+    Ok(created(format!("/budgets/{}", budget.id), budget))
+}
+
+#[utoipa::path(
+    get,
+    path = "/budgets",
+    tag = "budgets",
+    summary = "List budgets with the time tracked in their current period",
+    params(GetBudgetsQuery),
+    responses(
+        (status = 200, description = "Budgets by tag and period", body = GetBudgetsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 422, description = "`at` is too close to the end of the calendar", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_budgets(
+    State(tracker): State<TimeTracker>,
+    Query(query): Query<GetBudgetsQuery>,
+) -> Result<Json<GetBudgetsResponse>, ApiError> {
+    let budgets = tracker.budgets(&query).await?;
+    let count = budgets.len();
+    Ok(Json(GetBudgetsResponse { budgets, count }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/budgets/{id}",
+    tag = "budgets",
+    summary = "Get a budget with the time tracked in its current period",
+    params(("id" = i64, Path, description = "Budget id"), GetBudgetQuery),
+    responses(
+        (status = 200, description = "The budget", body = BudgetStatus),
+        (status = 400, description = "Invalid id or query parameters"),
+        (status = 404, description = "No budget with this id"),
+        (status = 422, description = "`at` is too close to the end of the calendar", body = ValidationErrors),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn get_budget(
+    State(tracker): State<TimeTracker>,
+    Path(budget_id): Path<i64>,
+    Query(query): Query<GetBudgetQuery>,
+) -> Result<Json<BudgetStatus>, ApiError> {
+    Ok(Json(tracker.budget(budget_id, &query).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/budgets/{id}",
+    tag = "budgets",
+    summary = "Delete a budget",
+    params(("id" = i64, Path, description = "Budget id")),
+    responses(
+        (status = 204, description = "Budget deleted"),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "No budget with this id"),
+        (status = 500, description = "Database error"),
+    )
+)]
+async fn delete_budget(
+    State(tracker): State<TimeTracker>,
+    Path(budget_id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    tracker.delete_budget(budget_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_database_pool(database_url: &str) -> Result<DatabasePool, sqlx::Error> {
+    connect_database(&DatabaseConfig {
+        url: database_url.to_string(),
+        ..DatabaseConfig::default()
+    })
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    match event {
-        Some(event_data) => {
-            sqlx::query!(
-                "UPDATE events SET stopped_at = datetime('now') WHERE id = ?",
-                event_id
-            )
-            .execute(&pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-            let duration_seconds = {
-                let now = chrono::Local::now().naive_local();
-                (now - event_data.created_at).num_seconds()
-            };
-
-            Ok(Json(StopEventResponse {
-                id: event_id,
-                message: "Event stopped successfully".to_string(),
-                duration_seconds,
-            }))
-        }
-        None => Err(StatusCode::NOT_FOUND),
-    }
 }
 
-pub async fn create_database_pool(database_url: &str) -> Result<Pool<Sqlite>, sqlx::Error> {
-    let pool = SqlitePool::connect(database_url).await?;
-    sqlx::migrate!("./migrations").run(&pool).await?;
-    Ok(pool)
+/// Opens the pool described by `config` and applies pending migrations.
+pub async fn connect_database(config: &DatabaseConfig) -> Result<DatabasePool, sqlx::Error> {
+    DatabasePool::connect(config).await
 }
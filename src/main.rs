@@ -1,17 +1,278 @@
-use rustimenator::{create_app, create_database_pool};
+use std::{
+    future::IntoFuture,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Parser;
+use rustimenator::{
+    AppState, AuthMode, Config, LogFormat, RunningEventsOnShutdown, TimerMode, connect_database,
+    create_app_with_state, finish_running_events, resume_checkpointed_events,
+};
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_CONFIG_FILE: &str = "rustimenator.toml";
+
+/// Rustimenator time tracking server.
+///
+/// Settings come from the configuration file, then environment variables, then flags; later
+/// sources win.
+#[derive(Parser)]
+#[command(name = "rustimenator", version)]
+struct Args {
+    /// TOML configuration file. Defaults to `rustimenator.toml` when it exists.
+    #[arg(long, env = "RUSTIMENATOR_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Print the effective configuration as TOML and exit.
+    #[arg(long)]
+    print_config: bool,
+
+    /// Address to listen on.
+    #[arg(long, env = "RUSTIMENATOR_BIND")]
+    bind: Option<String>,
+
+    /// Port to listen on.
+    #[arg(long, env = "RUSTIMENATOR_PORT")]
+    port: Option<u16>,
+
+    /// Database URL: `postgres://` or `postgresql://` for PostgreSQL, a `sqlite:` URL or a
+    /// file path for SQLite.
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: Option<String>,
+
+    /// Maximum number of database connections.
+    #[arg(long, env = "RUSTIMENATOR_POOL_SIZE")]
+    pool_size: Option<u32>,
+
+    /// Log level or `tracing` filter directive.
+    #[arg(long, env = "RUSTIMENATOR_LOG_LEVEL")]
+    log_level: Option<String>,
+
+    /// Log format: `text` or `json`.
+    #[arg(long, env = "RUSTIMENATOR_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+
+    /// Origins allowed to call the API from a browser, comma separated.
+    #[arg(long, env = "RUSTIMENATOR_CORS_ORIGINS", value_delimiter = ',')]
+    cors_origins: Option<Vec<String>>,
+
+    /// Authentication mode: `none` or `token`.
+    #[arg(long, env = "RUSTIMENATOR_AUTH_MODE")]
+    auth_mode: Option<AuthMode>,
+
+    /// Accepted bearer tokens, comma separated.
+    #[arg(
+        long,
+        env = "RUSTIMENATOR_AUTH_TOKENS",
+        value_delimiter = ',',
+        hide_env_values = true
+    )]
+    auth_tokens: Option<Vec<String>>,
+
+    /// Timer mode: `parallel`, `single` or `pomodoro`.
+    #[arg(long, env = "RUSTIMENATOR_TIMER_MODE")]
+    timer_mode: Option<TimerMode>,
+
+    /// Serve the web interface at `/`.
+    #[arg(long, env = "RUSTIMENATOR_WEB_UI")]
+    web_ui: Option<bool>,
+
+    /// Serve the Server-Sent Events stream at `/events/stream`.
+    #[arg(long, env = "RUSTIMENATOR_EVENT_STREAM")]
+    event_stream: Option<bool>,
+
+    /// Serve the WebSocket endpoint at `/ws`.
+    #[arg(long, env = "RUSTIMENATOR_WEBSOCKET")]
+    websocket: Option<bool>,
+
+    /// Seconds to let in-flight requests finish after a shutdown signal.
+    #[arg(long, env = "RUSTIMENATOR_DRAIN_SECONDS")]
+    drain_seconds: Option<u64>,
+
+    /// What to do with running events on shutdown: `keep`, `stop` or `checkpoint`.
+    #[arg(long, env = "RUSTIMENATOR_SHUTDOWN_RUNNING_EVENTS")]
+    shutdown_running_events: Option<RunningEventsOnShutdown>,
+
+    /// Serve Prometheus metrics at `/metrics`.
+    #[arg(long, env = "RUSTIMENATOR_METRICS")]
+    metrics: Option<bool>,
+
+    /// Serve the OpenAPI document at `/openapi.json` and API docs at `/docs`.
+    #[arg(long, env = "RUSTIMENATOR_API_DOCS")]
+    api_docs: Option<bool>,
+}
+
+impl Args {
+    fn load_config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = match &self.config {
+            Some(path) => Config::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Config::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            None => Config::default(),
+        };
+        self.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply(&self, config: &mut Config) {
+        if let Some(bind) = &self.bind {
+            config.server.bind = bind.clone();
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+        if let Some(url) = &self.database_url {
+            config.database.url = url.clone();
+        }
+        if let Some(pool_size) = self.pool_size {
+            config.database.max_connections = pool_size;
+        }
+        if let Some(level) = &self.log_level {
+            config.log.level = level.clone();
+        }
+        if let Some(format) = self.log_format {
+            config.log.format = format;
+        }
+        if let Some(origins) = &self.cors_origins {
+            config.cors.allowed_origins = origins.clone();
+        }
+        if let Some(mode) = self.auth_mode {
+            config.auth.mode = mode;
+        }
+        if let Some(tokens) = &self.auth_tokens {
+            config.auth.tokens = tokens.clone();
+        }
+        if let Some(timer_mode) = self.timer_mode {
+            config.events.timer_mode = timer_mode;
+        }
+        if let Some(web_ui) = self.web_ui {
+            config.features.web_ui = web_ui;
+        }
+        if let Some(event_stream) = self.event_stream {
+            config.features.event_stream = event_stream;
+        }
+        if let Some(websocket) = self.websocket {
+            config.features.websocket = websocket;
+        }
+        if let Some(metrics) = self.metrics {
+            config.features.metrics = metrics;
+        }
+        if let Some(api_docs) = self.api_docs {
+            config.features.api_docs = api_docs;
+        }
+        if let Some(drain_seconds) = self.drain_seconds {
+            config.shutdown.drain_seconds = drain_seconds;
+        }
+        if let Some(action) = self.shutdown_running_events {
+            config.shutdown.running_events = action;
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let database_url =
-        std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:./rustimenator.db".to_string());
-    let pool = create_database_pool(&database_url).await?;
-    let app = create_app(pool);
+    let args = Args::parse();
+    let config = args.load_config()?;
+    if args.print_config {
+        print!("{}", config.to_toml_redacted());
+        return Ok(());
+    }
+
+    let filter = EnvFilter::try_new(&config.log.level)?;
+    match config.log.format {
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(filter)
+            .init(),
+    }
+
+    let pool = connect_database(&config.database).await?;
+    let state = AppState::from_config(pool.clone(), &config);
+    // Timers first, as resuming checkpoints schedules the timers it starts itself.
+    match state.tracker().resume_pomodoros().await {
+        Ok(count) if count > 0 => tracing::info!(count, "resumed pomodoro timers"),
+        Ok(_) => {}
+        Err(error) => tracing::error!(%error, "could not resume pomodoro timers"),
+    }
+    match resume_checkpointed_events(&state).await {
+        Ok(resumed) if !resumed.is_empty() => {
+            tracing::info!(count = resumed.len(), "resumed checkpointed events")
+        }
+        Ok(_) => {}
+        Err(error) => tracing::error!(%error, "could not resume checkpointed events"),
+    }
+    state.tracker().spawn_auto_stop();
+    state.tracker().spawn_idle_sweeper();
+    state.tracker().spawn_idempotency_purge();
+    let app = create_app_with_state(state.clone());
+
+    let address = config.bind_address();
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    tracing::info!(%address, database = %config.database.redacted_url(), "server running");
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
-    println!("Server running on http://0.0.0.0:8080");
-    println!("Database: {}", database_url);
+    let shutdown = state.shutdown.clone();
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("shutdown signal received, draining connections");
+            shutdown.trigger();
+        }
+    });
+    let drain = Duration::from_secs(config.shutdown.drain_seconds);
+    tokio::select! {
+        result = server.into_future() => result?,
+        _ = async {
+            shutdown.wait().await;
+            tokio::time::sleep(drain).await;
+        } => tracing::warn!(?drain, "drain period elapsed, dropping remaining connections"),
+    }
 
-    axum::serve(listener, app).await?;
+    match finish_running_events(&state, config.shutdown.running_events).await {
+        Ok(stopped) if !stopped.is_empty() => tracing::info!(
+            count = stopped.len(),
+            action = ?config.shutdown.running_events,
+            "stopped running events"
+        ),
+        Ok(_) => {}
+        Err(error) => tracing::error!(%error, "could not stop running events"),
+    }
+    pool.close().await;
+    tracing::info!("shutdown complete");
 
     Ok(())
 }
+
+/// Resolves on Ctrl-C, or on SIGTERM where available.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            tracing::error!(%error, "could not listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                tracing::error!(%error, "could not listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
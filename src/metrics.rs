@@ -0,0 +1,156 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::{ApiError, AppState, Storage};
+
+/// Prometheus metrics for one application instance. Request metrics are recorded by
+/// [`track`]; database and event gauges are refreshed whenever `/metrics` is scraped.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    pool_connections: IntGauge,
+    pool_idle_connections: IntGauge,
+    pool_max_connections: IntGauge,
+    running_events: IntGauge,
+    tracked_seconds: Gauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new_custom(Some("rustimenator".to_string()), None)
+            .expect("valid metrics prefix");
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled."),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling HTTP requests.",
+            ),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let pool_connections = IntGauge::new(
+            "db_pool_connections",
+            "Open database connections, idle or in use.",
+        )
+        .expect("valid metric");
+        let pool_idle_connections =
+            IntGauge::new("db_pool_idle_connections", "Idle database connections.")
+                .expect("valid metric");
+        let pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Maximum number of database connections.",
+        )
+        .expect("valid metric");
+        let running_events =
+            IntGauge::new("running_events", "Events currently running.").expect("valid metric");
+        let tracked_seconds = Gauge::new(
+            "tracked_seconds",
+            "Seconds tracked across all events, including running ones.",
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(request_duration.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pool_idle_connections.clone()),
+            Box::new(pool_max_connections.clone()),
+            Box::new(running_events.clone()),
+            Box::new(tracked_seconds.clone()),
+        ] {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            pool_connections,
+            pool_idle_connections,
+            pool_max_connections,
+            running_events,
+            tracked_seconds,
+        }
+    }
+}
+
+/// Counts each request and records its latency, labelled by route template rather than raw
+/// path so ids do not create new series.
+pub async fn track(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let metrics = &state.metrics;
+    metrics
+        .request_duration
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+    metrics
+        .requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "monitoring",
+    summary = "Prometheus metrics",
+    security(()),
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain", body = String),
+        (status = 500, description = "Database error"),
+    )
+)]
+pub async fn metrics(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let totals = state.pool.event_totals().await?;
+
+    let metrics = &state.metrics;
+    metrics.running_events.set(totals.running);
+    metrics.tracked_seconds.set(totals.tracked_seconds);
+    metrics.pool_connections.set(state.pool.size().into());
+    metrics
+        .pool_idle_connections
+        .set(state.pool.num_idle() as i64);
+    metrics
+        .pool_max_connections
+        .set(state.pool.max_connections().into());
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&metrics.registry.gather(), &mut body)
+        .map_err(|error| {
+            tracing::error!(%error, "could not encode metrics");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok((
+        [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,120 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, WatchStream, errors::BroadcastStreamRecvError},
+};
+
+use crate::{DatabasePool, Shutdown, Storage, TimedEvent};
+
+/// What happened to the event carried by an [`EventNotification`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventChange {
+    Started,
+    Stopped,
+    Edited,
+    Deleted,
+}
+
+impl EventChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventChange::Started => "started",
+            EventChange::Stopped => "stopped",
+            EventChange::Edited => "edited",
+            EventChange::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventNotification {
+    pub change: EventChange,
+    pub event: TimedEvent,
+}
+
+/// In-process broadcast of event mutations, shared by every handler through the app state.
+#[derive(Clone)]
+pub struct EventNotifier {
+    sender: broadcast::Sender<EventNotification>,
+}
+
+impl Default for EventNotifier {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl EventNotifier {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EventNotification> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, change: EventChange, event: TimedEvent) {
+        // Sending only fails when nobody is listening, which is fine.
+        let _ = self.sender.send(EventNotification { change, event });
+    }
+
+    /// Loads the event with its task and tags and publishes it. Skips the lookup when there
+    /// are no subscribers; failures are only logged because the mutation is already committed.
+    pub async fn notify(&self, pool: &DatabasePool, change: EventChange, event_id: i64) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        match pool.event(event_id).await {
+            Ok(event) => self.publish(change, event),
+            Err(error) => {
+                tracing::warn!(event_id, %error, "could not load event for notification")
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/events/stream",
+    tag = "events",
+    summary = "Server-sent events for started, stopped, edited and deleted events",
+    description = "Each message is named after the change (`started`, `stopped`, `edited`, `deleted`) and carries the event as JSON. A `lagged` message means notifications were missed and `/events` should be refetched.",
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream", body = String),
+    )
+)]
+pub async fn event_stream(
+    State(notifier): State<EventNotifier>,
+    State(shutdown): State<Shutdown>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = BroadcastStream::new(notifier.subscribe()).map(|message| {
+        let event = match message {
+            Ok(notification) => Event::default()
+                .event(notification.change.as_str())
+                .json_data(&notification.event)
+                .unwrap_or_else(|_| Event::default().event("error")),
+            // The client fell behind and missed notifications; it should refetch `/events`.
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "event stream subscriber lagged");
+                Event::default().event("lagged").data("{}")
+            }
+        };
+        Some(event)
+    });
+    // Ends the stream on shutdown so the connection does not hold up the drain.
+    let closing = WatchStream::new(shutdown.subscribe())
+        .filter(|triggered| *triggered)
+        .map(|_| None);
+    let stream = events.merge(closing).map_while(|event| event).map(Ok);
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
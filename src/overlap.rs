@@ -0,0 +1,199 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::TimedEvent;
+
+/// Whether events may cover the same time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    #[default]
+    Allow,
+    /// Starting an event while another one runs in [`TimerMode::Parallel`](crate::TimerMode),
+    /// and editing an event's times so that it overlaps another event, fail with
+    /// `409 Conflict`.
+    Reject,
+}
+
+/// Two events covering the same time, from `started_at` to `ended_at`. A running event
+/// counts until now.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Overlap {
+    /// The event that started first.
+    pub event_id: i64,
+    pub other_event_id: i64,
+    pub started_at: NaiveDateTime,
+    pub ended_at: NaiveDateTime,
+    pub overlap_seconds: i64,
+}
+
+impl Overlap {
+    pub fn new(
+        event_id: i64,
+        other_event_id: i64,
+        started_at: NaiveDateTime,
+        ended_at: NaiveDateTime,
+    ) -> Self {
+        Self {
+            event_id,
+            other_event_id,
+            started_at,
+            ended_at,
+            overlap_seconds: (ended_at - started_at).num_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetOverlapsQuery {
+    /// Only overlaps between events that both carry this tag.
+    pub tag_id: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetOverlapsResponse {
+    pub overlaps: Vec<Overlap>,
+    pub count: usize,
+    /// Seconds counted more than once, summed over all overlaps.
+    pub overlapping_seconds: i64,
+}
+
+/// How overlapping events are made disjoint. Either way the later event is left as it is, so
+/// events that start at the same time cannot be resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapAction {
+    /// The earlier event ends where the later one starts.
+    Trim,
+    /// Like `Trim`, and when the earlier event ran past the end of the later one, that time
+    /// continues as a new event with the earlier event's task and tags.
+    Split,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolveOverlapsRequest {
+    pub action: OverlapAction,
+    /// Only resolve overlaps between events that both carry this tag.
+    #[serde(default)]
+    pub tag_id: Option<i64>,
+}
+
+/// What [`Storage::resolve_overlaps`](crate::Storage::resolve_overlaps) changed.
+#[derive(Clone, Debug, Default)]
+pub struct ResolvedOverlaps {
+    /// Running events that were trimmed, and so stopped.
+    pub stopped: Vec<i64>,
+    /// Stopped events that were trimmed.
+    pub edited: Vec<i64>,
+    /// Events created for the time after a later event.
+    pub created_event_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ResolveOverlapsResponse {
+    /// The trimmed events followed by the events the resolution created.
+    pub events: Vec<TimedEvent>,
+    pub count: usize,
+}
+
+/// The times of an event, as the resolution plan sees them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct EventSpan {
+    pub id: i64,
+    pub task_id: i64,
+    pub created_at: NaiveDateTime,
+    pub stopped_at: Option<NaiveDateTime>,
+}
+
+/// The changes that make a set of events disjoint.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct OverlapPlan {
+    /// Existing events with their new `stopped_at`, and whether they were running.
+    pub trimmed: Vec<(i64, NaiveDateTime, bool)>,
+    /// New events, with the id of the event whose tags they take.
+    pub created: Vec<(i64, EventSpan)>,
+}
+
+/// Walks `spans` by start time and ends each one where the next one starts. With
+/// [`OverlapAction::Split`] the time an event had left after the next one ends is queued as a
+/// new span, so it is checked against the events after it as well; such a span gives way to
+/// any event it runs into, and is dropped when nothing of it is left. Fails with the ids of
+/// two events that start at the same time.
+pub(crate) fn plan(spans: Vec<EventSpan>, action: OverlapAction) -> Result<OverlapPlan, Vec<i64>> {
+    // Spans from the database keep their id; spans split off them have `None`.
+    let mut planned: Vec<(Option<i64>, EventSpan)> = spans
+        .into_iter()
+        .map(|span| (Some(span.id), span))
+        .collect();
+    let original: Vec<Option<NaiveDateTime>> =
+        planned.iter().map(|(_, span)| span.stopped_at).collect();
+    let mut queue: BinaryHeap<_> = planned
+        .iter()
+        .enumerate()
+        .map(|(index, (_, span))| Reverse((span.created_at, index)))
+        .collect();
+
+    let mut previous: Option<usize> = None;
+    while let Some(Reverse((started_at, index))) = queue.pop() {
+        if let Some(previous) = previous {
+            let earlier = planned[previous].1;
+            if earlier.stopped_at.is_none_or(|end| end > started_at) {
+                if planned[index].0.is_none() && planned[previous].0.is_some() {
+                    // Split-off time starts after the event it runs into, if any is left.
+                    let span = &mut planned[index].1;
+                    match earlier.stopped_at {
+                        Some(end) if span.stopped_at.is_none_or(|stop| stop > end) => {
+                            span.created_at = end;
+                            queue.push(Reverse((end, index)));
+                        }
+                        _ => span.stopped_at = Some(span.created_at),
+                    }
+                    continue;
+                }
+                if let (Some(_), Some(_)) = (planned[previous].0, planned[index].0)
+                    && earlier.created_at == started_at
+                {
+                    return Err(vec![earlier.id, planned[index].1.id]);
+                }
+                planned[previous].1.stopped_at = Some(started_at);
+                let later_end = planned[index].1.stopped_at;
+                let rest = match (earlier.stopped_at, later_end) {
+                    (None, Some(later_end)) => Some((later_end, None)),
+                    (Some(end), Some(later_end)) if end > later_end => Some((later_end, Some(end))),
+                    _ => None,
+                };
+                if let (OverlapAction::Split, Some((created_at, stopped_at))) = (action, rest) {
+                    queue.push(Reverse((created_at, planned.len())));
+                    planned.push((
+                        None,
+                        EventSpan {
+                            created_at,
+                            stopped_at,
+                            ..earlier
+                        },
+                    ));
+                }
+            }
+        }
+        previous = Some(index);
+    }
+
+    let mut plan = OverlapPlan::default();
+    for (index, (id, span)) in planned.into_iter().enumerate() {
+        match id {
+            Some(id) if span.stopped_at != original[index] => {
+                let stopped_at = span.stopped_at.expect("trimmed spans are stopped");
+                plan.trimmed
+                    .push((id, stopped_at, original[index].is_none()));
+            }
+            Some(_) => {}
+            None if span.stopped_at == Some(span.created_at) => {}
+            None => plan.created.push((span.id, span)),
+        }
+    }
+    Ok(plan)
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Interval lengths for [`TimerMode::Pomodoro`](crate::TimerMode::Pomodoro).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    /// Length of a work interval, in seconds.
+    pub work_seconds: u64,
+    pub short_break_seconds: u64,
+    pub long_break_seconds: u64,
+    /// Every this many completed work intervals, the break is a long one.
+    pub long_break_every: u32,
+    /// Start a break event when a work interval ends.
+    pub breaks: bool,
+    /// The task break events are recorded under; created when missing.
+    pub break_task: String,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_seconds: 25 * 60,
+            short_break_seconds: 5 * 60,
+            long_break_seconds: 15 * 60,
+            long_break_every: 4,
+            breaks: true,
+            break_task: "Break".to_string(),
+        }
+    }
+}
+
+impl PomodoroConfig {
+    pub fn seconds(&self, phase: PomodoroPhase) -> u64 {
+        match phase {
+            PomodoroPhase::Work => self.work_seconds,
+            PomodoroPhase::ShortBreak => self.short_break_seconds,
+            PomodoroPhase::LongBreak => self.long_break_seconds,
+        }
+    }
+
+    /// The break that follows the `completed`th work interval since the last long break.
+    pub fn break_after(&self, completed: i64) -> PomodoroPhase {
+        if completed > 0 && completed % i64::from(self.long_break_every.max(1)) == 0 {
+            PomodoroPhase::LongBreak
+        } else {
+            PomodoroPhase::ShortBreak
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PomodoroPhase::Work => "work",
+            PomodoroPhase::ShortBreak => "short_break",
+            PomodoroPhase::LongBreak => "long_break",
+        }
+    }
+
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        match value {
+            "work" => Some(PomodoroPhase::Work),
+            "short_break" => Some(PomodoroPhase::ShortBreak),
+            "long_break" => Some(PomodoroPhase::LongBreak),
+            _ => None,
+        }
+    }
+}
+
+/// A running event that the server stops at `ends_at`.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct PomodoroTimer {
+    pub event_id: i64,
+    pub phase: PomodoroPhase,
+    pub ends_at: chrono::NaiveDateTime,
+}
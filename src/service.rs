@@ -0,0 +1,911 @@
+use std::{fmt, time::Duration};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+
+use crate::{
+    ApiError, AutoStopConfig, Budget, BudgetPeriod, BudgetStatus, CreateBudgetRequest,
+    CreateTagRequest, CreateTagResponse, CreateTaskRequest, CreateTaskResponse,
+    CreateTimedEventRequest, CreateTimedEventResponse, DatabasePool, EventChange, EventCombination,
+    EventCombinationsQuery, EventNotifier, EventStatus, GetBudgetQuery, GetBudgetsQuery,
+    GetEventsQuery, GetIdleGapsQuery, GetOverlapsQuery, HeartbeatResponse, IdempotencyConfig,
+    IdleConfig, IdleGap, IdleResolution, JoinEventsRequest, JoinEventsResponse, Overlap,
+    OverlapPolicy, PomodoroConfig, PomodoroPhase, PomodoroTimer, ResolveIdleGapRequest,
+    ResolveIdleGapResponse, ResolveOverlapsRequest, SetTaskEstimateRequest, Shutdown,
+    SplitEventRequest, SplitEventResponse, StopEventResponse, StoppedEvent, Storage, StorageError,
+    Tag, Task, TaskEstimate, TimedEvent, TimerMode, UpdateTimedEventRequest, ValidationConfig,
+    ValidationErrors, budget::tracked_seconds,
+};
+
+/// Why a [`TimeTracker`] operation failed.
+#[derive(Debug)]
+pub enum TrackerError {
+    /// The request failed validation.
+    Invalid(ValidationErrors),
+    TagNotFound(i64),
+    TaskNotFound(i64),
+    EventNotFound(i64),
+    /// The event does not exist or has already been stopped.
+    EventNotRunning(i64),
+    /// The event has to be stopped before it can be continued.
+    EventStillRunning(i64),
+    IdleGapNotFound(i64),
+    /// The idle gap has already been resolved.
+    IdleGapResolved(i64),
+    BudgetNotFound(i64),
+    /// The tag already has a budget for this period.
+    DuplicateBudget(i64, BudgetPeriod),
+    /// The change would overlap these events and `overlap_policy` rejects overlaps.
+    Overlap(Vec<i64>),
+    DuplicateTag(String),
+    DuplicateTask(String),
+    /// A task or tag id in the request does not exist.
+    UnknownReference,
+    Storage(StorageError),
+}
+
+impl TrackerError {
+    /// Names a storage `NotFound` after the entity the operation addressed.
+    fn or_not_found(error: StorageError, not_found: impl FnOnce() -> TrackerError) -> Self {
+        match error {
+            StorageError::NotFound => not_found(),
+            error => error.into(),
+        }
+    }
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackerError::Invalid(errors) => write!(f, "invalid request: {errors:?}"),
+            TrackerError::TagNotFound(id) => write!(f, "tag {id} not found"),
+            TrackerError::TaskNotFound(id) => write!(f, "task {id} not found"),
+            TrackerError::EventNotFound(id) => write!(f, "event {id} not found"),
+            TrackerError::EventNotRunning(id) => write!(f, "event {id} is not running"),
+            TrackerError::EventStillRunning(id) => write!(f, "event {id} is still running"),
+            TrackerError::IdleGapNotFound(id) => write!(f, "idle gap {id} not found"),
+            TrackerError::IdleGapResolved(id) => write!(f, "idle gap {id} is already resolved"),
+            TrackerError::BudgetNotFound(id) => write!(f, "budget {id} not found"),
+            TrackerError::DuplicateBudget(tag_id, period) => {
+                write!(f, "tag {tag_id} already has a {} budget", period.as_str())
+            }
+            TrackerError::Overlap(ids) => {
+                let ids: Vec<String> = ids.iter().map(i64::to_string).collect();
+                write!(f, "overlaps events {}", ids.join(", "))
+            }
+            TrackerError::DuplicateTag(name) => write!(f, "tag `{name}` already exists"),
+            TrackerError::DuplicateTask(task) => write!(f, "task `{task}` already exists"),
+            TrackerError::UnknownReference => write!(f, "unknown task or tag"),
+            TrackerError::Storage(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TrackerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TrackerError::Storage(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<StorageError> for TrackerError {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::UnknownReference => TrackerError::UnknownReference,
+            StorageError::Invalid(errors) => TrackerError::Invalid(errors),
+            StorageError::Overlap(ids) => TrackerError::Overlap(ids),
+            error => TrackerError::Storage(error),
+        }
+    }
+}
+
+impl From<ValidationErrors> for TrackerError {
+    fn from(errors: ValidationErrors) -> Self {
+        TrackerError::Invalid(errors)
+    }
+}
+
+impl From<TrackerError> for ApiError {
+    fn from(error: TrackerError) -> Self {
+        match error {
+            TrackerError::Invalid(errors) => errors.into(),
+            TrackerError::TagNotFound(_)
+            | TrackerError::TaskNotFound(_)
+            | TrackerError::EventNotFound(_)
+            | TrackerError::EventNotRunning(_)
+            | TrackerError::IdleGapNotFound(_)
+            | TrackerError::BudgetNotFound(_) => StatusCode::NOT_FOUND.into(),
+            TrackerError::EventStillRunning(_)
+            | TrackerError::IdleGapResolved(_)
+            | TrackerError::DuplicateBudget(..)
+            | TrackerError::Overlap(_)
+            | TrackerError::DuplicateTag(_)
+            | TrackerError::DuplicateTask(_) => StatusCode::CONFLICT.into(),
+            TrackerError::UnknownReference => StatusCode::BAD_REQUEST.into(),
+            TrackerError::Storage(error) => error.into(),
+        }
+    }
+}
+
+/// The time tracking operations behind the HTTP and WebSocket APIs, for use without a server.
+/// Requests are validated with `validation`, and every change is published on `notifier` once
+/// it is committed. In [`TimerMode::Pomodoro`] the tracker stops events itself, from tasks that
+/// wait until `shutdown` is triggered at the latest.
+#[derive(Clone)]
+pub struct TimeTracker {
+    pub pool: DatabasePool,
+    pub validation: ValidationConfig,
+    pub timer_mode: TimerMode,
+    pub overlap_policy: OverlapPolicy,
+    pub pomodoro: PomodoroConfig,
+    pub auto_stop: AutoStopConfig,
+    pub idle: IdleConfig,
+    pub idempotency: IdempotencyConfig,
+    pub notifier: EventNotifier,
+    pub shutdown: Shutdown,
+}
+
+impl TimeTracker {
+    pub fn new(pool: impl Into<DatabasePool>) -> Self {
+        Self {
+            pool: pool.into(),
+            validation: ValidationConfig::default(),
+            timer_mode: TimerMode::default(),
+            overlap_policy: OverlapPolicy::default(),
+            pomodoro: PomodoroConfig::default(),
+            auto_stop: AutoStopConfig::default(),
+            idle: IdleConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            notifier: EventNotifier::default(),
+            shutdown: Shutdown::default(),
+        }
+    }
+
+    pub fn with_validation(mut self, validation: ValidationConfig) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn with_timer_mode(mut self, timer_mode: TimerMode) -> Self {
+        self.timer_mode = timer_mode;
+        self
+    }
+
+    pub fn with_overlap_policy(mut self, overlap_policy: OverlapPolicy) -> Self {
+        self.overlap_policy = overlap_policy;
+        self
+    }
+
+    pub fn with_pomodoro(mut self, pomodoro: PomodoroConfig) -> Self {
+        self.pomodoro = pomodoro;
+        self
+    }
+
+    pub fn with_auto_stop(mut self, auto_stop: AutoStopConfig) -> Self {
+        self.auto_stop = auto_stop;
+        self
+    }
+
+    pub fn with_idle(mut self, idle: IdleConfig) -> Self {
+        self.idle = idle;
+        self
+    }
+
+    pub fn with_idempotency(mut self, idempotency: IdempotencyConfig) -> Self {
+        self.idempotency = idempotency;
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: EventNotifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub async fn create_tag(
+        &self,
+        request: CreateTagRequest,
+    ) -> Result<CreateTagResponse, TrackerError> {
+        let request = request.validate(&self.validation)?;
+        let id = match self.pool.create_tag(&request.name).await {
+            Err(StorageError::Conflict) => return Err(TrackerError::DuplicateTag(request.name)),
+            result => result?,
+        };
+        Ok(CreateTagResponse {
+            id,
+            name: request.name,
+            message: "Tag created successfully".to_string(),
+        })
+    }
+
+    /// All tags, newest first.
+    pub async fn tags(&self) -> Result<Vec<Tag>, TrackerError> {
+        Ok(self.pool.tags().await?)
+    }
+
+    pub async fn tag(&self, id: i64) -> Result<Tag, TrackerError> {
+        self.pool
+            .tag(id)
+            .await
+            .map_err(|error| TrackerError::or_not_found(error, || TrackerError::TagNotFound(id)))
+    }
+
+    pub async fn create_task(
+        &self,
+        request: CreateTaskRequest,
+    ) -> Result<CreateTaskResponse, TrackerError> {
+        let request = request.validate(&self.validation)?;
+        let id = match self
+            .pool
+            .create_task(&request.task, request.estimate_seconds)
+            .await
+        {
+            Err(StorageError::Conflict) => return Err(TrackerError::DuplicateTask(request.task)),
+            result => result?,
+        };
+        Ok(CreateTaskResponse {
+            id,
+            task: request.task,
+            message: "Task created successfully".to_string(),
+        })
+    }
+
+    /// All tasks, newest first.
+    pub async fn tasks(&self) -> Result<Vec<Task>, TrackerError> {
+        Ok(self.pool.tasks().await?)
+    }
+
+    pub async fn task(&self, id: i64) -> Result<Task, TrackerError> {
+        self.pool
+            .task(id)
+            .await
+            .map_err(|error| TrackerError::or_not_found(error, || TrackerError::TaskNotFound(id)))
+    }
+
+    /// Sets or clears a task's estimate.
+    pub async fn set_task_estimate(
+        &self,
+        id: i64,
+        request: SetTaskEstimateRequest,
+    ) -> Result<Task, TrackerError> {
+        let request = request.validate()?;
+        self.pool
+            .set_task_estimate(id, request.estimate_seconds)
+            .await
+            .map_err(|error| TrackerError::or_not_found(error, || TrackerError::TaskNotFound(id)))
+    }
+
+    /// A task's estimate against all the time tracked on it.
+    pub async fn task_estimate(&self, id: i64) -> Result<TaskEstimate, TrackerError> {
+        let task = self.task(id).await?;
+        let times = self.pool.event_times(Some(id), None, None, None).await?;
+        let consumed = tracked_seconds(&times, (None, None), Utc::now().naive_utc());
+        Ok(TaskEstimate::new(id, task.estimate_seconds, consumed))
+    }
+
+    pub async fn create_budget(
+        &self,
+        request: CreateBudgetRequest,
+    ) -> Result<Budget, TrackerError> {
+        let request = request.validate()?;
+        match self
+            .pool
+            .create_budget(request.tag_id, request.period, request.seconds)
+            .await
+        {
+            Err(StorageError::Conflict) => Err(TrackerError::DuplicateBudget(
+                request.tag_id,
+                request.period,
+            )),
+            result => Ok(result?),
+        }
+    }
+
+    /// Every budget with the time tracked in its period containing `query.at`, or now.
+    pub async fn budgets(
+        &self,
+        query: &GetBudgetsQuery,
+    ) -> Result<Vec<BudgetStatus>, TrackerError> {
+        let now = Utc::now().naive_utc();
+        let mut statuses = Vec::new();
+        for budget in self.pool.budgets().await? {
+            let status = self
+                .budget_status(budget, query.at.unwrap_or(now), now)
+                .await?;
+            if query
+                .over_budget
+                .is_none_or(|over_budget| over_budget == status.over_budget)
+            {
+                statuses.push(status);
+            }
+        }
+        Ok(statuses)
+    }
+
+    pub async fn budget(
+        &self,
+        id: i64,
+        query: &GetBudgetQuery,
+    ) -> Result<BudgetStatus, TrackerError> {
+        let budget = self.pool.budget(id).await.map_err(|error| {
+            TrackerError::or_not_found(error, || TrackerError::BudgetNotFound(id))
+        })?;
+        let now = Utc::now().naive_utc();
+        self.budget_status(budget, query.at.unwrap_or(now), now)
+            .await
+    }
+
+    pub async fn delete_budget(&self, id: i64) -> Result<(), TrackerError> {
+        self.pool
+            .delete_budget(id)
+            .await
+            .map_err(|error| TrackerError::or_not_found(error, || TrackerError::BudgetNotFound(id)))
+    }
+
+    async fn budget_status(
+        &self,
+        budget: Budget,
+        at: chrono::NaiveDateTime,
+        now: chrono::NaiveDateTime,
+    ) -> Result<BudgetStatus, TrackerError> {
+        let bounds = budget.period.bounds(at).ok_or_else(|| {
+            ValidationErrors::single("at", "is too close to the end of the calendar")
+        })?;
+        let times = self
+            .pool
+            .event_times(None, Some(budget.tag_id), bounds.0, bounds.1)
+            .await?;
+        let consumed = tracked_seconds(&times, bounds, now);
+        Ok(BudgetStatus::new(budget, bounds, consumed))
+    }
+
+    /// Events matching `query` with their task and tags, newest first.
+    pub async fn list_events(
+        &self,
+        query: &GetEventsQuery,
+    ) -> Result<Vec<TimedEvent>, TrackerError> {
+        Ok(self.pool.events(query).await?)
+    }
+
+    pub async fn event(&self, id: i64) -> Result<TimedEvent, TrackerError> {
+        self.pool
+            .event(id)
+            .await
+            .map_err(|error| TrackerError::or_not_found(error, || TrackerError::EventNotFound(id)))
+    }
+
+    /// Starts an event. In [`TimerMode::Single`] and [`TimerMode::Pomodoro`] running events are
+    /// stopped first and returned in `stopped_events`; in the latter the event is a work
+    /// interval that is stopped when its `pomodoro` timer ends.
+    pub async fn start_event(
+        &self,
+        request: CreateTimedEventRequest,
+    ) -> Result<CreateTimedEventResponse, TrackerError> {
+        let request = request.validate(&self.validation)?;
+        if self.timer_mode == TimerMode::Pomodoro {
+            return self.start_work(request.task_id, &request.tag_ids).await;
+        }
+        let response = self
+            .pool
+            .start_event(
+                self.timer_mode,
+                self.overlap_policy,
+                request.task_id,
+                &request.tag_ids,
+            )
+            .await?;
+        self.notify_started(&response).await;
+        Ok(response)
+    }
+
+    /// Starts a new event with the task and tags of a stopped one.
+    pub async fn continue_event(&self, id: i64) -> Result<CreateTimedEventResponse, TrackerError> {
+        if self.timer_mode == TimerMode::Pomodoro {
+            let event = self.event(id).await?;
+            if event.stopped_at.is_none() {
+                return Err(TrackerError::EventStillRunning(id));
+            }
+            let tag_ids: Vec<i64> = event.tags.iter().map(|tag| tag.id).collect();
+            return self.start_work(event.task_id, &tag_ids).await;
+        }
+        let response = match self
+            .pool
+            .continue_event(self.timer_mode, self.overlap_policy, id)
+            .await
+        {
+            Err(StorageError::NotFound) => return Err(TrackerError::EventNotFound(id)),
+            Err(StorageError::Conflict) => return Err(TrackerError::EventStillRunning(id)),
+            result => result?,
+        };
+        self.notify_started(&response).await;
+        Ok(response)
+    }
+
+    pub async fn stop_event(&self, id: i64) -> Result<StopEventResponse, TrackerError> {
+        let response = self.pool.stop_event(id).await.map_err(|error| {
+            TrackerError::or_not_found(error, || TrackerError::EventNotRunning(id))
+        })?;
+        self.notifier
+            .notify(&self.pool, EventChange::Stopped, id)
+            .await;
+        Ok(response)
+    }
+
+    /// Changes an event's task, tags or times, and returns the updated event.
+    pub async fn update_event(
+        &self,
+        id: i64,
+        request: UpdateTimedEventRequest,
+    ) -> Result<TimedEvent, TrackerError> {
+        let request = request.validate(&self.validation)?;
+        let event = self
+            .pool
+            .update_event(id, &request, self.overlap_policy)
+            .await
+            .map_err(|error| {
+                TrackerError::or_not_found(error, || TrackerError::EventNotFound(id))
+            })?;
+        self.notifier.publish(EventChange::Edited, event.clone());
+        Ok(event)
+    }
+
+    /// Ends an event at `request.at` and records the rest of its time as a new event, with the
+    /// requested task and tags or the event's own.
+    pub async fn split_event(
+        &self,
+        id: i64,
+        request: SplitEventRequest,
+    ) -> Result<SplitEventResponse, TrackerError> {
+        let request = request.validate(&self.validation)?;
+        let was_running = self.event(id).await?.stopped_at.is_none();
+        let second = self
+            .pool
+            .split_event(
+                id,
+                request.at,
+                request.task_id,
+                request.tag_ids.as_deref(),
+                self.overlap_policy,
+            )
+            .await
+            .map_err(|error| {
+                TrackerError::or_not_found(error, || TrackerError::EventNotFound(id))
+            })?;
+
+        let first = self.event(id).await?;
+        let second = self.event(second).await?;
+        let change = if was_running {
+            EventChange::Stopped
+        } else {
+            EventChange::Edited
+        };
+        self.notifier.publish(change, first.clone());
+        self.notifier.publish(EventChange::Started, second.clone());
+        // A pending Pomodoro timer moved to the second half along with the end of the event.
+        self.schedule_pending_pomodoro(second.id).await?;
+        Ok(SplitEventResponse { first, second })
+    }
+
+    /// Joins consecutive events of one task into the earliest of them, which keeps the tags of
+    /// all of them; the others are deleted.
+    pub async fn join_events(
+        &self,
+        request: JoinEventsRequest,
+    ) -> Result<JoinEventsResponse, TrackerError> {
+        let request = request.validate()?;
+        let mut events = Vec::with_capacity(request.event_ids.len());
+        for id in &request.event_ids {
+            events.push(self.event(*id).await?);
+        }
+        let timers = self.pool.pending_pomodoros().await?;
+
+        let id = self
+            .pool
+            .join_events(&request.event_ids, self.overlap_policy)
+            .await?;
+        if timers
+            .iter()
+            .any(|timer| timer.event_id != id && request.event_ids.contains(&timer.event_id))
+        {
+            self.schedule_pending_pomodoro(id).await?;
+        }
+        let event = self.event(id).await?;
+        self.notifier.publish(EventChange::Edited, event.clone());
+        let mut deleted_event_ids = Vec::new();
+        for deleted in events.into_iter().filter(|event| event.id != id) {
+            deleted_event_ids.push(deleted.id);
+            self.notifier.publish(EventChange::Deleted, deleted);
+        }
+        Ok(JoinEventsResponse {
+            event,
+            deleted_event_ids,
+        })
+    }
+
+    /// Deletes an event and returns it as it was.
+    pub async fn delete_event(&self, id: i64) -> Result<TimedEvent, TrackerError> {
+        let event = self.pool.delete_event(id).await.map_err(|error| {
+            TrackerError::or_not_found(error, || TrackerError::EventNotFound(id))
+        })?;
+        self.notifier.publish(EventChange::Deleted, event.clone());
+        Ok(event)
+    }
+
+    /// Pairs of events covering the same time, optionally only events with `tag_id`.
+    pub async fn overlaps(&self, query: &GetOverlapsQuery) -> Result<Vec<Overlap>, TrackerError> {
+        Ok(self.pool.overlaps(query.tag_id).await?)
+    }
+
+    /// Trims or splits overlapping events, and returns the trimmed events followed by the
+    /// events the resolution created.
+    pub async fn resolve_overlaps(
+        &self,
+        request: ResolveOverlapsRequest,
+    ) -> Result<Vec<TimedEvent>, TrackerError> {
+        let resolved = self
+            .pool
+            .resolve_overlaps(request.tag_id, request.action)
+            .await?;
+
+        let mut events = Vec::new();
+        let changes = (resolved
+            .stopped
+            .into_iter()
+            .map(|id| (EventChange::Stopped, id)))
+        .chain(
+            resolved
+                .edited
+                .into_iter()
+                .map(|id| (EventChange::Edited, id)),
+        )
+        .chain(
+            resolved
+                .created_event_ids
+                .into_iter()
+                .map(|id| (EventChange::Started, id)),
+        );
+        for (change, id) in changes {
+            let event = self.event(id).await?;
+            self.notifier.publish(change, event.clone());
+            events.push(event);
+        }
+        Ok(events)
+    }
+
+    /// The most used task and tag combinations, by default the top 10 of the last 30 days.
+    pub async fn event_combinations(
+        &self,
+        query: &EventCombinationsQuery,
+    ) -> Result<Vec<EventCombination>, TrackerError> {
+        let limit = query.limit.unwrap_or(10).clamp(1, 100);
+        let days = query.days.unwrap_or(30).max(1);
+        Ok(self.pool.event_combinations(limit, days).await?)
+    }
+
+    /// Stops every running event.
+    pub async fn stop_running_events(&self) -> Result<Vec<StoppedEvent>, TrackerError> {
+        let stopped = self.pool.stop_running_events().await?;
+        self.notify_stopped(&stopped).await;
+        Ok(stopped)
+    }
+
+    /// Stops every running event and records it, to be resumed by
+    /// [`TimeTracker::resume_checkpointed_events`].
+    pub async fn checkpoint_running_events(&self) -> Result<Vec<StoppedEvent>, TrackerError> {
+        let stopped = self.pool.checkpoint_running_events().await?;
+        self.notify_stopped(&stopped).await;
+        Ok(stopped)
+    }
+
+    /// Starts a continuation of every checkpointed event. In [`TimerMode::Pomodoro`] each one
+    /// continues its checkpointed timer, or starts a work interval if it had none.
+    pub async fn resume_checkpointed_events(
+        &self,
+    ) -> Result<Vec<CreateTimedEventResponse>, TrackerError> {
+        let work_seconds = (self.timer_mode == TimerMode::Pomodoro)
+            .then(|| self.pomodoro.seconds(PomodoroPhase::Work));
+        let resumed = self.pool.resume_checkpoints(work_seconds).await?;
+        for response in &resumed {
+            self.notify_started(response).await;
+            if let Some(timer) = &response.pomodoro {
+                self.schedule_pomodoro(timer.clone());
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Stops every running event that is past a limit in `auto_stop`, at the time the limit
+    /// was reached, and marks it with the reason.
+    pub async fn auto_stop_events(&self) -> Result<Vec<StoppedEvent>, TrackerError> {
+        let now = Utc::now().naive_utc();
+        let running = self
+            .pool
+            .events(&GetEventsQuery {
+                status: Some(EventStatus::Running),
+                ..GetEventsQuery::default()
+            })
+            .await?;
+
+        let mut stopped = Vec::new();
+        for event in running {
+            let Some((deadline, reason)) = self.auto_stop.deadline(&event) else {
+                continue;
+            };
+            if deadline > now {
+                continue;
+            }
+            if let Some(event) = self
+                .pool
+                .auto_stop_event(event.id, deadline, reason)
+                .await?
+            {
+                stopped.push(event);
+            }
+        }
+        self.notify_stopped(&stopped).await;
+        Ok(stopped)
+    }
+
+    /// Runs [`TimeTracker::auto_stop_events`] every `auto_stop.interval_seconds` until
+    /// `shutdown` is triggered. Does nothing when no auto-stop limit is configured.
+    pub fn spawn_auto_stop(&self) {
+        if !self.auto_stop.is_enabled() {
+            return;
+        }
+        self.spawn_periodic(self.auto_stop.interval_seconds, |tracker| async move {
+            match tracker.auto_stop_events().await {
+                Ok(stopped) if !stopped.is_empty() => {
+                    tracing::info!(count = stopped.len(), "auto-stopped running events")
+                }
+                Ok(_) => {}
+                Err(error) => tracing::error!(%error, "could not auto-stop running events"),
+            }
+        });
+    }
+
+    /// Records that the client tracking a running event is active. A heartbeat after an idle
+    /// gap ends the gap and returns it.
+    pub async fn heartbeat(&self, event_id: i64) -> Result<HeartbeatResponse, TrackerError> {
+        self.pool.heartbeat(event_id).await.map_err(|error| {
+            TrackerError::or_not_found(error, || TrackerError::EventNotRunning(event_id))
+        })
+    }
+
+    /// Flags running events whose heartbeats stopped more than `idle.timeout_seconds` ago.
+    pub async fn flag_idle_events(&self) -> Result<Vec<IdleGap>, TrackerError> {
+        let timeout = i64::try_from(self.idle.timeout_seconds).unwrap_or(i64::MAX);
+        Ok(self.pool.flag_idle_events(timeout).await?)
+    }
+
+    /// Unresolved idle gaps, or resolved ones with `resolved = Some(true)`, oldest first.
+    pub async fn idle_gaps(&self, query: &GetIdleGapsQuery) -> Result<Vec<IdleGap>, TrackerError> {
+        Ok(self.pool.idle_gaps(query.resolved.unwrap_or(false)).await?)
+    }
+
+    /// Discards, keeps or splits off the time of an idle gap, and returns the gap's event with
+    /// the events the resolution created.
+    pub async fn resolve_idle_gap(
+        &self,
+        id: i64,
+        request: ResolveIdleGapRequest,
+    ) -> Result<ResolveIdleGapResponse, TrackerError> {
+        let resolved = match self
+            .pool
+            .resolve_idle_gap(id, request.resolution, self.overlap_policy)
+            .await
+        {
+            Err(StorageError::NotFound) => return Err(TrackerError::IdleGapNotFound(id)),
+            Err(StorageError::Conflict) => return Err(TrackerError::IdleGapResolved(id)),
+            result => result?,
+        };
+
+        let event_id = resolved.idle_gap.event_id;
+        let mut events = vec![self.event(event_id).await?];
+        if request.resolution != IdleResolution::Keep {
+            let change = if resolved.stopped {
+                EventChange::Stopped
+            } else {
+                EventChange::Edited
+            };
+            self.notifier.publish(change, events[0].clone());
+        }
+        for id in resolved.created_event_ids {
+            let event = self.event(id).await?;
+            self.notifier.publish(EventChange::Started, event.clone());
+            events.push(event);
+        }
+        Ok(ResolveIdleGapResponse {
+            idle_gap: resolved.idle_gap,
+            events,
+        })
+    }
+
+    /// Runs [`TimeTracker::flag_idle_events`] every `idle.interval_seconds` until `shutdown` is
+    /// triggered.
+    pub fn spawn_idle_sweeper(&self) {
+        self.spawn_periodic(self.idle.interval_seconds, |tracker| async move {
+            match tracker.flag_idle_events().await {
+                Ok(gaps) if !gaps.is_empty() => {
+                    tracing::info!(count = gaps.len(), "flagged idle events")
+                }
+                Ok(_) => {}
+                Err(error) => tracing::error!(%error, "could not flag idle events"),
+            }
+        });
+    }
+
+    /// Deletes idempotency keys older than `idempotency.window_seconds` every
+    /// `idempotency.purge_interval_seconds` until `shutdown` is triggered.
+    pub fn spawn_idempotency_purge(&self) {
+        self.spawn_periodic(
+            self.idempotency.purge_interval_seconds,
+            |tracker| async move {
+                let window = tracker.idempotency.window_seconds;
+                if let Err(error) = tracker.pool.purge_idempotency_keys(window).await {
+                    tracing::error!(%error, "could not purge idempotency keys");
+                }
+            },
+        );
+    }
+
+    /// Runs `job` right away and then every `seconds` until `shutdown` is triggered.
+    fn spawn_periodic<F, Fut>(&self, seconds: u64, job: F)
+    where
+        F: Fn(TimeTracker) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let tracker = self.clone();
+        let shutdown = self.shutdown.wait();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(seconds));
+            tokio::pin!(shutdown);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut shutdown => return,
+                }
+                job(tracker.clone()).await;
+            }
+        });
+    }
+
+    /// Schedules every Pomodoro timer left pending by a previous run. Timers that ended while
+    /// the server was down stop their event at the time they ended.
+    pub async fn resume_pomodoros(&self) -> Result<usize, TrackerError> {
+        let timers = self.pool.pending_pomodoros().await?;
+        let count = timers.len();
+        for timer in timers {
+            self.schedule_pomodoro(timer);
+        }
+        Ok(count)
+    }
+
+    /// Schedules the pending timer of `event_id`, after it moved there from another event. The
+    /// task waiting on the old event finds no timer left to finish.
+    async fn schedule_pending_pomodoro(&self, event_id: i64) -> Result<(), TrackerError> {
+        let timers = self.pool.pending_pomodoros().await?;
+        if let Some(timer) = timers.into_iter().find(|timer| timer.event_id == event_id) {
+            self.schedule_pomodoro(timer);
+        }
+        Ok(())
+    }
+
+    async fn start_work(
+        &self,
+        task_id: i64,
+        tag_ids: &[i64],
+    ) -> Result<CreateTimedEventResponse, TrackerError> {
+        let phase = PomodoroPhase::Work;
+        let response = self
+            .pool
+            .start_pomodoro(
+                task_id,
+                tag_ids,
+                phase,
+                self.pomodoro.seconds(phase),
+                self.overlap_policy,
+            )
+            .await?;
+        self.notify_started(&response).await;
+        if let Some(timer) = &response.pomodoro {
+            self.schedule_pomodoro(timer.clone());
+        }
+        Ok(response)
+    }
+
+    /// Waits for `timer` to end, then finishes it.
+    fn schedule_pomodoro(&self, timer: PomodoroTimer) {
+        let tracker = self.clone();
+        let shutdown = self.shutdown.wait();
+        tokio::spawn(async move {
+            let remaining = (timer.ends_at - Utc::now().naive_utc())
+                .to_std()
+                .unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(remaining) => {}
+                _ = shutdown => return,
+            }
+            if let Err(error) = tracker.finish_pomodoro(&timer).await {
+                tracing::error!(%error, event_id = timer.event_id, "could not finish pomodoro");
+            }
+        });
+    }
+
+    /// Stops the event of an ended timer and, after a work interval, starts the break.
+    async fn finish_pomodoro(&self, timer: &PomodoroTimer) -> Result<(), TrackerError> {
+        let Some(stopped) = self.pool.finish_pomodoro(timer.event_id).await? else {
+            return Ok(());
+        };
+        self.notify_stopped(&[stopped]).await;
+        if timer.phase != PomodoroPhase::Work || !self.pomodoro.breaks {
+            return Ok(());
+        }
+
+        let task_id = self.break_task_id().await?;
+        let phase = self
+            .pomodoro
+            .break_after(self.pool.completed_pomodoros().await?);
+        let response = self
+            .pool
+            .start_pomodoro(
+                task_id,
+                &[],
+                phase,
+                self.pomodoro.seconds(phase),
+                self.overlap_policy,
+            )
+            .await?;
+        self.notify_started(&response).await;
+        if let Some(timer) = response.pomodoro {
+            self.schedule_pomodoro(timer);
+        }
+        Ok(())
+    }
+
+    /// The id of the task breaks are recorded under, created on first use.
+    async fn break_task_id(&self) -> Result<i64, TrackerError> {
+        let name = &self.pomodoro.break_task;
+        if let Some(task) = self
+            .pool
+            .tasks()
+            .await?
+            .iter()
+            .find(|task| &task.task == name)
+        {
+            return Ok(task.id);
+        }
+        match self.pool.create_task(name, None).await {
+            Err(StorageError::Conflict) => {}
+            result => return Ok(result?),
+        }
+        // Created concurrently since the lookup.
+        self.pool
+            .tasks()
+            .await?
+            .into_iter()
+            .find(|task| &task.task == name)
+            .map(|task| task.id)
+            .ok_or(TrackerError::DuplicateTask(name.clone()))
+    }
+
+    async fn notify_started(&self, response: &CreateTimedEventResponse) {
+        self.notify_stopped(&response.stopped_events).await;
+        self.notifier
+            .notify(&self.pool, EventChange::Started, response.id)
+            .await;
+    }
+
+    async fn notify_stopped(&self, stopped: &[StoppedEvent]) {
+        for event in stopped {
+            self.notifier
+                .notify(&self.pool, EventChange::Stopped, event.id)
+                .await;
+        }
+    }
+}
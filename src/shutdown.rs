@@ -0,0 +1,93 @@
+use std::{future::Future, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::{AppState, CreateTimedEventResponse, StoppedEvent, TrackerError};
+
+/// Broadcasts that the server is shutting down. Long-lived connections (the event stream and
+/// WebSockets) close when it fires, so graceful shutdown only waits for ordinary requests.
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.sender.subscribe()
+    }
+
+    /// Resolves once [`Shutdown::trigger`] has been called, immediately if it already was.
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut receiver = self.subscribe();
+        async move {
+            let _ = receiver.wait_for(|triggered| *triggered).await;
+        }
+    }
+}
+
+/// What happens to running events when the server shuts down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunningEventsOnShutdown {
+    /// Leave them running; their time keeps counting while the server is down.
+    #[default]
+    Keep,
+    /// Stop them at shutdown.
+    Stop,
+    /// Stop them at shutdown and start them again, with the same task and tags, on the next
+    /// start, so the downtime is not tracked.
+    Checkpoint,
+}
+
+impl std::str::FromStr for RunningEventsOnShutdown {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "keep" => Ok(RunningEventsOnShutdown::Keep),
+            "stop" => Ok(RunningEventsOnShutdown::Stop),
+            "checkpoint" => Ok(RunningEventsOnShutdown::Checkpoint),
+            _ => Err(format!(
+                "unknown shutdown action `{value}`, expected `keep`, `stop` or `checkpoint`"
+            )),
+        }
+    }
+}
+
+/// Applies `action` to the events still running at shutdown and returns the stopped ones.
+pub async fn finish_running_events(
+    state: &AppState,
+    action: RunningEventsOnShutdown,
+) -> Result<Vec<StoppedEvent>, TrackerError> {
+    let tracker = state.tracker();
+    match action {
+        RunningEventsOnShutdown::Keep => Ok(Vec::new()),
+        RunningEventsOnShutdown::Stop => tracker.stop_running_events().await,
+        RunningEventsOnShutdown::Checkpoint => tracker.checkpoint_running_events().await,
+    }
+}
+
+/// Starts a continuation of every event checkpointed at the last shutdown.
+pub async fn resume_checkpointed_events(
+    state: &AppState,
+) -> Result<Vec<CreateTimedEventResponse>, TrackerError> {
+    state.tracker().resume_checkpointed_events().await
+}
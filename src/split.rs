@@ -0,0 +1,41 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::TimedEvent;
+
+/// Ends an event at `at` and records the rest of its time as a new event.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SplitEventRequest {
+    /// Must lie strictly between the event's start and its stop, or now for a running event.
+    pub at: NaiveDateTime,
+    /// Task of the second half; the event's task when absent.
+    #[serde(default)]
+    pub task_id: Option<i64>,
+    /// Tags of the second half; the event's tags when absent.
+    #[serde(default)]
+    pub tag_ids: Option<Vec<i64>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SplitEventResponse {
+    /// The event, now ending at the split.
+    pub first: TimedEvent,
+    /// The new event, from the split to where the event ended; running if it was running.
+    pub second: TimedEvent,
+}
+
+/// Joins consecutive events of one task: no other event of the task may have started between
+/// them, and only the latest may be running.
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct JoinEventsRequest {
+    pub event_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct JoinEventsResponse {
+    /// The earliest event, now running from its start to where the latest one ended, with the
+    /// tags of all of them.
+    pub event: TimedEvent,
+    pub deleted_event_ids: Vec<i64>,
+}
@@ -0,0 +1,875 @@
+use std::{fmt, future::Future};
+
+use axum::http::StatusCode;
+use sqlx::{
+    Pool, Postgres, Sqlite, migrate::Migrator, postgres::PgPoolOptions, sqlite::SqlitePoolOptions,
+};
+
+use crate::{
+    ApiError, AutoStopReason, Budget, BudgetPeriod, CreateTimedEventResponse, DatabaseConfig,
+    EventCombination, GetEventsQuery, HeartbeatResponse, IdleGap, IdleResolution, Overlap,
+    OverlapAction, OverlapPolicy, PomodoroPhase, PomodoroTimer, ResolvedIdleGap, ResolvedOverlaps,
+    StopEventResponse, StoppedEvent, Tag, Task, TimedEvent, TimerMode, UpdateTimedEventRequest,
+    ValidationErrors, internal_error, overlap::EventSpan,
+};
+
+mod postgres;
+mod sqlite;
+
+pub use postgres::PostgresStorage;
+pub use sqlite::SqliteStorage;
+
+/// Why a [`Storage`] operation failed.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The addressed row does not exist.
+    NotFound,
+    /// A unique name is taken, or the row is in the wrong state for the change.
+    Conflict,
+    /// A task or tag id in the request does not exist.
+    UnknownReference,
+    /// The change would leave the row inconsistent.
+    Invalid(ValidationErrors),
+    /// The change would overlap these events and overlaps are rejected.
+    Overlap(Vec<i64>),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::NotFound => write!(f, "not found"),
+            StorageError::Conflict => write!(f, "conflict"),
+            StorageError::UnknownReference => write!(f, "unknown task or tag"),
+            StorageError::Invalid(errors) => write!(f, "invalid change: {errors:?}"),
+            StorageError::Overlap(ids) => write!(f, "overlaps events {ids:?}"),
+            StorageError::Database(error) => write!(f, "database error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<sqlx::Error> for StorageError {
+    fn from(error: sqlx::Error) -> Self {
+        StorageError::Database(error)
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::NotFound => StatusCode::NOT_FOUND.into(),
+            StorageError::Conflict | StorageError::Overlap(_) => StatusCode::CONFLICT.into(),
+            StorageError::UnknownReference => StatusCode::BAD_REQUEST.into(),
+            StorageError::Invalid(errors) => errors.into(),
+            StorageError::Database(error) => internal_error(error).into(),
+        }
+    }
+}
+
+/// A response stored for an `Idempotency-Key`. `status` is `None` while the first request
+/// holding the key is still being processed.
+pub struct StoredResponse {
+    pub status: Option<u16>,
+    pub location: Option<String>,
+    pub body: Vec<u8>,
+    /// Hash of the body of the request that claimed the key; set when the key is reserved, so
+    /// it is ignored when storing.
+    pub request_hash: Option<String>,
+}
+
+/// Totals reported by `/metrics`.
+pub struct EventTotals {
+    pub running: i64,
+    pub tracked_seconds: f64,
+}
+
+/// A row of `_sqlx_migrations`, compared against the embedded migrations by `/readyz`.
+pub struct AppliedMigration {
+    pub version: i64,
+    pub success: bool,
+    pub checksum: Vec<u8>,
+}
+
+/// Everything the server reads from or writes to its database. Each operation is atomic.
+pub trait Storage {
+    fn create_tag(&self, name: &str) -> impl Future<Output = Result<i64, StorageError>> + Send;
+
+    fn tags(&self) -> impl Future<Output = Result<Vec<Tag>, StorageError>> + Send;
+
+    fn tag(&self, id: i64) -> impl Future<Output = Result<Tag, StorageError>> + Send;
+
+    fn create_task(
+        &self,
+        task: &str,
+        estimate_seconds: Option<i64>,
+    ) -> impl Future<Output = Result<i64, StorageError>> + Send;
+
+    fn tasks(&self) -> impl Future<Output = Result<Vec<Task>, StorageError>> + Send;
+
+    fn task(&self, id: i64) -> impl Future<Output = Result<Task, StorageError>> + Send;
+
+    /// Sets or clears a task's estimate and returns the task.
+    fn set_task_estimate(
+        &self,
+        id: i64,
+        estimate_seconds: Option<i64>,
+    ) -> impl Future<Output = Result<Task, StorageError>> + Send;
+
+    /// Events matching `query` with their task and tags, newest first.
+    fn events(
+        &self,
+        query: &GetEventsQuery,
+    ) -> impl Future<Output = Result<Vec<TimedEvent>, StorageError>> + Send;
+
+    /// A single event with its task and tags.
+    fn event(&self, id: i64) -> impl Future<Output = Result<TimedEvent, StorageError>> + Send;
+
+    /// Inserts a running event with its tags, stopping running events first in
+    /// [`TimerMode::Single`] and [`TimerMode::Pomodoro`].
+    ///
+    /// Like every change to an event's span below, this fails with [`StorageError::Overlap`]
+    /// under [`OverlapPolicy::Reject`] when the changed events would overlap others. The check
+    /// runs in the transaction making the change, so concurrent changes cannot both pass it.
+    fn start_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        task_id: i64,
+        tag_ids: &[i64],
+    ) -> impl Future<Output = Result<CreateTimedEventResponse, StorageError>> + Send;
+
+    /// Starts a new event with the task and tags of a stopped one.
+    fn continue_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        id: i64,
+    ) -> impl Future<Output = Result<CreateTimedEventResponse, StorageError>> + Send;
+
+    fn stop_event(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<StopEventResponse, StorageError>> + Send;
+
+    /// Stops every running event and reports how long each one ran.
+    fn stop_running_events(
+        &self,
+    ) -> impl Future<Output = Result<Vec<StoppedEvent>, StorageError>> + Send;
+
+    fn update_event(
+        &self,
+        id: i64,
+        update: &UpdateTimedEventRequest,
+        overlap_policy: OverlapPolicy,
+    ) -> impl Future<Output = Result<TimedEvent, StorageError>> + Send;
+
+    /// Deletes an event and returns it as it was.
+    fn delete_event(
+        &self,
+        id: i64,
+    ) -> impl Future<Output = Result<TimedEvent, StorageError>> + Send;
+
+    /// Task and tag combinations started in the last `days` days, most used first.
+    fn event_combinations(
+        &self,
+        limit: i64,
+        days: i64,
+    ) -> impl Future<Output = Result<Vec<EventCombination>, StorageError>> + Send;
+
+    /// Stops every running event and remembers it for [`Storage::resume_checkpoints`], along
+    /// with the phase and remaining time of its pending Pomodoro timer, which is cancelled.
+    fn checkpoint_running_events(
+        &self,
+    ) -> impl Future<Output = Result<Vec<StoppedEvent>, StorageError>> + Send;
+
+    /// Starts a continuation of every checkpointed event and clears the checkpoints. With
+    /// `pomodoro_work_seconds`, in [`TimerMode::Pomodoro`], each continuation is a Pomodoro
+    /// interval: the rest of its checkpointed timer, or else a work interval this long.
+    fn resume_checkpoints(
+        &self,
+        pomodoro_work_seconds: Option<u64>,
+    ) -> impl Future<Output = Result<Vec<CreateTimedEventResponse>, StorageError>> + Send;
+
+    /// Stops a running event at `stopped_at` and marks it as stopped for `reason`. Returns
+    /// `None` when the event is not running.
+    fn auto_stop_event(
+        &self,
+        id: i64,
+        stopped_at: chrono::NaiveDateTime,
+        reason: AutoStopReason,
+    ) -> impl Future<Output = Result<Option<StoppedEvent>, StorageError>> + Send;
+
+    /// Records a heartbeat for a running event and ends its open idle gap, if any.
+    fn heartbeat(
+        &self,
+        event_id: i64,
+    ) -> impl Future<Output = Result<HeartbeatResponse, StorageError>> + Send;
+
+    /// Opens an idle gap for every running event whose last heartbeat is more than
+    /// `timeout_seconds` old, unless that silence is already covered by a gap.
+    fn flag_idle_events(
+        &self,
+        timeout_seconds: i64,
+    ) -> impl Future<Output = Result<Vec<IdleGap>, StorageError>> + Send;
+
+    /// Resolved or unresolved idle gaps, oldest first.
+    fn idle_gaps(
+        &self,
+        resolved: bool,
+    ) -> impl Future<Output = Result<Vec<IdleGap>, StorageError>> + Send;
+
+    /// Applies `resolution` to an unresolved gap in one transaction. Fails with `Conflict` if
+    /// the gap was already resolved.
+    fn resolve_idle_gap(
+        &self,
+        id: i64,
+        resolution: IdleResolution,
+        overlap_policy: OverlapPolicy,
+    ) -> impl Future<Output = Result<ResolvedIdleGap, StorageError>> + Send;
+
+    /// Pairs of events covering the same time, optionally only events tagged `tag_id`, by
+    /// the start of the overlap.
+    fn overlaps(
+        &self,
+        tag_id: Option<i64>,
+    ) -> impl Future<Output = Result<Vec<Overlap>, StorageError>> + Send;
+
+    /// Ids of the events, other than `exclude`, that overlap the span from `created_at` to
+    /// `stopped_at`. Running events and an open span count as never ending.
+    fn overlapping_events(
+        &self,
+        created_at: chrono::NaiveDateTime,
+        stopped_at: Option<chrono::NaiveDateTime>,
+        exclude: Option<i64>,
+    ) -> impl Future<Output = Result<Vec<i64>, StorageError>> + Send;
+
+    /// Makes the events, optionally only those tagged `tag_id`, disjoint with `action` in one
+    /// transaction. Events created by a split take the task and tags of the event they
+    /// continue.
+    fn resolve_overlaps(
+        &self,
+        tag_id: Option<i64>,
+        action: OverlapAction,
+    ) -> impl Future<Output = Result<ResolvedOverlaps, StorageError>> + Send;
+
+    /// Ends an event at `at` and inserts an event from `at` to where it ended, with `task_id`
+    /// and `tag_ids`, or the event's own when absent. Returns the new event's id.
+    fn split_event(
+        &self,
+        id: i64,
+        at: chrono::NaiveDateTime,
+        task_id: Option<i64>,
+        tag_ids: Option<&[i64]>,
+        overlap_policy: OverlapPolicy,
+    ) -> impl Future<Output = Result<i64, StorageError>> + Send;
+
+    /// Extends the earliest of `ids` to where the latest ended, gives it the tags of all of
+    /// them and deletes the others. Returns the id of the joined event.
+    fn join_events(
+        &self,
+        ids: &[i64],
+        overlap_policy: OverlapPolicy,
+    ) -> impl Future<Output = Result<i64, StorageError>> + Send;
+
+    /// Start and stop times of the events of a task, of a tag, or both, that overlap
+    /// `from`..`to`; either end may be open.
+    fn event_times(
+        &self,
+        task_id: Option<i64>,
+        tag_id: Option<i64>,
+        from: Option<chrono::NaiveDateTime>,
+        to: Option<chrono::NaiveDateTime>,
+    ) -> impl Future<
+        Output = Result<Vec<(chrono::NaiveDateTime, Option<chrono::NaiveDateTime>)>, StorageError>,
+    > + Send;
+
+    /// Fails with [`StorageError::Conflict`] when the tag already has a budget for the period.
+    fn create_budget(
+        &self,
+        tag_id: i64,
+        period: BudgetPeriod,
+        seconds: i64,
+    ) -> impl Future<Output = Result<Budget, StorageError>> + Send;
+
+    /// All budgets, by tag and period.
+    fn budgets(&self) -> impl Future<Output = Result<Vec<Budget>, StorageError>> + Send;
+
+    fn budget(&self, id: i64) -> impl Future<Output = Result<Budget, StorageError>> + Send;
+
+    fn delete_budget(&self, id: i64) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Starts an event like [`TimerMode::Pomodoro`] and schedules its `phase` to end `seconds`
+    /// after it started. The response carries the timer.
+    fn start_pomodoro(
+        &self,
+        task_id: i64,
+        tag_ids: &[i64],
+        phase: PomodoroPhase,
+        seconds: u64,
+        overlap_policy: OverlapPolicy,
+    ) -> impl Future<Output = Result<CreateTimedEventResponse, StorageError>> + Send;
+
+    /// Timers whose phase has not been finished yet, by end time.
+    fn pending_pomodoros(
+        &self,
+    ) -> impl Future<Output = Result<Vec<PomodoroTimer>, StorageError>> + Send;
+
+    /// Finishes a pending timer by stopping its event at `ends_at`. Returns `None`, and
+    /// cancels the timer, when the event was stopped before or the timer is not pending.
+    fn finish_pomodoro(
+        &self,
+        event_id: i64,
+    ) -> impl Future<Output = Result<Option<StoppedEvent>, StorageError>> + Send;
+
+    /// Work intervals completed since the last long break.
+    fn completed_pomodoros(&self) -> impl Future<Output = Result<i64, StorageError>> + Send;
+
+    /// Forgets idempotency keys older than `window_seconds`.
+    fn purge_idempotency_keys(
+        &self,
+        window_seconds: i64,
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Claims `key` for `route` for a request whose body hashes to `request_hash`; `false`
+    /// when it is already claimed. A claim still pending after `pending_seconds` is taken over,
+    /// as the request holding it is assumed lost, and so is any claim older than
+    /// `window_seconds`, whose response is no longer replayed.
+    fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        route: &str,
+        request_hash: &str,
+        pending_seconds: i64,
+        window_seconds: i64,
+    ) -> impl Future<Output = Result<bool, StorageError>> + Send;
+
+    fn release_idempotency_key(
+        &self,
+        key: &str,
+        route: &str,
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    fn store_idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        response: &StoredResponse,
+    ) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// The claim on `key` for `route`, unless it is older than `window_seconds`.
+    fn idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        window_seconds: i64,
+    ) -> impl Future<Output = Result<Option<StoredResponse>, StorageError>> + Send;
+
+    fn event_totals(&self) -> impl Future<Output = Result<EventTotals, StorageError>> + Send;
+
+    /// Fails unless the database answers queries.
+    fn ping(&self) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Fails unless the database accepts writes, e.g. when the file is read-only.
+    fn check_writable(&self) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    fn applied_migrations(
+        &self,
+    ) -> impl Future<Output = Result<Vec<AppliedMigration>, StorageError>> + Send;
+
+    /// The migrations embedded for this backend.
+    fn migrator(&self) -> &'static Migrator;
+}
+
+/// The database the server runs on, chosen from the scheme of the database URL:
+/// `postgres://` or `postgresql://` for Postgres, `sqlite:` or a plain path for SQLite.
+#[derive(Clone, Debug)]
+pub enum DatabasePool {
+    Sqlite(SqliteStorage),
+    Postgres(PostgresStorage),
+}
+
+impl DatabasePool {
+    /// Opens the pool described by `config` and applies pending migrations.
+    pub async fn connect(config: &DatabaseConfig) -> Result<Self, sqlx::Error> {
+        let url = config.url.as_str();
+        let pool = if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let pool = PgPoolOptions::new()
+                .max_connections(config.max_connections)
+                .connect(url)
+                .await?;
+            DatabasePool::from(pool)
+        } else if url.contains("://") && !url.starts_with("sqlite://") {
+            return Err(sqlx::Error::Configuration(
+                format!("unsupported database URL `{url}`, expected sqlite: or postgres://").into(),
+            ));
+        } else {
+            let pool = SqlitePoolOptions::new()
+                .max_connections(config.max_connections)
+                .connect(url)
+                .await?;
+            DatabasePool::from(pool)
+        };
+        match &pool {
+            DatabasePool::Sqlite(storage) => storage.migrator().run(&storage.pool).await?,
+            DatabasePool::Postgres(storage) => storage.migrator().run(&storage.pool).await?,
+        }
+        Ok(pool)
+    }
+
+    /// Open connections, idle or in use.
+    pub fn size(&self) -> u32 {
+        match self {
+            DatabasePool::Sqlite(storage) => storage.pool.size(),
+            DatabasePool::Postgres(storage) => storage.pool.size(),
+        }
+    }
+
+    pub fn num_idle(&self) -> usize {
+        match self {
+            DatabasePool::Sqlite(storage) => storage.pool.num_idle(),
+            DatabasePool::Postgres(storage) => storage.pool.num_idle(),
+        }
+    }
+
+    pub fn max_connections(&self) -> u32 {
+        match self {
+            DatabasePool::Sqlite(storage) => storage.pool.options().get_max_connections(),
+            DatabasePool::Postgres(storage) => storage.pool.options().get_max_connections(),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            DatabasePool::Sqlite(storage) => storage.pool.close().await,
+            DatabasePool::Postgres(storage) => storage.pool.close().await,
+        }
+    }
+}
+
+impl From<Pool<Sqlite>> for DatabasePool {
+    fn from(pool: Pool<Sqlite>) -> Self {
+        DatabasePool::Sqlite(SqliteStorage { pool })
+    }
+}
+
+impl From<Pool<Postgres>> for DatabasePool {
+    fn from(pool: Pool<Postgres>) -> Self {
+        DatabasePool::Postgres(PostgresStorage { pool })
+    }
+}
+
+/// Forwards a [`Storage`] call to the backend in use.
+macro_rules! dispatch {
+    ($self:ident.$method:ident($($arg:expr),*)) => {
+        match $self {
+            DatabasePool::Sqlite(storage) => storage.$method($($arg),*).await,
+            DatabasePool::Postgres(storage) => storage.$method($($arg),*).await,
+        }
+    };
+}
+
+impl Storage for DatabasePool {
+    async fn create_tag(&self, name: &str) -> Result<i64, StorageError> {
+        dispatch!(self.create_tag(name))
+    }
+
+    async fn tags(&self) -> Result<Vec<Tag>, StorageError> {
+        dispatch!(self.tags())
+    }
+
+    async fn tag(&self, id: i64) -> Result<Tag, StorageError> {
+        dispatch!(self.tag(id))
+    }
+
+    async fn create_task(
+        &self,
+        task: &str,
+        estimate_seconds: Option<i64>,
+    ) -> Result<i64, StorageError> {
+        dispatch!(self.create_task(task, estimate_seconds))
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, StorageError> {
+        dispatch!(self.tasks())
+    }
+
+    async fn task(&self, id: i64) -> Result<Task, StorageError> {
+        dispatch!(self.task(id))
+    }
+
+    async fn set_task_estimate(
+        &self,
+        id: i64,
+        estimate_seconds: Option<i64>,
+    ) -> Result<Task, StorageError> {
+        dispatch!(self.set_task_estimate(id, estimate_seconds))
+    }
+
+    async fn events(&self, query: &GetEventsQuery) -> Result<Vec<TimedEvent>, StorageError> {
+        dispatch!(self.events(query))
+    }
+
+    async fn event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        dispatch!(self.event(id))
+    }
+
+    async fn start_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        task_id: i64,
+        tag_ids: &[i64],
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        dispatch!(self.start_event(timer_mode, overlap_policy, task_id, tag_ids))
+    }
+
+    async fn continue_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        id: i64,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        dispatch!(self.continue_event(timer_mode, overlap_policy, id))
+    }
+
+    async fn stop_event(&self, id: i64) -> Result<StopEventResponse, StorageError> {
+        dispatch!(self.stop_event(id))
+    }
+
+    async fn stop_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        dispatch!(self.stop_running_events())
+    }
+
+    async fn update_event(
+        &self,
+        id: i64,
+        update: &UpdateTimedEventRequest,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<TimedEvent, StorageError> {
+        dispatch!(self.update_event(id, update, overlap_policy))
+    }
+
+    async fn delete_event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        dispatch!(self.delete_event(id))
+    }
+
+    async fn event_combinations(
+        &self,
+        limit: i64,
+        days: i64,
+    ) -> Result<Vec<EventCombination>, StorageError> {
+        dispatch!(self.event_combinations(limit, days))
+    }
+
+    async fn checkpoint_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        dispatch!(self.checkpoint_running_events())
+    }
+
+    async fn resume_checkpoints(
+        &self,
+        pomodoro_work_seconds: Option<u64>,
+    ) -> Result<Vec<CreateTimedEventResponse>, StorageError> {
+        dispatch!(self.resume_checkpoints(pomodoro_work_seconds))
+    }
+
+    async fn auto_stop_event(
+        &self,
+        id: i64,
+        stopped_at: chrono::NaiveDateTime,
+        reason: AutoStopReason,
+    ) -> Result<Option<StoppedEvent>, StorageError> {
+        dispatch!(self.auto_stop_event(id, stopped_at, reason))
+    }
+
+    async fn heartbeat(&self, event_id: i64) -> Result<HeartbeatResponse, StorageError> {
+        dispatch!(self.heartbeat(event_id))
+    }
+
+    async fn flag_idle_events(&self, timeout_seconds: i64) -> Result<Vec<IdleGap>, StorageError> {
+        dispatch!(self.flag_idle_events(timeout_seconds))
+    }
+
+    async fn idle_gaps(&self, resolved: bool) -> Result<Vec<IdleGap>, StorageError> {
+        dispatch!(self.idle_gaps(resolved))
+    }
+
+    async fn resolve_idle_gap(
+        &self,
+        id: i64,
+        resolution: IdleResolution,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<ResolvedIdleGap, StorageError> {
+        dispatch!(self.resolve_idle_gap(id, resolution, overlap_policy))
+    }
+
+    async fn overlaps(&self, tag_id: Option<i64>) -> Result<Vec<Overlap>, StorageError> {
+        dispatch!(self.overlaps(tag_id))
+    }
+
+    async fn overlapping_events(
+        &self,
+        created_at: chrono::NaiveDateTime,
+        stopped_at: Option<chrono::NaiveDateTime>,
+        exclude: Option<i64>,
+    ) -> Result<Vec<i64>, StorageError> {
+        dispatch!(self.overlapping_events(created_at, stopped_at, exclude))
+    }
+
+    async fn resolve_overlaps(
+        &self,
+        tag_id: Option<i64>,
+        action: OverlapAction,
+    ) -> Result<ResolvedOverlaps, StorageError> {
+        dispatch!(self.resolve_overlaps(tag_id, action))
+    }
+
+    async fn split_event(
+        &self,
+        id: i64,
+        at: chrono::NaiveDateTime,
+        task_id: Option<i64>,
+        tag_ids: Option<&[i64]>,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        dispatch!(self.split_event(id, at, task_id, tag_ids, overlap_policy))
+    }
+
+    async fn join_events(
+        &self,
+        ids: &[i64],
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        dispatch!(self.join_events(ids, overlap_policy))
+    }
+
+    async fn event_times(
+        &self,
+        task_id: Option<i64>,
+        tag_id: Option<i64>,
+        from: Option<chrono::NaiveDateTime>,
+        to: Option<chrono::NaiveDateTime>,
+    ) -> Result<Vec<(chrono::NaiveDateTime, Option<chrono::NaiveDateTime>)>, StorageError> {
+        dispatch!(self.event_times(task_id, tag_id, from, to))
+    }
+
+    async fn create_budget(
+        &self,
+        tag_id: i64,
+        period: BudgetPeriod,
+        seconds: i64,
+    ) -> Result<Budget, StorageError> {
+        dispatch!(self.create_budget(tag_id, period, seconds))
+    }
+
+    async fn budgets(&self) -> Result<Vec<Budget>, StorageError> {
+        dispatch!(self.budgets())
+    }
+
+    async fn budget(&self, id: i64) -> Result<Budget, StorageError> {
+        dispatch!(self.budget(id))
+    }
+
+    async fn delete_budget(&self, id: i64) -> Result<(), StorageError> {
+        dispatch!(self.delete_budget(id))
+    }
+
+    async fn start_pomodoro(
+        &self,
+        task_id: i64,
+        tag_ids: &[i64],
+        phase: PomodoroPhase,
+        seconds: u64,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        dispatch!(self.start_pomodoro(task_id, tag_ids, phase, seconds, overlap_policy))
+    }
+
+    async fn pending_pomodoros(&self) -> Result<Vec<PomodoroTimer>, StorageError> {
+        dispatch!(self.pending_pomodoros())
+    }
+
+    async fn finish_pomodoro(&self, event_id: i64) -> Result<Option<StoppedEvent>, StorageError> {
+        dispatch!(self.finish_pomodoro(event_id))
+    }
+
+    async fn completed_pomodoros(&self) -> Result<i64, StorageError> {
+        dispatch!(self.completed_pomodoros())
+    }
+
+    async fn purge_idempotency_keys(&self, window_seconds: i64) -> Result<(), StorageError> {
+        dispatch!(self.purge_idempotency_keys(window_seconds))
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        route: &str,
+        request_hash: &str,
+        pending_seconds: i64,
+        window_seconds: i64,
+    ) -> Result<bool, StorageError> {
+        dispatch!(self.reserve_idempotency_key(
+            key,
+            route,
+            request_hash,
+            pending_seconds,
+            window_seconds
+        ))
+    }
+
+    async fn release_idempotency_key(&self, key: &str, route: &str) -> Result<(), StorageError> {
+        dispatch!(self.release_idempotency_key(key, route))
+    }
+
+    async fn store_idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        response: &StoredResponse,
+    ) -> Result<(), StorageError> {
+        dispatch!(self.store_idempotent_response(key, route, response))
+    }
+
+    async fn idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        window_seconds: i64,
+    ) -> Result<Option<StoredResponse>, StorageError> {
+        dispatch!(self.idempotent_response(key, route, window_seconds))
+    }
+
+    async fn event_totals(&self) -> Result<EventTotals, StorageError> {
+        dispatch!(self.event_totals())
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        dispatch!(self.ping())
+    }
+
+    async fn check_writable(&self) -> Result<(), StorageError> {
+        dispatch!(self.check_writable())
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, StorageError> {
+        dispatch!(self.applied_migrations())
+    }
+
+    fn migrator(&self) -> &'static Migrator {
+        match self {
+            DatabasePool::Sqlite(storage) => storage.migrator(),
+            DatabasePool::Postgres(storage) => storage.migrator(),
+        }
+    }
+}
+
+/// Splits the comma separated tag ids built by the combination queries.
+fn parse_tag_key(tag_key: &str) -> impl Iterator<Item = i64> + '_ {
+    tag_key.split(',').filter_map(|id| id.parse().ok())
+}
+
+/// Rejects a change that would end an event before it started.
+fn check_span(
+    created_at: chrono::NaiveDateTime,
+    stopped_at: Option<chrono::NaiveDateTime>,
+) -> Result<(), StorageError> {
+    if stopped_at.is_some_and(|stopped_at| stopped_at < created_at) {
+        return Err(StorageError::Invalid(ValidationErrors::single(
+            "stopped_at",
+            "must not be before created_at",
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a split point outside the event; a running event ends now.
+fn check_split(
+    created_at: chrono::NaiveDateTime,
+    stopped_at: Option<chrono::NaiveDateTime>,
+    at: chrono::NaiveDateTime,
+) -> Result<(), StorageError> {
+    let ends_at = stopped_at.unwrap_or_else(|| chrono::Utc::now().naive_utc());
+    if at <= created_at || at >= ends_at {
+        return Err(StorageError::Invalid(ValidationErrors::single(
+            "at",
+            "must lie between the start and the end of the event",
+        )));
+    }
+    Ok(())
+}
+
+/// Sorts the events to join by start and rejects them unless they share a task and only the
+/// latest is running. Whether they are consecutive is checked by the backends.
+fn check_join(spans: &mut [EventSpan]) -> Result<(), StorageError> {
+    spans.sort_by_key(|span| (span.created_at, span.id));
+    if spans
+        .windows(2)
+        .any(|pair| pair[0].task_id != pair[1].task_id)
+    {
+        return Err(StorageError::Invalid(ValidationErrors::single(
+            "event_ids",
+            "must belong to the same task",
+        )));
+    }
+    if spans[..spans.len() - 1]
+        .iter()
+        .any(|span| span.stopped_at.is_none())
+    {
+        return Err(StorageError::Invalid(ValidationErrors::single(
+            "event_ids",
+            "only the latest event may be running",
+        )));
+    }
+    Ok(())
+}
+
+/// The error for events to join that another event of their task separates.
+fn not_consecutive() -> StorageError {
+    StorageError::Invalid(ValidationErrors::single(
+        "event_ids",
+        "must be consecutive events of the task",
+    ))
+}
+
+fn stopped_event(
+    id: i64,
+    created_at: chrono::NaiveDateTime,
+    stopped_at: chrono::NaiveDateTime,
+) -> StoppedEvent {
+    StoppedEvent {
+        id,
+        duration_seconds: (stopped_at - created_at).num_seconds(),
+    }
+}
+
+fn started(id: i64, stopped_events: Vec<StoppedEvent>) -> CreateTimedEventResponse {
+    CreateTimedEventResponse {
+        id,
+        message: "Event started successfully".to_string(),
+        stopped_events,
+        pomodoro: None,
+    }
+}
+
+fn pomodoro_phase(phase: &str) -> Result<PomodoroPhase, StorageError> {
+    Ok(PomodoroPhase::parse(phase)
+        .ok_or_else(|| sqlx::Error::Decode(format!("unknown pomodoro phase `{phase}`").into()))?)
+}
+
+fn pomodoro_timer(
+    event_id: i64,
+    phase: &str,
+    ends_at: chrono::NaiveDateTime,
+) -> Result<PomodoroTimer, StorageError> {
+    Ok(PomodoroTimer {
+        event_id,
+        phase: pomodoro_phase(phase)?,
+        ends_at,
+    })
+}
+
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(db_err) if db_err.is_unique_violation())
+}
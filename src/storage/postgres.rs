@@ -0,0 +1,1442 @@
+use chrono::NaiveDateTime;
+use sqlx::{Pool, Postgres, Transaction, migrate::Migrator};
+
+use super::{
+    AppliedMigration, EventTotals, Storage, StorageError, StoredResponse, check_join, check_span,
+    check_split, is_unique_violation, not_consecutive, parse_tag_key, pomodoro_phase,
+    pomodoro_timer, started, stopped_event,
+};
+use crate::{
+    AutoStopReason, Budget, BudgetPeriod, CreateTimedEventResponse, EventCombination,
+    GetEventsQuery, HeartbeatResponse, IdleGap, IdleResolution, Overlap, OverlapAction,
+    OverlapPolicy, PomodoroPhase, PomodoroTimer, ResolvedIdleGap, ResolvedOverlaps,
+    StopEventResponse, StoppedEvent, Tag, Task, TimedEvent, TimerMode, UpdateTimedEventRequest,
+    overlap::{self, EventSpan},
+};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Current UTC time without a zone, matching how SQLite stores timestamps.
+const NOW: &str = "(now() AT TIME ZONE 'UTC')";
+
+/// Advisory lock key held while checking for overlaps, so that the checks run one at a time.
+const OVERLAP_LOCK: i64 = 0x006f_7665_726c_6170;
+
+/// Postgres storage. Queries are checked at run time, so building does not need a Postgres
+/// server; the storage tests exercise them when `TEST_POSTGRES_URL` is set.
+#[derive(Clone, Debug)]
+pub struct PostgresStorage {
+    pub(super) pool: Pool<Postgres>,
+}
+
+impl PostgresStorage {
+    async fn load_event_details(&self, event: TimedEvent) -> Result<TimedEvent, StorageError> {
+        let task = sqlx::query_as::<_, Task>(
+            "SELECT id, task, created_at, estimate_seconds FROM tasks WHERE id = $1",
+        )
+        .bind(event.task_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let tags = sqlx::query_as::<_, Tag>(
+            "SELECT t.id, t.name, t.created_at
+             FROM tags t
+             JOIN event_tags et ON et.tag_id = t.id
+             WHERE et.event_id = $1",
+        )
+        .bind(event.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(TimedEvent {
+            task,
+            tags,
+            ..event
+        })
+    }
+}
+
+/// Rejects unknown task or tag ids.
+async fn check_references(
+    tx: &mut Transaction<'_, Postgres>,
+    task_id: Option<i64>,
+    tag_ids: &[i64],
+) -> Result<(), StorageError> {
+    if let Some(task_id) = task_id {
+        let task_exists = sqlx::query("SELECT id FROM tasks WHERE id = $1")
+            .bind(task_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        if task_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+    }
+
+    for tag_id in tag_ids {
+        let tag_exists = sqlx::query("SELECT id FROM tags WHERE id = $1")
+            .bind(tag_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        if tag_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+    }
+
+    Ok(())
+}
+
+async fn stop_running_events(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<StoppedEvent>, StorageError> {
+    let stopped = sqlx::query_as::<_, (i64, NaiveDateTime, NaiveDateTime)>(&format!(
+        "UPDATE events SET stopped_at = {NOW}
+         WHERE stopped_at IS NULL
+         RETURNING id, created_at, stopped_at"
+    ))
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(stopped
+        .into_iter()
+        .map(|(id, created_at, stopped_at)| stopped_event(id, created_at, stopped_at))
+        .collect())
+}
+
+/// With [`OverlapPolicy::Reject`], fails with [`StorageError::Overlap`] when one of `ids`
+/// overlaps another event. Called after the change, in its transaction. The checks take turns
+/// on [`OVERLAP_LOCK`], which is held until commit, so a concurrent change is either seen here
+/// or sees this one.
+async fn check_overlaps(
+    tx: &mut Transaction<'_, Postgres>,
+    overlap_policy: OverlapPolicy,
+    ids: &[i64],
+) -> Result<(), StorageError> {
+    if overlap_policy != OverlapPolicy::Reject {
+        return Ok(());
+    }
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(OVERLAP_LOCK)
+        .execute(&mut **tx)
+        .await?;
+    let mut overlapping = Vec::new();
+    for id in ids {
+        let others: Vec<i64> = sqlx::query_scalar(
+            "SELECT other.id FROM events changed
+             JOIN events other ON other.id <> changed.id
+             WHERE changed.id = $1
+               AND (other.stopped_at IS NULL OR other.stopped_at > changed.created_at)
+               AND (changed.stopped_at IS NULL OR other.created_at < changed.stopped_at)
+             ORDER BY other.created_at, other.id",
+        )
+        .bind(id)
+        .fetch_all(&mut **tx)
+        .await?;
+        for other in others {
+            if !overlapping.contains(&other) {
+                overlapping.push(other);
+            }
+        }
+    }
+    if overlapping.is_empty() {
+        Ok(())
+    } else {
+        Err(StorageError::Overlap(overlapping))
+    }
+}
+
+async fn start_event(
+    tx: &mut Transaction<'_, Postgres>,
+    timer_mode: TimerMode,
+    overlap_policy: OverlapPolicy,
+    task_id: i64,
+    tag_ids: &[i64],
+) -> Result<CreateTimedEventResponse, StorageError> {
+    check_references(tx, Some(task_id), tag_ids).await?;
+
+    let stopped_events = match timer_mode {
+        TimerMode::Single | TimerMode::Pomodoro => stop_running_events(tx).await?,
+        TimerMode::Parallel => Vec::new(),
+    };
+
+    let event_id: i64 = sqlx::query_scalar(&format!(
+        "INSERT INTO events (task_id, created_at, stopped_at) VALUES ($1, {NOW}, NULL)
+         RETURNING id"
+    ))
+    .bind(task_id)
+    .fetch_one(&mut **tx)
+    .await?;
+    insert_event_tags(tx, event_id, tag_ids).await?;
+    check_overlaps(tx, overlap_policy, &[event_id]).await?;
+
+    Ok(started(event_id, stopped_events))
+}
+
+/// Starts an event in [`TimerMode::Pomodoro`] with a timer that ends `seconds` later.
+async fn start_pomodoro(
+    tx: &mut Transaction<'_, Postgres>,
+    overlap_policy: OverlapPolicy,
+    task_id: i64,
+    tag_ids: &[i64],
+    phase: PomodoroPhase,
+    seconds: u64,
+) -> Result<CreateTimedEventResponse, StorageError> {
+    let mut response =
+        start_event(tx, TimerMode::Pomodoro, overlap_policy, task_id, tag_ids).await?;
+
+    let ends_at: NaiveDateTime = sqlx::query_scalar(
+        "INSERT INTO pomodoro_timers (event_id, phase, ends_at)
+         SELECT id, $1, created_at + make_interval(secs => $2::double precision)
+         FROM events WHERE id = $3
+         RETURNING ends_at",
+    )
+    .bind(phase.as_str())
+    .bind(seconds as i64)
+    .bind(response.id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    response.pomodoro = Some(PomodoroTimer {
+        event_id: response.id,
+        phase,
+        ends_at,
+    });
+    Ok(response)
+}
+
+/// Inserts an event with a given span, for events carved out of an existing one.
+async fn insert_event(
+    tx: &mut Transaction<'_, Postgres>,
+    task_id: i64,
+    tag_ids: &[i64],
+    created_at: NaiveDateTime,
+    stopped_at: Option<NaiveDateTime>,
+) -> Result<i64, StorageError> {
+    let event_id: i64 = sqlx::query_scalar(
+        "INSERT INTO events (task_id, created_at, stopped_at) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(task_id)
+    .bind(created_at)
+    .bind(stopped_at)
+    .fetch_one(&mut **tx)
+    .await?;
+    insert_event_tags(tx, event_id, tag_ids).await?;
+    Ok(event_id)
+}
+
+async fn insert_event_tags(
+    tx: &mut Transaction<'_, Postgres>,
+    event_id: i64,
+    tag_ids: &[i64],
+) -> Result<(), StorageError> {
+    for tag_id in tag_ids {
+        sqlx::query("INSERT INTO event_tags (event_id, tag_id) VALUES ($1, $2)")
+            .bind(event_id)
+            .bind(tag_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn event_tag_ids(
+    tx: &mut Transaction<'_, Postgres>,
+    event_id: i64,
+) -> Result<Vec<i64>, StorageError> {
+    Ok(
+        sqlx::query_scalar("SELECT tag_id FROM event_tags WHERE event_id = $1 ORDER BY tag_id")
+            .bind(event_id)
+            .fetch_all(&mut **tx)
+            .await?,
+    )
+}
+
+impl Storage for PostgresStorage {
+    async fn create_tag(&self, name: &str) -> Result<i64, StorageError> {
+        match sqlx::query_scalar("INSERT INTO tags (name) VALUES ($1) RETURNING id")
+            .bind(name)
+            .fetch_one(&self.pool)
+            .await
+        {
+            Ok(id) => Ok(id),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn tags(&self) -> Result<Vec<Tag>, StorageError> {
+        Ok(sqlx::query_as::<_, Tag>(
+            "SELECT id, name, created_at FROM tags ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn tag(&self, id: i64) -> Result<Tag, StorageError> {
+        sqlx::query_as::<_, Tag>("SELECT id, name, created_at FROM tags WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(StorageError::NotFound)
+    }
+
+    async fn create_task(
+        &self,
+        task: &str,
+        estimate_seconds: Option<i64>,
+    ) -> Result<i64, StorageError> {
+        match sqlx::query_scalar(
+            "INSERT INTO tasks (task, estimate_seconds) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(task)
+        .bind(estimate_seconds)
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(id) => Ok(id),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, StorageError> {
+        Ok(sqlx::query_as::<_, Task>(
+            "SELECT id, task, created_at, estimate_seconds FROM tasks ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn task(&self, id: i64) -> Result<Task, StorageError> {
+        sqlx::query_as::<_, Task>(
+            "SELECT id, task, created_at, estimate_seconds FROM tasks WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn set_task_estimate(
+        &self,
+        id: i64,
+        estimate_seconds: Option<i64>,
+    ) -> Result<Task, StorageError> {
+        sqlx::query_as::<_, Task>(
+            "UPDATE tasks SET estimate_seconds = $1 WHERE id = $2
+             RETURNING id, task, created_at, estimate_seconds",
+        )
+        .bind(estimate_seconds)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn events(&self, query: &GetEventsQuery) -> Result<Vec<TimedEvent>, StorageError> {
+        let rows = sqlx::query_as::<_, TimedEvent>(
+            "SELECT id, task_id, created_at, stopped_at, auto_stop_reason
+             FROM events
+             WHERE ($1 = 'all'
+                    OR ($1 = 'running' AND stopped_at IS NULL)
+                    OR ($1 = 'stopped' AND stopped_at IS NOT NULL))
+               AND ($2::timestamp IS NULL OR created_at >= $2)
+               AND ($3::timestamp IS NULL OR created_at < $3)
+               AND ($5::boolean IS NULL OR (auto_stop_reason IS NOT NULL) = $5)
+             ORDER BY created_at DESC
+             LIMIT $4",
+        )
+        .bind(query.status.unwrap_or_default().as_str())
+        .bind(query.since)
+        .bind(query.until)
+        // Postgres has no `LIMIT -1`; a null limit means no limit.
+        .bind(query.limit.filter(|limit| *limit >= 0))
+        .bind(query.auto_stopped)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(self.load_event_details(row).await?);
+        }
+        Ok(events)
+    }
+
+    async fn event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        let event = sqlx::query_as::<_, TimedEvent>(
+            "SELECT id, task_id, created_at, stopped_at, auto_stop_reason
+             FROM events WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        self.load_event_details(event).await
+    }
+
+    async fn start_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        task_id: i64,
+        tag_ids: &[i64],
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let response = start_event(&mut tx, timer_mode, overlap_policy, task_id, tag_ids).await?;
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn continue_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        id: i64,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (task_id, stopped_at) = sqlx::query_as::<_, (i64, Option<NaiveDateTime>)>(
+            "SELECT task_id, stopped_at FROM events WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        if stopped_at.is_none() {
+            return Err(StorageError::Conflict);
+        }
+
+        let tag_ids = event_tag_ids(&mut tx, id).await?;
+        let response = start_event(&mut tx, timer_mode, overlap_policy, task_id, &tag_ids).await?;
+
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn stop_event(&self, id: i64) -> Result<StopEventResponse, StorageError> {
+        let (created_at, stopped_at) =
+            sqlx::query_as::<_, (NaiveDateTime, NaiveDateTime)>(&format!(
+                "UPDATE events SET stopped_at = {NOW}
+                 WHERE id = $1 AND stopped_at IS NULL
+                 RETURNING created_at, stopped_at"
+            ))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+
+        Ok(StopEventResponse {
+            id,
+            message: "Event stopped successfully".to_string(),
+            duration_seconds: (stopped_at - created_at).num_seconds(),
+        })
+    }
+
+    async fn stop_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let stopped = stop_running_events(&mut tx).await?;
+        tx.commit().await?;
+        Ok(stopped)
+    }
+
+    async fn update_event(
+        &self,
+        id: i64,
+        update: &UpdateTimedEventRequest,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<TimedEvent, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (current_task_id, current_created_at, current_stopped_at) =
+            sqlx::query_as::<_, (i64, NaiveDateTime, Option<NaiveDateTime>)>(
+                "SELECT task_id, created_at, stopped_at FROM events WHERE id = $1 FOR UPDATE",
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+
+        let task_id = update.task_id.unwrap_or(current_task_id);
+        let created_at = update.created_at.unwrap_or(current_created_at);
+        let stopped_at = update.stopped_at.or(current_stopped_at);
+        check_span(created_at, stopped_at)?;
+
+        check_references(
+            &mut tx,
+            update.task_id,
+            update.tag_ids.as_deref().unwrap_or_default(),
+        )
+        .await?;
+
+        sqlx::query(
+            "UPDATE events SET task_id = $1, created_at = $2, stopped_at = $3 WHERE id = $4",
+        )
+        .bind(task_id)
+        .bind(created_at)
+        .bind(stopped_at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(tag_ids) = &update.tag_ids {
+            sqlx::query("DELETE FROM event_tags WHERE event_id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+            for tag_id in tag_ids {
+                sqlx::query("INSERT INTO event_tags (event_id, tag_id) VALUES ($1, $2)")
+                    .bind(id)
+                    .bind(tag_id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        check_overlaps(&mut tx, overlap_policy, &[id]).await?;
+
+        tx.commit().await?;
+
+        self.event(id).await
+    }
+
+    async fn delete_event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        let event = self.event(id).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM event_tags WHERE event_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        let deleted = sqlx::query("DELETE FROM events WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn event_combinations(
+        &self,
+        limit: i64,
+        days: i64,
+    ) -> Result<Vec<EventCombination>, StorageError> {
+        let rows = sqlx::query_as::<_, (i64, String, i64, NaiveDateTime)>(&format!(
+            "SELECT task_id, tag_key, COUNT(*), MAX(created_at)
+             FROM (
+                 SELECT e.task_id, e.created_at,
+                        COALESCE((SELECT string_agg(tag_id::text, ',' ORDER BY tag_id)
+                                  FROM event_tags WHERE event_id = e.id), '') AS tag_key
+                 FROM events e
+                 WHERE e.created_at >= {NOW} - make_interval(days => $1::int)
+             ) AS combinations
+             GROUP BY task_id, tag_key
+             ORDER BY 3 DESC, 4 DESC
+             LIMIT $2"
+        ))
+        .bind(days)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut combinations = Vec::new();
+
+        for (task_id, tag_key, uses, last_used_at) in rows {
+            let task = sqlx::query_as::<_, Task>(
+                "SELECT id, task, created_at, estimate_seconds FROM tasks WHERE id = $1",
+            )
+            .bind(task_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            let mut tags = Vec::new();
+            for tag_id in parse_tag_key(&tag_key) {
+                let tag =
+                    sqlx::query_as::<_, Tag>("SELECT id, name, created_at FROM tags WHERE id = $1")
+                        .bind(tag_id)
+                        .fetch_optional(&self.pool)
+                        .await?;
+                tags.extend(tag);
+            }
+
+            combinations.push(EventCombination {
+                task_id,
+                tag_ids: tags.iter().map(|tag| tag.id).collect(),
+                task,
+                tags,
+                uses,
+                last_used_at,
+            });
+        }
+
+        Ok(combinations)
+    }
+
+    async fn checkpoint_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let stopped = stop_running_events(&mut tx).await?;
+        for event in &stopped {
+            let timer = sqlx::query_as::<_, (String, NaiveDateTime)>(
+                "UPDATE pomodoro_timers SET state = 'cancelled'
+                 WHERE event_id = $1 AND state = 'pending'
+                 RETURNING phase, ends_at",
+            )
+            .bind(event.id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            let (phase, seconds) = match timer {
+                Some((phase, ends_at)) => {
+                    let remaining = ends_at - chrono::Utc::now().naive_utc();
+                    (Some(phase), Some(remaining.num_seconds().max(0)))
+                }
+                None => (None, None),
+            };
+            sqlx::query(&format!(
+                "INSERT INTO event_checkpoints (event_id, pomodoro_phase, pomodoro_seconds)
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (event_id) DO UPDATE
+                 SET created_at = {NOW}, pomodoro_phase = $2, pomodoro_seconds = $3"
+            ))
+            .bind(event.id)
+            .bind(phase)
+            .bind(seconds)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(stopped)
+    }
+
+    async fn resume_checkpoints(
+        &self,
+        pomodoro_work_seconds: Option<u64>,
+    ) -> Result<Vec<CreateTimedEventResponse>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let checkpoints = sqlx::query_as::<_, (i64, i64, Option<String>, Option<i64>)>(
+            "SELECT events.id, events.task_id, event_checkpoints.pomodoro_phase,
+                    event_checkpoints.pomodoro_seconds
+             FROM event_checkpoints
+             JOIN events ON events.id = event_checkpoints.event_id
+             ORDER BY events.id",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut resumed = Vec::new();
+        for (event_id, task_id, phase, seconds) in checkpoints {
+            let tag_ids = event_tag_ids(&mut tx, event_id).await?;
+            // Allowing overlaps as the events already ran side by side before the shutdown.
+            let response = match (pomodoro_work_seconds, phase) {
+                (Some(_), Some(phase)) => {
+                    let seconds = seconds.unwrap_or_default().max(0);
+                    start_pomodoro(
+                        &mut tx,
+                        OverlapPolicy::Allow,
+                        task_id,
+                        &tag_ids,
+                        pomodoro_phase(&phase)?,
+                        seconds as u64,
+                    )
+                    .await?
+                }
+                (Some(work_seconds), None) => {
+                    start_pomodoro(
+                        &mut tx,
+                        OverlapPolicy::Allow,
+                        task_id,
+                        &tag_ids,
+                        PomodoroPhase::Work,
+                        work_seconds,
+                    )
+                    .await?
+                }
+                // Parallel so that resuming several checkpoints does not stop the earlier ones.
+                (None, _) => {
+                    start_event(
+                        &mut tx,
+                        TimerMode::Parallel,
+                        OverlapPolicy::Allow,
+                        task_id,
+                        &tag_ids,
+                    )
+                    .await?
+                }
+            };
+            resumed.push(response);
+        }
+
+        sqlx::query("DELETE FROM event_checkpoints")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(resumed)
+    }
+
+    async fn auto_stop_event(
+        &self,
+        id: i64,
+        stopped_at: NaiveDateTime,
+        reason: AutoStopReason,
+    ) -> Result<Option<StoppedEvent>, StorageError> {
+        let stopped = sqlx::query_as::<_, (i64, NaiveDateTime, NaiveDateTime)>(
+            "UPDATE events SET stopped_at = $1, auto_stop_reason = $2
+             WHERE id = $3 AND stopped_at IS NULL
+             RETURNING id, created_at, stopped_at",
+        )
+        .bind(stopped_at)
+        .bind(reason)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(stopped.map(|(id, created_at, stopped_at)| stopped_event(id, created_at, stopped_at)))
+    }
+
+    async fn heartbeat(&self, event_id: i64) -> Result<HeartbeatResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let last_heartbeat_at: NaiveDateTime = sqlx::query_scalar(&format!(
+            "UPDATE events SET last_heartbeat_at = {NOW}
+             WHERE id = $1 AND stopped_at IS NULL
+             RETURNING last_heartbeat_at"
+        ))
+        .bind(event_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        let idle_gap = sqlx::query_as::<_, IdleGap>(
+            "UPDATE idle_gaps SET ended_at = $1
+             WHERE event_id = $2 AND ended_at IS NULL
+             RETURNING id, event_id, started_at, ended_at, resolution",
+        )
+        .bind(last_heartbeat_at)
+        .bind(event_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(HeartbeatResponse {
+            event_id,
+            last_heartbeat_at,
+            idle_gap,
+        })
+    }
+
+    async fn flag_idle_events(&self, timeout_seconds: i64) -> Result<Vec<IdleGap>, StorageError> {
+        Ok(sqlx::query_as::<_, IdleGap>(&format!(
+            "INSERT INTO idle_gaps (event_id, started_at)
+             SELECT id, last_heartbeat_at FROM events
+             WHERE stopped_at IS NULL
+               AND last_heartbeat_at < {NOW} - make_interval(secs => $1::double precision)
+               AND NOT EXISTS (
+                   SELECT 1 FROM idle_gaps
+                   WHERE idle_gaps.event_id = events.id
+                     AND (idle_gaps.ended_at IS NULL
+                          OR idle_gaps.started_at >= events.last_heartbeat_at))
+             RETURNING id, event_id, started_at, ended_at, resolution"
+        ))
+        .bind(timeout_seconds)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn idle_gaps(&self, resolved: bool) -> Result<Vec<IdleGap>, StorageError> {
+        Ok(sqlx::query_as::<_, IdleGap>(
+            "SELECT id, event_id, started_at, ended_at, resolution
+             FROM idle_gaps
+             WHERE (resolution IS NOT NULL) = $1
+             ORDER BY started_at, id",
+        )
+        .bind(resolved)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn resolve_idle_gap(
+        &self,
+        id: i64,
+        resolution: IdleResolution,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<ResolvedIdleGap, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (event_id, started_at, ended_at, resolved, task_id, stopped_at) = sqlx::query_as::<
+            _,
+            (
+                i64,
+                NaiveDateTime,
+                Option<NaiveDateTime>,
+                bool,
+                i64,
+                Option<NaiveDateTime>,
+            ),
+        >(
+            "SELECT g.event_id, g.started_at, g.ended_at, g.resolution IS NOT NULL,
+                        e.task_id, e.stopped_at
+                 FROM idle_gaps g
+                 JOIN events e ON e.id = g.event_id
+                 WHERE g.id = $1
+                 FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+        if resolved {
+            return Err(StorageError::Conflict);
+        }
+
+        let mut created_event_ids = Vec::new();
+        let carve = resolution != IdleResolution::Keep
+            && stopped_at.is_none_or(|stopped_at| stopped_at > started_at);
+        if carve {
+            let tag_ids = event_tag_ids(&mut tx, event_id).await?;
+            sqlx::query("UPDATE events SET stopped_at = $1 WHERE id = $2")
+                .bind(started_at)
+                .bind(event_id)
+                .execute(&mut *tx)
+                .await?;
+
+            let idle_end = ended_at.or(stopped_at);
+            if resolution == IdleResolution::Split {
+                created_event_ids
+                    .push(insert_event(&mut tx, task_id, &tag_ids, started_at, idle_end).await?);
+            }
+            if let Some(ended_at) = ended_at
+                && stopped_at.is_none_or(|stopped_at| stopped_at > ended_at)
+            {
+                created_event_ids
+                    .push(insert_event(&mut tx, task_id, &tag_ids, ended_at, stopped_at).await?);
+            }
+            check_overlaps(&mut tx, overlap_policy, &created_event_ids).await?;
+        }
+
+        let idle_gap = sqlx::query_as::<_, IdleGap>(&format!(
+            "UPDATE idle_gaps SET resolution = $1, ended_at = COALESCE(ended_at, $2, {NOW})
+             WHERE id = $3
+             RETURNING id, event_id, started_at, ended_at, resolution"
+        ))
+        .bind(resolution)
+        .bind(stopped_at)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(ResolvedIdleGap {
+            idle_gap,
+            stopped: carve && stopped_at.is_none(),
+            created_event_ids,
+        })
+    }
+
+    async fn overlaps(&self, tag_id: Option<i64>) -> Result<Vec<Overlap>, StorageError> {
+        let rows = sqlx::query_as::<_, (i64, i64, NaiveDateTime, NaiveDateTime)>(&format!(
+            "SELECT a.id, b.id, GREATEST(a.created_at, b.created_at),
+                    LEAST(COALESCE(a.stopped_at, {NOW}), COALESCE(b.stopped_at, {NOW}))
+             FROM events a
+             JOIN events b
+               ON (a.created_at < b.created_at OR (a.created_at = b.created_at AND a.id < b.id))
+              AND (a.stopped_at IS NULL OR a.stopped_at > b.created_at)
+              AND (b.stopped_at IS NULL OR b.stopped_at > a.created_at)
+             WHERE $1::bigint IS NULL
+                OR (EXISTS (SELECT 1 FROM event_tags WHERE event_id = a.id AND tag_id = $1)
+                    AND EXISTS (SELECT 1 FROM event_tags WHERE event_id = b.id AND tag_id = $1))
+             ORDER BY 3, a.id, b.id"
+        ))
+        .bind(tag_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(event_id, other_event_id, started_at, ended_at)| {
+                Overlap::new(event_id, other_event_id, started_at, ended_at)
+            })
+            .collect())
+    }
+
+    async fn overlapping_events(
+        &self,
+        created_at: NaiveDateTime,
+        stopped_at: Option<NaiveDateTime>,
+        exclude: Option<i64>,
+    ) -> Result<Vec<i64>, StorageError> {
+        Ok(sqlx::query_scalar(
+            "SELECT id FROM events
+             WHERE ($3::bigint IS NULL OR id <> $3)
+               AND (stopped_at IS NULL OR stopped_at > $1)
+               AND ($2::timestamp IS NULL OR created_at < $2)
+             ORDER BY created_at, id",
+        )
+        .bind(created_at)
+        .bind(stopped_at)
+        .bind(exclude)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn resolve_overlaps(
+        &self,
+        tag_id: Option<i64>,
+        action: OverlapAction,
+    ) -> Result<ResolvedOverlaps, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let spans = sqlx::query_as::<_, (i64, i64, NaiveDateTime, Option<NaiveDateTime>)>(
+            "SELECT id, task_id, created_at, stopped_at
+             FROM events
+             WHERE created_at IS NOT NULL
+               AND ($1::bigint IS NULL
+                    OR EXISTS (SELECT 1 FROM event_tags WHERE event_id = events.id AND tag_id = $1))
+             ORDER BY created_at, id
+             FOR UPDATE",
+        )
+        .bind(tag_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|(id, task_id, created_at, stopped_at)| EventSpan {
+            id,
+            task_id,
+            created_at,
+            stopped_at,
+        })
+        .collect();
+
+        let plan = overlap::plan(spans, action).map_err(StorageError::Overlap)?;
+        let mut resolved = ResolvedOverlaps::default();
+        for (id, stopped_at, was_running) in plan.trimmed {
+            sqlx::query("UPDATE events SET stopped_at = $1 WHERE id = $2")
+                .bind(stopped_at)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            if was_running {
+                resolved.stopped.push(id);
+            } else {
+                resolved.edited.push(id);
+            }
+        }
+        for (source, span) in plan.created {
+            let tag_ids = event_tag_ids(&mut tx, source).await?;
+            resolved.created_event_ids.push(
+                insert_event(
+                    &mut tx,
+                    span.task_id,
+                    &tag_ids,
+                    span.created_at,
+                    span.stopped_at,
+                )
+                .await?,
+            );
+        }
+
+        tx.commit().await?;
+        Ok(resolved)
+    }
+
+    async fn split_event(
+        &self,
+        id: i64,
+        at: NaiveDateTime,
+        task_id: Option<i64>,
+        tag_ids: Option<&[i64]>,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (event_task_id, created_at, stopped_at, auto_stop_reason, last_heartbeat_at) =
+            sqlx::query_as::<
+                _,
+                (
+                    i64,
+                    NaiveDateTime,
+                    Option<NaiveDateTime>,
+                    Option<AutoStopReason>,
+                    Option<NaiveDateTime>,
+                ),
+            >(
+                "SELECT task_id, created_at, stopped_at, auto_stop_reason, last_heartbeat_at
+                 FROM events WHERE id = $1 FOR UPDATE",
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+        check_split(created_at, stopped_at, at)?;
+        check_references(&mut tx, task_id, tag_ids.unwrap_or_default()).await?;
+
+        let tag_ids = match tag_ids {
+            Some(tag_ids) => tag_ids.to_vec(),
+            None => event_tag_ids(&mut tx, id).await?,
+        };
+        // An auto-stop reason describes where the event ended, which is now the second half.
+        // Heartbeats, the Pomodoro timer and later idle gaps follow it there too.
+        sqlx::query(
+            "UPDATE events SET stopped_at = $1, auto_stop_reason = NULL, last_heartbeat_at = NULL
+             WHERE id = $2",
+        )
+        .bind(at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        let second = insert_event(
+            &mut tx,
+            task_id.unwrap_or(event_task_id),
+            &tag_ids,
+            at,
+            stopped_at,
+        )
+        .await?;
+        sqlx::query(
+            "UPDATE events SET auto_stop_reason = $1, last_heartbeat_at = $2 WHERE id = $3",
+        )
+        .bind(auto_stop_reason)
+        .bind(last_heartbeat_at.map(|heartbeat| heartbeat.max(at)))
+        .bind(second)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE pomodoro_timers SET event_id = $1 WHERE event_id = $2")
+            .bind(second)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE idle_gaps SET event_id = $1 WHERE event_id = $2 AND started_at >= $3")
+            .bind(second)
+            .bind(id)
+            .bind(at)
+            .execute(&mut *tx)
+            .await?;
+        check_overlaps(&mut tx, overlap_policy, &[id, second]).await?;
+
+        tx.commit().await?;
+        Ok(second)
+    }
+
+    async fn join_events(
+        &self,
+        ids: &[i64],
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut spans = Vec::with_capacity(ids.len());
+        for id in ids {
+            let (id, task_id, created_at, stopped_at) = sqlx::query_as::<
+                _,
+                (i64, i64, NaiveDateTime, Option<NaiveDateTime>),
+            >(
+                "SELECT id, task_id, created_at, stopped_at FROM events WHERE id = $1 FOR UPDATE",
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+            spans.push(EventSpan {
+                id,
+                task_id,
+                created_at,
+                stopped_at,
+            });
+        }
+        check_join(&mut spans)?;
+        for pair in spans.windows(2) {
+            let between: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM events
+                 WHERE task_id = $1 AND created_at > $2 AND created_at < $3",
+            )
+            .bind(pair[0].task_id)
+            .bind(pair[0].created_at)
+            .bind(pair[1].created_at)
+            .fetch_one(&mut *tx)
+            .await?;
+            if between > 0 {
+                return Err(not_consecutive());
+            }
+        }
+
+        let (first, last) = (spans[0], spans[spans.len() - 1]);
+        sqlx::query(
+            "UPDATE events
+             SET stopped_at = $1,
+                 auto_stop_reason = (SELECT auto_stop_reason FROM events WHERE id = $2),
+                 last_heartbeat_at = (SELECT last_heartbeat_at FROM events WHERE id = $2)
+             WHERE id = $3",
+        )
+        .bind(last.stopped_at)
+        .bind(last.id)
+        .bind(first.id)
+        .execute(&mut *tx)
+        .await?;
+
+        let mut tag_ids = event_tag_ids(&mut tx, first.id).await?;
+        let mut added = Vec::new();
+        for span in &spans[1..] {
+            for tag_id in event_tag_ids(&mut tx, span.id).await? {
+                if !tag_ids.contains(&tag_id) {
+                    tag_ids.push(tag_id);
+                    added.push(tag_id);
+                }
+            }
+            // The joined event keeps the timer of the latest span that has one, and every gap.
+            sqlx::query(
+                "DELETE FROM pomodoro_timers
+                 WHERE event_id = $1 AND EXISTS (SELECT 1 FROM pomodoro_timers WHERE event_id = $2)",
+            )
+            .bind(first.id)
+            .bind(span.id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE pomodoro_timers SET event_id = $1 WHERE event_id = $2")
+                .bind(first.id)
+                .bind(span.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE idle_gaps SET event_id = $1 WHERE event_id = $2")
+                .bind(first.id)
+                .bind(span.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM event_tags WHERE event_id = $1")
+                .bind(span.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM events WHERE id = $1")
+                .bind(span.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        insert_event_tags(&mut tx, first.id, &added).await?;
+        check_overlaps(&mut tx, overlap_policy, &[first.id]).await?;
+
+        tx.commit().await?;
+        Ok(first.id)
+    }
+
+    async fn event_times(
+        &self,
+        task_id: Option<i64>,
+        tag_id: Option<i64>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<(NaiveDateTime, Option<NaiveDateTime>)>, StorageError> {
+        Ok(sqlx::query_as::<_, (NaiveDateTime, Option<NaiveDateTime>)>(
+            "SELECT created_at, stopped_at
+             FROM events
+             WHERE created_at IS NOT NULL
+               AND ($1::bigint IS NULL OR task_id = $1)
+               AND ($2::bigint IS NULL OR EXISTS (
+                       SELECT 1 FROM event_tags
+                       WHERE event_tags.event_id = events.id AND event_tags.tag_id = $2))
+               AND ($3::timestamp IS NULL OR stopped_at IS NULL OR stopped_at > $3)
+               AND ($4::timestamp IS NULL OR created_at < $4)",
+        )
+        .bind(task_id)
+        .bind(tag_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn create_budget(
+        &self,
+        tag_id: i64,
+        period: BudgetPeriod,
+        seconds: i64,
+    ) -> Result<Budget, StorageError> {
+        let tag_exists = sqlx::query("SELECT id FROM tags WHERE id = $1")
+            .bind(tag_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if tag_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+
+        match sqlx::query_as::<_, Budget>(
+            "INSERT INTO budgets (tag_id, period, seconds) VALUES ($1, $2, $3)
+             RETURNING id, tag_id, period, seconds, created_at",
+        )
+        .bind(tag_id)
+        .bind(period)
+        .bind(seconds)
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(budget) => Ok(budget),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn budgets(&self) -> Result<Vec<Budget>, StorageError> {
+        Ok(sqlx::query_as::<_, Budget>(
+            "SELECT id, tag_id, period, seconds, created_at
+             FROM budgets
+             ORDER BY tag_id, period",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn budget(&self, id: i64) -> Result<Budget, StorageError> {
+        sqlx::query_as::<_, Budget>(
+            "SELECT id, tag_id, period, seconds, created_at FROM budgets WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn delete_budget(&self, id: i64) -> Result<(), StorageError> {
+        let deleted = sqlx::query("DELETE FROM budgets WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn start_pomodoro(
+        &self,
+        task_id: i64,
+        tag_ids: &[i64],
+        phase: PomodoroPhase,
+        seconds: u64,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let response =
+            start_pomodoro(&mut tx, overlap_policy, task_id, tag_ids, phase, seconds).await?;
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn pending_pomodoros(&self) -> Result<Vec<PomodoroTimer>, StorageError> {
+        let timers = sqlx::query_as::<_, (i64, String, NaiveDateTime)>(
+            "SELECT event_id, phase, ends_at FROM pomodoro_timers WHERE state = 'pending'
+             ORDER BY ends_at, event_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        timers
+            .into_iter()
+            .map(|(event_id, phase, ends_at)| pomodoro_timer(event_id, &phase, ends_at))
+            .collect()
+    }
+
+    async fn finish_pomodoro(&self, event_id: i64) -> Result<Option<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let pending: Option<i64> = sqlx::query_scalar(
+            "SELECT event_id FROM pomodoro_timers
+             WHERE event_id = $1 AND state = 'pending'
+             FOR UPDATE",
+        )
+        .bind(event_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if pending.is_none() {
+            return Ok(None);
+        }
+
+        let stopped = sqlx::query_as::<_, (i64, NaiveDateTime, NaiveDateTime)>(
+            "UPDATE events
+             SET stopped_at = (SELECT ends_at FROM pomodoro_timers WHERE event_id = events.id)
+             WHERE id = $1 AND stopped_at IS NULL
+             RETURNING id, created_at, stopped_at",
+        )
+        .bind(event_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let state = if stopped.is_some() {
+            "completed"
+        } else {
+            "cancelled"
+        };
+        sqlx::query("UPDATE pomodoro_timers SET state = $1 WHERE event_id = $2")
+            .bind(state)
+            .bind(event_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(stopped.map(|(id, created_at, stopped_at)| stopped_event(id, created_at, stopped_at)))
+    }
+
+    async fn completed_pomodoros(&self) -> Result<i64, StorageError> {
+        Ok(sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pomodoro_timers
+             WHERE phase = 'work' AND state = 'completed'
+               AND event_id > COALESCE(
+                   (SELECT MAX(event_id) FROM pomodoro_timers WHERE phase = 'long_break'), 0)",
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn purge_idempotency_keys(&self, window_seconds: i64) -> Result<(), StorageError> {
+        sqlx::query(&format!(
+            "DELETE FROM idempotency_keys
+             WHERE created_at < {NOW} - make_interval(secs => $1::double precision)"
+        ))
+        .bind(window_seconds)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        route: &str,
+        request_hash: &str,
+        pending_seconds: i64,
+        window_seconds: i64,
+    ) -> Result<bool, StorageError> {
+        let reserved = sqlx::query(&format!(
+            "INSERT INTO idempotency_keys (idempotency_key, route, request_hash)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (idempotency_key, route) DO UPDATE
+             SET request_hash = excluded.request_hash, created_at = {NOW},
+                 status = NULL, location = NULL, body = NULL
+             WHERE (idempotency_keys.status IS NULL
+                    AND idempotency_keys.created_at
+                        < {NOW} - make_interval(secs => $4::double precision))
+                OR idempotency_keys.created_at < {NOW} - make_interval(secs => $5::double precision)"
+        ))
+        .bind(key)
+        .bind(route)
+        .bind(request_hash)
+        .bind(pending_seconds)
+        .bind(window_seconds)
+        .execute(&self.pool)
+        .await?;
+        Ok(reserved.rows_affected() == 1)
+    }
+
+    async fn release_idempotency_key(&self, key: &str, route: &str) -> Result<(), StorageError> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE idempotency_key = $1 AND route = $2")
+            .bind(key)
+            .bind(route)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn store_idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        response: &StoredResponse,
+    ) -> Result<(), StorageError> {
+        sqlx::query(
+            "UPDATE idempotency_keys SET status = $1, location = $2, body = $3
+             WHERE idempotency_key = $4 AND route = $5",
+        )
+        .bind(response.status.map(i32::from))
+        .bind(&response.location)
+        .bind(&response.body)
+        .bind(key)
+        .bind(route)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        window_seconds: i64,
+    ) -> Result<Option<StoredResponse>, StorageError> {
+        let stored = sqlx::query_as::<
+            _,
+            (Option<i32>, Option<String>, Option<Vec<u8>>, Option<String>),
+        >(&format!(
+            "SELECT status, location, body, request_hash FROM idempotency_keys
+                     WHERE idempotency_key = $1 AND route = $2
+                       AND created_at >= {NOW} - make_interval(secs => $3::double precision)"
+        ))
+        .bind(key)
+        .bind(route)
+        .bind(window_seconds)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(
+            stored.map(|(status, location, body, request_hash)| StoredResponse {
+                status: status.and_then(|status| u16::try_from(status).ok()),
+                location,
+                body: body.unwrap_or_default(),
+                request_hash,
+            }),
+        )
+    }
+
+    async fn event_totals(&self) -> Result<EventTotals, StorageError> {
+        let (running, tracked_seconds) = sqlx::query_as::<_, (i64, f64)>(&format!(
+            "SELECT
+                COUNT(*) FILTER (WHERE stopped_at IS NULL),
+                COALESCE(SUM(EXTRACT(EPOCH FROM COALESCE(stopped_at, {NOW}) - created_at)), 0)::float8
+            FROM events"
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(EventTotals {
+            running,
+            tracked_seconds,
+        })
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn check_writable(&self) -> Result<(), StorageError> {
+        // Deletes nothing, but fails on a read-only server such as a hot standby.
+        sqlx::query("DELETE FROM idempotency_keys WHERE false")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, StorageError> {
+        let applied = sqlx::query_as::<_, (i64, bool, Vec<u8>)>(
+            "SELECT version, success, checksum FROM _sqlx_migrations",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(applied
+            .into_iter()
+            .map(|(version, success, checksum)| AppliedMigration {
+                version,
+                success,
+                checksum,
+            })
+            .collect())
+    }
+
+    fn migrator(&self) -> &'static Migrator {
+        &MIGRATOR
+    }
+}
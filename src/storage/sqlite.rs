@@ -0,0 +1,1502 @@
+use sqlx::{Pool, Sqlite, Transaction, migrate::Migrator};
+
+use super::{
+    AppliedMigration, EventTotals, Storage, StorageError, StoredResponse, check_join, check_span,
+    check_split, is_unique_violation, not_consecutive, parse_tag_key, pomodoro_phase,
+    pomodoro_timer, started, stopped_event,
+};
+use crate::{
+    AutoStopReason, Budget, BudgetPeriod, CreateTimedEventResponse, EventCombination,
+    GetEventsQuery, HeartbeatResponse, IdleGap, IdleResolution, Overlap, OverlapAction,
+    OverlapPolicy, PomodoroPhase, PomodoroTimer, ResolvedIdleGap, ResolvedOverlaps,
+    StopEventResponse, StoppedEvent, Tag, Task, TimedEvent, TimerMode, UpdateTimedEventRequest,
+    overlap::{self, EventSpan},
+};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// SQLite storage. Queries are checked at build time against the database in `DATABASE_URL`.
+#[derive(Clone, Debug)]
+pub struct SqliteStorage {
+    pub(super) pool: Pool<Sqlite>,
+}
+
+impl SqliteStorage {
+    async fn load_event_details(
+        &self,
+        id: i64,
+        task_id: i64,
+        created_at: chrono::NaiveDateTime,
+        stopped_at: Option<chrono::NaiveDateTime>,
+        auto_stop_reason: Option<AutoStopReason>,
+    ) -> Result<TimedEvent, StorageError> {
+        let task = sqlx::query_as!(
+            Task,
+            "SELECT id as \"id!\", task as \"task!\", created_at, estimate_seconds
+             FROM tasks WHERE id = ?",
+            task_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let tags = sqlx::query_as!(
+            Tag,
+            "SELECT t.id as \"id!\", t.name as \"name!\", t.created_at
+             FROM tags t
+             JOIN event_tags et ON et.tag_id = t.id
+             WHERE et.event_id = ?",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(TimedEvent {
+            id,
+            task_id,
+            task,
+            tags,
+            created_at: Some(created_at),
+            stopped_at,
+            auto_stop_reason,
+        })
+    }
+}
+
+/// Rejects unknown task or tag ids.
+async fn check_references(
+    tx: &mut Transaction<'_, Sqlite>,
+    task_id: Option<i64>,
+    tag_ids: &[i64],
+) -> Result<(), StorageError> {
+    if let Some(task_id) = task_id {
+        let task_exists = sqlx::query!("SELECT id FROM tasks WHERE id = ?", task_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        if task_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+    }
+
+    for tag_id in tag_ids {
+        let tag_exists = sqlx::query!("SELECT id FROM tags WHERE id = ?", tag_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        if tag_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+    }
+
+    Ok(())
+}
+
+async fn stop_running_events(
+    tx: &mut Transaction<'_, Sqlite>,
+) -> Result<Vec<StoppedEvent>, StorageError> {
+    let stopped = sqlx::query!(
+        "UPDATE events SET stopped_at = datetime('now')
+         WHERE stopped_at IS NULL
+         RETURNING id as \"id!\", created_at as \"created_at!: chrono::NaiveDateTime\",
+                   stopped_at as \"stopped_at!: chrono::NaiveDateTime\"",
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(stopped
+        .into_iter()
+        .map(|event| stopped_event(event.id, event.created_at, event.stopped_at))
+        .collect())
+}
+
+/// With [`OverlapPolicy::Reject`], fails with [`StorageError::Overlap`] when one of `ids`
+/// overlaps another event. Called after the change, in its transaction: SQLite lets only one
+/// transaction write at a time, so a concurrent change is either seen here or sees this one.
+async fn check_overlaps(
+    tx: &mut Transaction<'_, Sqlite>,
+    overlap_policy: OverlapPolicy,
+    ids: &[i64],
+) -> Result<(), StorageError> {
+    if overlap_policy != OverlapPolicy::Reject {
+        return Ok(());
+    }
+    let mut overlapping = Vec::new();
+    for id in ids {
+        let others = sqlx::query_scalar!(
+            "SELECT other.id as \"id!\" FROM events changed
+             JOIN events other ON other.id <> changed.id
+             WHERE changed.id = ?
+               AND (other.stopped_at IS NULL OR other.stopped_at > changed.created_at)
+               AND (changed.stopped_at IS NULL OR other.created_at < changed.stopped_at)
+             ORDER BY other.created_at, other.id",
+            id
+        )
+        .fetch_all(&mut **tx)
+        .await?;
+        for other in others {
+            if !overlapping.contains(&other) {
+                overlapping.push(other);
+            }
+        }
+    }
+    if overlapping.is_empty() {
+        Ok(())
+    } else {
+        Err(StorageError::Overlap(overlapping))
+    }
+}
+
+async fn start_event(
+    tx: &mut Transaction<'_, Sqlite>,
+    timer_mode: TimerMode,
+    overlap_policy: OverlapPolicy,
+    task_id: i64,
+    tag_ids: &[i64],
+) -> Result<CreateTimedEventResponse, StorageError> {
+    check_references(tx, Some(task_id), tag_ids).await?;
+
+    let stopped_events = match timer_mode {
+        TimerMode::Single | TimerMode::Pomodoro => stop_running_events(tx).await?,
+        TimerMode::Parallel => Vec::new(),
+    };
+
+    let event_result = sqlx::query!(
+        "INSERT INTO events (task_id, created_at, stopped_at) VALUES (?, datetime('now'), NULL)",
+        task_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    let event_id = event_result.last_insert_rowid();
+    insert_event_tags(tx, event_id, tag_ids).await?;
+    check_overlaps(tx, overlap_policy, &[event_id]).await?;
+
+    Ok(started(event_id, stopped_events))
+}
+
+/// Starts an event in [`TimerMode::Pomodoro`] with a timer that ends `seconds` later.
+async fn start_pomodoro(
+    tx: &mut Transaction<'_, Sqlite>,
+    overlap_policy: OverlapPolicy,
+    task_id: i64,
+    tag_ids: &[i64],
+    phase: PomodoroPhase,
+    seconds: u64,
+) -> Result<CreateTimedEventResponse, StorageError> {
+    let mut response =
+        start_event(tx, TimerMode::Pomodoro, overlap_policy, task_id, tag_ids).await?;
+
+    let phase_name = phase.as_str();
+    let length = format!("+{} seconds", seconds);
+    let timer = sqlx::query!(
+        "INSERT INTO pomodoro_timers (event_id, phase, ends_at)
+         SELECT id, ?, datetime(created_at, ?) FROM events WHERE id = ?
+         RETURNING ends_at as \"ends_at!: chrono::NaiveDateTime\"",
+        phase_name,
+        length,
+        response.id
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    response.pomodoro = Some(PomodoroTimer {
+        event_id: response.id,
+        phase,
+        ends_at: timer.ends_at,
+    });
+    Ok(response)
+}
+
+/// Inserts an event with a given span, for events carved out of an existing one.
+async fn insert_event(
+    tx: &mut Transaction<'_, Sqlite>,
+    task_id: i64,
+    tag_ids: &[i64],
+    created_at: chrono::NaiveDateTime,
+    stopped_at: Option<chrono::NaiveDateTime>,
+) -> Result<i64, StorageError> {
+    let event_id = sqlx::query!(
+        "INSERT INTO events (task_id, created_at, stopped_at) VALUES (?, ?, ?)",
+        task_id,
+        created_at,
+        stopped_at
+    )
+    .execute(&mut **tx)
+    .await?
+    .last_insert_rowid();
+    insert_event_tags(tx, event_id, tag_ids).await?;
+    Ok(event_id)
+}
+
+async fn insert_event_tags(
+    tx: &mut Transaction<'_, Sqlite>,
+    event_id: i64,
+    tag_ids: &[i64],
+) -> Result<(), StorageError> {
+    for tag_id in tag_ids {
+        sqlx::query!(
+            "INSERT INTO event_tags (event_id, tag_id) VALUES (?, ?)",
+            event_id,
+            tag_id
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn event_tag_ids(
+    tx: &mut Transaction<'_, Sqlite>,
+    event_id: i64,
+) -> Result<Vec<i64>, StorageError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT tag_id FROM event_tags WHERE event_id = ? ORDER BY tag_id",
+        event_id
+    )
+    .fetch_all(&mut **tx)
+    .await?)
+}
+
+impl Storage for SqliteStorage {
+    async fn create_tag(&self, name: &str) -> Result<i64, StorageError> {
+        match sqlx::query!("INSERT INTO tags (name) VALUES (?)", name)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(result) => Ok(result.last_insert_rowid()),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn tags(&self) -> Result<Vec<Tag>, StorageError> {
+        Ok(sqlx::query_as!(
+            Tag,
+            "SELECT id, name, created_at FROM tags ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn tag(&self, id: i64) -> Result<Tag, StorageError> {
+        sqlx::query_as!(
+            Tag,
+            "SELECT id, name, created_at FROM tags WHERE id = ?",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn create_task(
+        &self,
+        task: &str,
+        estimate_seconds: Option<i64>,
+    ) -> Result<i64, StorageError> {
+        match sqlx::query!(
+            "INSERT INTO tasks (task, estimate_seconds) VALUES (?, ?)",
+            task,
+            estimate_seconds
+        )
+        .execute(&self.pool)
+        .await
+        {
+            Ok(result) => Ok(result.last_insert_rowid()),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn tasks(&self) -> Result<Vec<Task>, StorageError> {
+        Ok(sqlx::query_as!(
+            Task,
+            "SELECT id, task, created_at, estimate_seconds FROM tasks ORDER BY created_at DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn task(&self, id: i64) -> Result<Task, StorageError> {
+        sqlx::query_as!(
+            Task,
+            "SELECT id, task, created_at, estimate_seconds FROM tasks WHERE id = ?",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn set_task_estimate(
+        &self,
+        id: i64,
+        estimate_seconds: Option<i64>,
+    ) -> Result<Task, StorageError> {
+        sqlx::query_as!(
+            Task,
+            "UPDATE tasks SET estimate_seconds = ? WHERE id = ?
+             RETURNING id as \"id!\", task as \"task!\", created_at, estimate_seconds",
+            estimate_seconds,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn events(&self, query: &GetEventsQuery) -> Result<Vec<TimedEvent>, StorageError> {
+        let status = query.status.unwrap_or_default().as_str();
+        let limit = query.limit.unwrap_or(-1);
+        let rows = sqlx::query!(
+            "SELECT id as \"id!\", task_id as \"task_id!\", created_at, stopped_at,
+                    auto_stop_reason as \"auto_stop_reason: AutoStopReason\"
+             FROM events
+             WHERE (?1 = 'all'
+                    OR (?1 = 'running' AND stopped_at IS NULL)
+                    OR (?1 = 'stopped' AND stopped_at IS NOT NULL))
+               AND (?2 IS NULL OR created_at >= ?2)
+               AND (?3 IS NULL OR created_at < ?3)
+               AND (?5 IS NULL OR (auto_stop_reason IS NOT NULL) = ?5)
+             ORDER BY created_at DESC
+             LIMIT ?4",
+            status,
+            query.since,
+            query.until,
+            limit,
+            query.auto_stopped
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            events.push(
+                self.load_event_details(
+                    row.id,
+                    row.task_id,
+                    row.created_at,
+                    row.stopped_at,
+                    row.auto_stop_reason,
+                )
+                .await?,
+            );
+        }
+        Ok(events)
+    }
+
+    async fn event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        let event = sqlx::query!(
+            "SELECT id as \"id!\", task_id as \"task_id!\", created_at, stopped_at,
+                    auto_stop_reason as \"auto_stop_reason: AutoStopReason\"
+             FROM events
+             WHERE id = ?",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        self.load_event_details(
+            event.id,
+            event.task_id,
+            event.created_at,
+            event.stopped_at,
+            event.auto_stop_reason,
+        )
+        .await
+    }
+
+    async fn start_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        task_id: i64,
+        tag_ids: &[i64],
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let response = start_event(&mut tx, timer_mode, overlap_policy, task_id, tag_ids).await?;
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn continue_event(
+        &self,
+        timer_mode: TimerMode,
+        overlap_policy: OverlapPolicy,
+        id: i64,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let source = sqlx::query!("SELECT task_id, stopped_at FROM events WHERE id = ?", id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+
+        if source.stopped_at.is_none() {
+            return Err(StorageError::Conflict);
+        }
+
+        let tag_ids = event_tag_ids(&mut tx, id).await?;
+        let response = start_event(
+            &mut tx,
+            timer_mode,
+            overlap_policy,
+            source.task_id,
+            &tag_ids,
+        )
+        .await?;
+
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn stop_event(&self, id: i64) -> Result<StopEventResponse, StorageError> {
+        let event = sqlx::query!(
+            "UPDATE events SET stopped_at = datetime('now')
+             WHERE id = ? AND stopped_at IS NULL
+             RETURNING created_at as \"created_at!: chrono::NaiveDateTime\",
+                       stopped_at as \"stopped_at!: chrono::NaiveDateTime\"",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        Ok(StopEventResponse {
+            id,
+            message: "Event stopped successfully".to_string(),
+            duration_seconds: (event.stopped_at - event.created_at).num_seconds(),
+        })
+    }
+
+    async fn stop_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let stopped = stop_running_events(&mut tx).await?;
+        tx.commit().await?;
+        Ok(stopped)
+    }
+
+    async fn update_event(
+        &self,
+        id: i64,
+        update: &UpdateTimedEventRequest,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<TimedEvent, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query!(
+            "SELECT task_id, created_at, stopped_at FROM events WHERE id = ?",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        let task_id = update.task_id.unwrap_or(current.task_id);
+        let created_at = update.created_at.unwrap_or(current.created_at);
+        let stopped_at = update.stopped_at.or(current.stopped_at);
+        check_span(created_at, stopped_at)?;
+
+        check_references(
+            &mut tx,
+            update.task_id,
+            update.tag_ids.as_deref().unwrap_or_default(),
+        )
+        .await?;
+
+        sqlx::query!(
+            "UPDATE events SET task_id = ?, created_at = ?, stopped_at = ? WHERE id = ?",
+            task_id,
+            created_at,
+            stopped_at,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(tag_ids) = &update.tag_ids {
+            sqlx::query!("DELETE FROM event_tags WHERE event_id = ?", id)
+                .execute(&mut *tx)
+                .await?;
+
+            for tag_id in tag_ids {
+                sqlx::query!(
+                    "INSERT INTO event_tags (event_id, tag_id) VALUES (?, ?)",
+                    id,
+                    tag_id
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        check_overlaps(&mut tx, overlap_policy, &[id]).await?;
+
+        tx.commit().await?;
+
+        self.event(id).await
+    }
+
+    async fn delete_event(&self, id: i64) -> Result<TimedEvent, StorageError> {
+        let event = self.event(id).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!("DELETE FROM event_tags WHERE event_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+
+        let deleted = sqlx::query!("DELETE FROM events WHERE id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+
+        if deleted.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn event_combinations(
+        &self,
+        limit: i64,
+        days: i64,
+    ) -> Result<Vec<EventCombination>, StorageError> {
+        let since = format!("-{} days", days);
+
+        let rows = sqlx::query!(
+            "SELECT task_id as \"task_id!\", tag_key as \"tag_key!: String\",
+                    COUNT(*) as \"uses!: i64\",
+                    MAX(created_at) as \"last_used_at!: chrono::NaiveDateTime\"
+             FROM (
+                 SELECT e.task_id, e.created_at,
+                        COALESCE((SELECT group_concat(tag_id, ',')
+                                  FROM (SELECT tag_id FROM event_tags
+                                        WHERE event_id = e.id ORDER BY tag_id)), '') AS tag_key
+                 FROM events e
+                 WHERE e.created_at >= datetime('now', ?)
+             )
+             GROUP BY task_id, tag_key
+             ORDER BY 3 DESC, 4 DESC
+             LIMIT ?",
+            since,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut combinations = Vec::new();
+
+        for row in rows {
+            let task = sqlx::query_as!(
+                Task,
+                "SELECT id, task, created_at, estimate_seconds FROM tasks WHERE id = ?",
+                row.task_id
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+
+            let mut tags = Vec::new();
+            for tag_id in parse_tag_key(&row.tag_key) {
+                let tag = sqlx::query_as!(
+                    Tag,
+                    "SELECT id, name, created_at FROM tags WHERE id = ?",
+                    tag_id
+                )
+                .fetch_optional(&self.pool)
+                .await?;
+                tags.extend(tag);
+            }
+
+            combinations.push(EventCombination {
+                task_id: row.task_id,
+                tag_ids: tags.iter().map(|tag| tag.id).collect(),
+                task,
+                tags,
+                uses: row.uses,
+                last_used_at: row.last_used_at,
+            });
+        }
+
+        Ok(combinations)
+    }
+
+    async fn checkpoint_running_events(&self) -> Result<Vec<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let stopped = stop_running_events(&mut tx).await?;
+        for event in &stopped {
+            let timer = sqlx::query!(
+                "UPDATE pomodoro_timers SET state = 'cancelled'
+                 WHERE event_id = ? AND state = 'pending'
+                 RETURNING phase, ends_at as \"ends_at: chrono::NaiveDateTime\"",
+                event.id
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            let (phase, seconds) = match timer {
+                Some(timer) => {
+                    let remaining = timer.ends_at - chrono::Utc::now().naive_utc();
+                    (Some(timer.phase), Some(remaining.num_seconds().max(0)))
+                }
+                None => (None, None),
+            };
+            sqlx::query!(
+                "INSERT OR REPLACE INTO event_checkpoints (event_id, pomodoro_phase, pomodoro_seconds)
+                 VALUES (?, ?, ?)",
+                event.id,
+                phase,
+                seconds
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(stopped)
+    }
+
+    async fn resume_checkpoints(
+        &self,
+        pomodoro_work_seconds: Option<u64>,
+    ) -> Result<Vec<CreateTimedEventResponse>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let checkpoints = sqlx::query!(
+            "SELECT events.id, events.task_id, event_checkpoints.pomodoro_phase,
+                    event_checkpoints.pomodoro_seconds
+             FROM event_checkpoints
+             JOIN events ON events.id = event_checkpoints.event_id
+             ORDER BY events.id"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut resumed = Vec::new();
+        for checkpoint in checkpoints {
+            let tag_ids = event_tag_ids(&mut tx, checkpoint.id).await?;
+            // Allowing overlaps as the events already ran side by side before the shutdown.
+            let response = match (pomodoro_work_seconds, checkpoint.pomodoro_phase) {
+                (Some(_), Some(phase)) => {
+                    let seconds = checkpoint.pomodoro_seconds.unwrap_or_default().max(0);
+                    start_pomodoro(
+                        &mut tx,
+                        OverlapPolicy::Allow,
+                        checkpoint.task_id,
+                        &tag_ids,
+                        pomodoro_phase(&phase)?,
+                        seconds as u64,
+                    )
+                    .await?
+                }
+                (Some(work_seconds), None) => {
+                    start_pomodoro(
+                        &mut tx,
+                        OverlapPolicy::Allow,
+                        checkpoint.task_id,
+                        &tag_ids,
+                        PomodoroPhase::Work,
+                        work_seconds,
+                    )
+                    .await?
+                }
+                // Parallel so that resuming several checkpoints does not stop the earlier ones.
+                (None, _) => {
+                    start_event(
+                        &mut tx,
+                        TimerMode::Parallel,
+                        OverlapPolicy::Allow,
+                        checkpoint.task_id,
+                        &tag_ids,
+                    )
+                    .await?
+                }
+            };
+            resumed.push(response);
+        }
+
+        sqlx::query!("DELETE FROM event_checkpoints")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(resumed)
+    }
+
+    async fn auto_stop_event(
+        &self,
+        id: i64,
+        stopped_at: chrono::NaiveDateTime,
+        reason: AutoStopReason,
+    ) -> Result<Option<StoppedEvent>, StorageError> {
+        let stopped = sqlx::query!(
+            "UPDATE events SET stopped_at = ?, auto_stop_reason = ?
+             WHERE id = ? AND stopped_at IS NULL
+             RETURNING id as \"id!\", created_at as \"created_at!: chrono::NaiveDateTime\",
+                       stopped_at as \"stopped_at!: chrono::NaiveDateTime\"",
+            stopped_at,
+            reason,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(stopped.map(|event| stopped_event(event.id, event.created_at, event.stopped_at)))
+    }
+
+    async fn heartbeat(&self, event_id: i64) -> Result<HeartbeatResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let last_heartbeat_at = sqlx::query_scalar!(
+            "UPDATE events SET last_heartbeat_at = datetime('now')
+             WHERE id = ? AND stopped_at IS NULL
+             RETURNING last_heartbeat_at as \"last_heartbeat_at!: chrono::NaiveDateTime\"",
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+
+        let idle_gap = sqlx::query_as!(
+            IdleGap,
+            "UPDATE idle_gaps SET ended_at = ?
+             WHERE event_id = ? AND ended_at IS NULL
+             RETURNING id as \"id!\", event_id, started_at as \"started_at: chrono::NaiveDateTime\",
+                       ended_at as \"ended_at: chrono::NaiveDateTime\",
+                       resolution as \"resolution: IdleResolution\"",
+            last_heartbeat_at,
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(HeartbeatResponse {
+            event_id,
+            last_heartbeat_at,
+            idle_gap,
+        })
+    }
+
+    async fn flag_idle_events(&self, timeout_seconds: i64) -> Result<Vec<IdleGap>, StorageError> {
+        let window = format!("-{} seconds", timeout_seconds);
+        Ok(sqlx::query_as!(
+            IdleGap,
+            "INSERT INTO idle_gaps (event_id, started_at)
+             SELECT id, last_heartbeat_at FROM events
+             WHERE stopped_at IS NULL
+               AND last_heartbeat_at < datetime('now', ?)
+               AND NOT EXISTS (
+                   SELECT 1 FROM idle_gaps
+                   WHERE idle_gaps.event_id = events.id
+                     AND (idle_gaps.ended_at IS NULL
+                          OR idle_gaps.started_at >= events.last_heartbeat_at))
+             RETURNING id as \"id!\", event_id, started_at as \"started_at: chrono::NaiveDateTime\",
+                       ended_at as \"ended_at: chrono::NaiveDateTime\",
+                       resolution as \"resolution: IdleResolution\"",
+            window
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn idle_gaps(&self, resolved: bool) -> Result<Vec<IdleGap>, StorageError> {
+        Ok(sqlx::query_as!(
+            IdleGap,
+            "SELECT id as \"id!\", event_id, started_at as \"started_at: chrono::NaiveDateTime\",
+                    ended_at as \"ended_at: chrono::NaiveDateTime\",
+                    resolution as \"resolution: IdleResolution\"
+             FROM idle_gaps
+             WHERE (resolution IS NOT NULL) = ?
+             ORDER BY started_at, id",
+            resolved
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn resolve_idle_gap(
+        &self,
+        id: i64,
+        resolution: IdleResolution,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<ResolvedIdleGap, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let gap = sqlx::query!(
+            "SELECT g.event_id, g.started_at as \"started_at: chrono::NaiveDateTime\",
+                    g.ended_at as \"ended_at: chrono::NaiveDateTime\",
+                    g.resolution IS NOT NULL as \"resolved!: bool\",
+                    e.task_id, e.stopped_at as \"stopped_at: chrono::NaiveDateTime\"
+             FROM idle_gaps g
+             JOIN events e ON e.id = g.event_id
+             WHERE g.id = ?",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+        if gap.resolved {
+            return Err(StorageError::Conflict);
+        }
+
+        let mut created_event_ids = Vec::new();
+        let carve = resolution != IdleResolution::Keep
+            && gap
+                .stopped_at
+                .is_none_or(|stopped_at| stopped_at > gap.started_at);
+        if carve {
+            let tag_ids = event_tag_ids(&mut tx, gap.event_id).await?;
+            sqlx::query!(
+                "UPDATE events SET stopped_at = ? WHERE id = ?",
+                gap.started_at,
+                gap.event_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let idle_end = gap.ended_at.or(gap.stopped_at);
+            if resolution == IdleResolution::Split {
+                created_event_ids.push(
+                    insert_event(&mut tx, gap.task_id, &tag_ids, gap.started_at, idle_end).await?,
+                );
+            }
+            if let Some(ended_at) = gap.ended_at
+                && gap
+                    .stopped_at
+                    .is_none_or(|stopped_at| stopped_at > ended_at)
+            {
+                created_event_ids.push(
+                    insert_event(&mut tx, gap.task_id, &tag_ids, ended_at, gap.stopped_at).await?,
+                );
+            }
+            check_overlaps(&mut tx, overlap_policy, &created_event_ids).await?;
+        }
+
+        let idle_gap = sqlx::query_as!(
+            IdleGap,
+            "UPDATE idle_gaps SET resolution = ?, ended_at = COALESCE(ended_at, ?, datetime('now'))
+             WHERE id = ?
+             RETURNING id as \"id!\", event_id, started_at as \"started_at: chrono::NaiveDateTime\",
+                       ended_at as \"ended_at: chrono::NaiveDateTime\",
+                       resolution as \"resolution: IdleResolution\"",
+            resolution,
+            gap.stopped_at,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(ResolvedIdleGap {
+            idle_gap,
+            stopped: carve && gap.stopped_at.is_none(),
+            created_event_ids,
+        })
+    }
+
+    async fn overlaps(&self, tag_id: Option<i64>) -> Result<Vec<Overlap>, StorageError> {
+        let rows = sqlx::query!(
+            "SELECT a.id as \"event_id!\", b.id as \"other_event_id!\",
+                    MAX(a.created_at, b.created_at) as \"started_at!: chrono::NaiveDateTime\",
+                    MIN(COALESCE(a.stopped_at, datetime('now')),
+                        COALESCE(b.stopped_at, datetime('now')))
+                        as \"ended_at!: chrono::NaiveDateTime\"
+             FROM events a
+             JOIN events b
+               ON (a.created_at < b.created_at OR (a.created_at = b.created_at AND a.id < b.id))
+              AND (a.stopped_at IS NULL OR a.stopped_at > b.created_at)
+              AND (b.stopped_at IS NULL OR b.stopped_at > a.created_at)
+             WHERE ?1 IS NULL
+                OR (EXISTS (SELECT 1 FROM event_tags WHERE event_id = a.id AND tag_id = ?1)
+                    AND EXISTS (SELECT 1 FROM event_tags WHERE event_id = b.id AND tag_id = ?1))
+             ORDER BY 3, a.id, b.id",
+            tag_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                Overlap::new(
+                    row.event_id,
+                    row.other_event_id,
+                    row.started_at,
+                    row.ended_at,
+                )
+            })
+            .collect())
+    }
+
+    async fn overlapping_events(
+        &self,
+        created_at: chrono::NaiveDateTime,
+        stopped_at: Option<chrono::NaiveDateTime>,
+        exclude: Option<i64>,
+    ) -> Result<Vec<i64>, StorageError> {
+        Ok(sqlx::query_scalar!(
+            "SELECT id as \"id!\" FROM events
+             WHERE (?3 IS NULL OR id <> ?3)
+               AND (stopped_at IS NULL OR stopped_at > ?1)
+               AND (?2 IS NULL OR created_at < ?2)
+             ORDER BY created_at, id",
+            created_at,
+            stopped_at,
+            exclude
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn resolve_overlaps(
+        &self,
+        tag_id: Option<i64>,
+        action: OverlapAction,
+    ) -> Result<ResolvedOverlaps, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let spans = sqlx::query_as!(
+            EventSpan,
+            "SELECT id, task_id, created_at as \"created_at!: chrono::NaiveDateTime\",
+                    stopped_at as \"stopped_at: chrono::NaiveDateTime\"
+             FROM events
+             WHERE created_at IS NOT NULL
+               AND (?1 IS NULL
+                    OR EXISTS (SELECT 1 FROM event_tags WHERE event_id = events.id AND tag_id = ?1))
+             ORDER BY created_at, id",
+            tag_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let plan = overlap::plan(spans, action).map_err(StorageError::Overlap)?;
+        let mut resolved = ResolvedOverlaps::default();
+        for (id, stopped_at, was_running) in plan.trimmed {
+            sqlx::query!(
+                "UPDATE events SET stopped_at = ? WHERE id = ?",
+                stopped_at,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+            if was_running {
+                resolved.stopped.push(id);
+            } else {
+                resolved.edited.push(id);
+            }
+        }
+        for (source, span) in plan.created {
+            let tag_ids = event_tag_ids(&mut tx, source).await?;
+            resolved.created_event_ids.push(
+                insert_event(
+                    &mut tx,
+                    span.task_id,
+                    &tag_ids,
+                    span.created_at,
+                    span.stopped_at,
+                )
+                .await?,
+            );
+        }
+
+        tx.commit().await?;
+        Ok(resolved)
+    }
+
+    async fn split_event(
+        &self,
+        id: i64,
+        at: chrono::NaiveDateTime,
+        task_id: Option<i64>,
+        tag_ids: Option<&[i64]>,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let event = sqlx::query!(
+            "SELECT task_id, created_at as \"created_at!: chrono::NaiveDateTime\",
+                    stopped_at as \"stopped_at: chrono::NaiveDateTime\",
+                    auto_stop_reason as \"auto_stop_reason: AutoStopReason\",
+                    last_heartbeat_at as \"last_heartbeat_at: chrono::NaiveDateTime\"
+             FROM events WHERE id = ?",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(StorageError::NotFound)?;
+        check_split(event.created_at, event.stopped_at, at)?;
+        check_references(&mut tx, task_id, tag_ids.unwrap_or_default()).await?;
+
+        let tag_ids = match tag_ids {
+            Some(tag_ids) => tag_ids.to_vec(),
+            None => event_tag_ids(&mut tx, id).await?,
+        };
+        // An auto-stop reason describes where the event ended, which is now the second half.
+        // Heartbeats, the Pomodoro timer and later idle gaps follow it there too.
+        sqlx::query!(
+            "UPDATE events SET stopped_at = ?, auto_stop_reason = NULL, last_heartbeat_at = NULL
+             WHERE id = ?",
+            at,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let second = insert_event(
+            &mut tx,
+            task_id.unwrap_or(event.task_id),
+            &tag_ids,
+            at,
+            event.stopped_at,
+        )
+        .await?;
+        let last_heartbeat_at = event.last_heartbeat_at.map(|heartbeat| heartbeat.max(at));
+        sqlx::query!(
+            "UPDATE events SET auto_stop_reason = ?, last_heartbeat_at = ? WHERE id = ?",
+            event.auto_stop_reason,
+            last_heartbeat_at,
+            second
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE pomodoro_timers SET event_id = ? WHERE event_id = ?",
+            second,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE idle_gaps SET event_id = ? WHERE event_id = ? AND started_at >= ?",
+            second,
+            id,
+            at
+        )
+        .execute(&mut *tx)
+        .await?;
+        check_overlaps(&mut tx, overlap_policy, &[id, second]).await?;
+
+        tx.commit().await?;
+        Ok(second)
+    }
+
+    async fn join_events(
+        &self,
+        ids: &[i64],
+        overlap_policy: OverlapPolicy,
+    ) -> Result<i64, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut spans = Vec::with_capacity(ids.len());
+        for id in ids {
+            let span = sqlx::query_as!(
+                EventSpan,
+                "SELECT id, task_id, created_at as \"created_at!: chrono::NaiveDateTime\",
+                        stopped_at as \"stopped_at: chrono::NaiveDateTime\"
+                 FROM events WHERE id = ?",
+                id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(StorageError::NotFound)?;
+            spans.push(span);
+        }
+        check_join(&mut spans)?;
+        for pair in spans.windows(2) {
+            let between = sqlx::query_scalar!(
+                "SELECT COUNT(*) FROM events WHERE task_id = ? AND created_at > ? AND created_at < ?",
+                pair[0].task_id,
+                pair[0].created_at,
+                pair[1].created_at
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            if between > 0 {
+                return Err(not_consecutive());
+            }
+        }
+
+        let (first, last) = (spans[0], spans[spans.len() - 1]);
+        sqlx::query!(
+            "UPDATE events
+             SET stopped_at = ?,
+                 auto_stop_reason = (SELECT auto_stop_reason FROM events WHERE id = ?),
+                 last_heartbeat_at = (SELECT last_heartbeat_at FROM events WHERE id = ?)
+             WHERE id = ?",
+            last.stopped_at,
+            last.id,
+            last.id,
+            first.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut tag_ids = event_tag_ids(&mut tx, first.id).await?;
+        let mut added = Vec::new();
+        for span in &spans[1..] {
+            for tag_id in event_tag_ids(&mut tx, span.id).await? {
+                if !tag_ids.contains(&tag_id) {
+                    tag_ids.push(tag_id);
+                    added.push(tag_id);
+                }
+            }
+            // The joined event keeps the timer of the latest span that has one, and every gap.
+            sqlx::query!(
+                "DELETE FROM pomodoro_timers
+                 WHERE event_id = ?1 AND EXISTS (SELECT 1 FROM pomodoro_timers WHERE event_id = ?2)",
+                first.id,
+                span.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "UPDATE pomodoro_timers SET event_id = ? WHERE event_id = ?",
+                first.id,
+                span.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "UPDATE idle_gaps SET event_id = ? WHERE event_id = ?",
+                first.id,
+                span.id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!("DELETE FROM event_tags WHERE event_id = ?", span.id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("DELETE FROM events WHERE id = ?", span.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        insert_event_tags(&mut tx, first.id, &added).await?;
+        check_overlaps(&mut tx, overlap_policy, &[first.id]).await?;
+
+        tx.commit().await?;
+        Ok(first.id)
+    }
+
+    async fn event_times(
+        &self,
+        task_id: Option<i64>,
+        tag_id: Option<i64>,
+        from: Option<chrono::NaiveDateTime>,
+        to: Option<chrono::NaiveDateTime>,
+    ) -> Result<Vec<(chrono::NaiveDateTime, Option<chrono::NaiveDateTime>)>, StorageError> {
+        let rows = sqlx::query!(
+            "SELECT created_at as \"created_at!: chrono::NaiveDateTime\",
+                    stopped_at as \"stopped_at: chrono::NaiveDateTime\"
+             FROM events
+             WHERE created_at IS NOT NULL
+               AND (?1 IS NULL OR task_id = ?1)
+               AND (?2 IS NULL OR EXISTS (
+                       SELECT 1 FROM event_tags
+                       WHERE event_tags.event_id = events.id AND event_tags.tag_id = ?2))
+               AND (?3 IS NULL OR stopped_at IS NULL OR stopped_at > ?3)
+               AND (?4 IS NULL OR created_at < ?4)",
+            task_id,
+            tag_id,
+            from,
+            to
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.created_at, row.stopped_at))
+            .collect())
+    }
+
+    async fn create_budget(
+        &self,
+        tag_id: i64,
+        period: BudgetPeriod,
+        seconds: i64,
+    ) -> Result<Budget, StorageError> {
+        let tag_exists = sqlx::query!("SELECT id FROM tags WHERE id = ?", tag_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if tag_exists.is_none() {
+            return Err(StorageError::UnknownReference);
+        }
+
+        match sqlx::query_as!(
+            Budget,
+            "INSERT INTO budgets (tag_id, period, seconds) VALUES (?, ?, ?)
+             RETURNING id as \"id!\", tag_id, period as \"period: BudgetPeriod\", seconds,
+                       created_at as \"created_at: chrono::NaiveDateTime\"",
+            tag_id,
+            period,
+            seconds
+        )
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(budget) => Ok(budget),
+            Err(error) if is_unique_violation(&error) => Err(StorageError::Conflict),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn budgets(&self) -> Result<Vec<Budget>, StorageError> {
+        Ok(sqlx::query_as!(
+            Budget,
+            "SELECT id as \"id!\", tag_id, period as \"period: BudgetPeriod\", seconds, created_at
+             FROM budgets
+             ORDER BY tag_id, period"
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn budget(&self, id: i64) -> Result<Budget, StorageError> {
+        sqlx::query_as!(
+            Budget,
+            "SELECT id as \"id!\", tag_id, period as \"period: BudgetPeriod\", seconds, created_at
+             FROM budgets
+             WHERE id = ?",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(StorageError::NotFound)
+    }
+
+    async fn delete_budget(&self, id: i64) -> Result<(), StorageError> {
+        let deleted = sqlx::query!("DELETE FROM budgets WHERE id = ?", id)
+            .execute(&self.pool)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(StorageError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn start_pomodoro(
+        &self,
+        task_id: i64,
+        tag_ids: &[i64],
+        phase: PomodoroPhase,
+        seconds: u64,
+        overlap_policy: OverlapPolicy,
+    ) -> Result<CreateTimedEventResponse, StorageError> {
+        let mut tx = self.pool.begin().await?;
+        let response =
+            start_pomodoro(&mut tx, overlap_policy, task_id, tag_ids, phase, seconds).await?;
+        tx.commit().await?;
+        Ok(response)
+    }
+
+    async fn pending_pomodoros(&self) -> Result<Vec<PomodoroTimer>, StorageError> {
+        let timers = sqlx::query!(
+            "SELECT event_id, phase, ends_at as \"ends_at: chrono::NaiveDateTime\"
+             FROM pomodoro_timers WHERE state = 'pending'
+             ORDER BY ends_at, event_id"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        timers
+            .into_iter()
+            .map(|timer| pomodoro_timer(timer.event_id, &timer.phase, timer.ends_at))
+            .collect()
+    }
+
+    async fn finish_pomodoro(&self, event_id: i64) -> Result<Option<StoppedEvent>, StorageError> {
+        let mut tx = self.pool.begin().await?;
+
+        let pending = sqlx::query!(
+            "SELECT event_id FROM pomodoro_timers WHERE event_id = ? AND state = 'pending'",
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        if pending.is_none() {
+            return Ok(None);
+        }
+
+        let stopped = sqlx::query!(
+            "UPDATE events
+             SET stopped_at = (SELECT ends_at FROM pomodoro_timers WHERE event_id = events.id)
+             WHERE id = ? AND stopped_at IS NULL
+             RETURNING id as \"id!\", created_at as \"created_at!: chrono::NaiveDateTime\",
+                       stopped_at as \"stopped_at!: chrono::NaiveDateTime\"",
+            event_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let state = if stopped.is_some() {
+            "completed"
+        } else {
+            "cancelled"
+        };
+        sqlx::query!(
+            "UPDATE pomodoro_timers SET state = ? WHERE event_id = ?",
+            state,
+            event_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(stopped.map(|event| stopped_event(event.id, event.created_at, event.stopped_at)))
+    }
+
+    async fn completed_pomodoros(&self) -> Result<i64, StorageError> {
+        let completed = sqlx::query_scalar!(
+            "SELECT COUNT(*) as \"count!: i64\" FROM pomodoro_timers
+             WHERE phase = 'work' AND state = 'completed'
+               AND event_id > COALESCE(
+                   (SELECT MAX(event_id) FROM pomodoro_timers WHERE phase = 'long_break'), 0)"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(completed)
+    }
+
+    async fn purge_idempotency_keys(&self, window_seconds: i64) -> Result<(), StorageError> {
+        let window = format!("-{} seconds", window_seconds);
+        sqlx::query!(
+            "DELETE FROM idempotency_keys WHERE created_at < datetime('now', ?)",
+            window
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn reserve_idempotency_key(
+        &self,
+        key: &str,
+        route: &str,
+        request_hash: &str,
+        pending_seconds: i64,
+        window_seconds: i64,
+    ) -> Result<bool, StorageError> {
+        let pending = format!("{} seconds", -pending_seconds);
+        let window = format!("{} seconds", -window_seconds);
+        let reserved = sqlx::query!(
+            "INSERT INTO idempotency_keys (idempotency_key, route, request_hash) VALUES (?, ?, ?)
+             ON CONFLICT (idempotency_key, route) DO UPDATE
+             SET request_hash = excluded.request_hash, created_at = CURRENT_TIMESTAMP,
+                 status = NULL, location = NULL, body = NULL
+             WHERE (idempotency_keys.status IS NULL
+                    AND idempotency_keys.created_at < datetime('now', ?))
+                OR idempotency_keys.created_at < datetime('now', ?)",
+            key,
+            route,
+            request_hash,
+            pending,
+            window
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(reserved.rows_affected() == 1)
+    }
+
+    async fn release_idempotency_key(&self, key: &str, route: &str) -> Result<(), StorageError> {
+        sqlx::query!(
+            "DELETE FROM idempotency_keys WHERE idempotency_key = ? AND route = ?",
+            key,
+            route
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn store_idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        response: &StoredResponse,
+    ) -> Result<(), StorageError> {
+        sqlx::query!(
+            "UPDATE idempotency_keys SET status = ?, location = ?, body = ?
+             WHERE idempotency_key = ? AND route = ?",
+            response.status,
+            response.location,
+            response.body,
+            key,
+            route
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn idempotent_response(
+        &self,
+        key: &str,
+        route: &str,
+        window_seconds: i64,
+    ) -> Result<Option<StoredResponse>, StorageError> {
+        let window = format!("{} seconds", -window_seconds);
+        let stored = sqlx::query!(
+            "SELECT status, location, body, request_hash FROM idempotency_keys
+             WHERE idempotency_key = ? AND route = ? AND created_at >= datetime('now', ?)",
+            key,
+            route,
+            window
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(stored.map(|stored| StoredResponse {
+            status: stored.status.and_then(|status| u16::try_from(status).ok()),
+            location: stored.location,
+            body: stored.body.unwrap_or_default(),
+            request_hash: stored.request_hash,
+        }))
+    }
+
+    async fn event_totals(&self) -> Result<EventTotals, StorageError> {
+        let totals = sqlx::query!(
+            r#"SELECT
+                COALESCE(SUM(CASE WHEN stopped_at IS NULL THEN 1 ELSE 0 END), 0) AS "running!: i64",
+                COALESCE(SUM((julianday(COALESCE(stopped_at, datetime('now'))) - julianday(created_at)) * 86400.0), 0.0) AS "tracked!: f64"
+            FROM events"#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(EventTotals {
+            running: totals.running,
+            tracked_seconds: totals.tracked,
+        })
+    }
+
+    async fn ping(&self) -> Result<(), StorageError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn check_writable(&self) -> Result<(), StorageError> {
+        // Deletes nothing, but still needs a write lock, so it fails on a read-only database.
+        sqlx::query("DELETE FROM idempotency_keys WHERE 0")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, StorageError> {
+        let applied = sqlx::query_as::<_, (i64, bool, Vec<u8>)>(
+            "SELECT version, success, checksum FROM _sqlx_migrations",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(applied
+            .into_iter()
+            .map(|(version, success, checksum)| AppliedMigration {
+                version,
+                success,
+                checksum,
+            })
+            .collect())
+    }
+
+    fn migrator(&self) -> &'static Migrator {
+        &MIGRATOR
+    }
+}
@@ -0,0 +1,297 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    CreateBudgetRequest, CreateTagRequest, CreateTaskRequest, CreateTimedEventRequest,
+    JoinEventsRequest, SetTaskEstimateRequest, SplitEventRequest, UpdateTimedEventRequest,
+};
+
+/// How tag names are normalised before they are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagCase {
+    /// Keep the name exactly as submitted (after trimming).
+    #[default]
+    Preserve,
+    Lowercase,
+    Uppercase,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    pub max_tag_name_length: usize,
+    pub max_task_length: usize,
+    pub max_tags_per_event: usize,
+    pub tag_case: TagCase,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_tag_name_length: 64,
+            max_task_length: 255,
+            max_tags_per_event: 32,
+            tag_case: TagCase::Preserve,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every violation found in a request, returned as `422 Unprocessable Entity`.
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub(crate) fn single(field: &str, message: impl Into<String>) -> Self {
+        let mut errors = Self::default();
+        errors.add(field, message);
+        errors
+    }
+
+    fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn into_result<T>(self, value: T) -> Result<T, ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        (StatusCode::UNPROCESSABLE_ENTITY, Json(self)).into_response()
+    }
+}
+
+/// Error type for handlers that validate their payload before touching the database.
+pub enum ApiError {
+    Status(StatusCode),
+    Validation(ValidationErrors),
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Status(status) => *status,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl From<StatusCode> for ApiError {
+    fn from(status: StatusCode) -> Self {
+        ApiError::Status(status)
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ApiError::Validation(errors)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::Status(status) => status.into_response(),
+            ApiError::Validation(errors) => errors.into_response(),
+        }
+    }
+}
+
+fn check_name(errors: &mut ValidationErrors, field: &str, value: &str, max_length: usize) {
+    if value.is_empty() {
+        errors.add(field, "must not be empty");
+    }
+    let length = value.chars().count();
+    if length > max_length {
+        errors.add(
+            field,
+            format!("must be at most {} characters, got {}", max_length, length),
+        );
+    }
+    if value.chars().any(char::is_control) {
+        errors.add(field, "must not contain control characters");
+    }
+}
+
+impl CreateTagRequest {
+    /// Trims the name, checks its bounds and applies the configured [`TagCase`].
+    pub fn validate(self, config: &ValidationConfig) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let trimmed = self.name.trim();
+        check_name(&mut errors, "name", trimmed, config.max_tag_name_length);
+
+        let name = match config.tag_case {
+            TagCase::Preserve => trimmed.to_string(),
+            TagCase::Lowercase => trimmed.to_lowercase(),
+            TagCase::Uppercase => trimmed.to_uppercase(),
+        };
+        errors.into_result(CreateTagRequest { name })
+    }
+}
+
+impl CreateTaskRequest {
+    /// Trims the task description and checks its bounds.
+    pub fn validate(self, config: &ValidationConfig) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let task = self.task.trim();
+        check_name(&mut errors, "task", task, config.max_task_length);
+        check_estimate(&mut errors, self.estimate_seconds);
+
+        errors.into_result(CreateTaskRequest {
+            task: task.to_string(),
+            ..self
+        })
+    }
+}
+
+impl SetTaskEstimateRequest {
+    pub fn validate(self) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        check_estimate(&mut errors, self.estimate_seconds);
+        errors.into_result(self)
+    }
+}
+
+impl CreateBudgetRequest {
+    pub fn validate(self) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.tag_id <= 0 {
+            errors.add("tag_id", "must be a positive id");
+        }
+        if self.seconds <= 0 {
+            errors.add("seconds", "must be positive");
+        }
+        errors.into_result(self)
+    }
+}
+
+fn check_estimate(errors: &mut ValidationErrors, estimate_seconds: Option<i64>) {
+    if estimate_seconds.is_some_and(|seconds| seconds <= 0) {
+        errors.add("estimate_seconds", "must be positive");
+    }
+}
+
+/// Checks the ids are positive, drops duplicates keeping the first occurrence and bounds the
+/// number of tags.
+fn check_tag_ids(
+    errors: &mut ValidationErrors,
+    tag_ids: Vec<i64>,
+    config: &ValidationConfig,
+) -> Vec<i64> {
+    let mut unique = Vec::with_capacity(tag_ids.len());
+    for tag_id in tag_ids {
+        if tag_id <= 0 {
+            errors.add("tag_ids", format!("{} is not a positive id", tag_id));
+        } else if !unique.contains(&tag_id) {
+            unique.push(tag_id);
+        }
+    }
+    if unique.len() > config.max_tags_per_event {
+        errors.add(
+            "tag_ids",
+            format!(
+                "must contain at most {} tags, got {}",
+                config.max_tags_per_event,
+                unique.len()
+            ),
+        );
+    }
+    unique
+}
+
+impl CreateTimedEventRequest {
+    /// Checks the ids are positive and drops duplicate `tag_ids`, keeping the first occurrence.
+    pub fn validate(self, config: &ValidationConfig) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.task_id <= 0 {
+            errors.add("task_id", "must be a positive id");
+        }
+        let tag_ids = check_tag_ids(&mut errors, self.tag_ids, config);
+
+        errors.into_result(CreateTimedEventRequest {
+            tag_ids,
+            task_id: self.task_id,
+        })
+    }
+}
+
+impl UpdateTimedEventRequest {
+    /// Applies the same id rules as [`CreateTimedEventRequest::validate`] to the fields present,
+    /// and checks the new times are in order when both are given.
+    pub fn validate(self, config: &ValidationConfig) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.task_id.is_some_and(|task_id| task_id <= 0) {
+            errors.add("task_id", "must be a positive id");
+        }
+        let tag_ids = self
+            .tag_ids
+            .map(|tag_ids| check_tag_ids(&mut errors, tag_ids, config));
+        if let (Some(created_at), Some(stopped_at)) = (self.created_at, self.stopped_at)
+            && stopped_at < created_at
+        {
+            errors.add("stopped_at", "must not be before created_at");
+        }
+
+        errors.into_result(UpdateTimedEventRequest { tag_ids, ..self })
+    }
+}
+
+impl SplitEventRequest {
+    /// Applies the id rules of [`CreateTimedEventRequest::validate`] to the second half's task
+    /// and tags.
+    pub fn validate(self, config: &ValidationConfig) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        if self.task_id.is_some_and(|task_id| task_id <= 0) {
+            errors.add("task_id", "must be a positive id");
+        }
+        let tag_ids = self
+            .tag_ids
+            .map(|tag_ids| check_tag_ids(&mut errors, tag_ids, config));
+
+        errors.into_result(SplitEventRequest { tag_ids, ..self })
+    }
+}
+
+impl JoinEventsRequest {
+    /// Checks the ids are positive and name at least two different events.
+    pub fn validate(self) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        let mut event_ids = Vec::with_capacity(self.event_ids.len());
+        for id in self.event_ids {
+            if id <= 0 {
+                errors.add("event_ids", format!("{id} is not a positive id"));
+            } else if event_ids.contains(&id) {
+                errors.add("event_ids", format!("{id} is listed more than once"));
+            } else {
+                event_ids.push(id);
+            }
+        }
+        if event_ids.len() < 2 {
+            errors.add("event_ids", "must name at least two events");
+        }
+
+        errors.into_result(JoinEventsRequest { event_ids })
+    }
+}
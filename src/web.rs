@@ -0,0 +1,46 @@
+use axum::{Json, extract::State, http::header, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, AuthMode};
+
+const INDEX_HTML: &str = include_str!("../assets/web/index.html");
+const APP_JS: &str = include_str!("../assets/web/app.js");
+const STYLE_CSS: &str = include_str!("../assets/web/style.css");
+
+/// The single-page web UI. It talks to the JSON API like any other client.
+pub async fn index() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+        INDEX_HTML,
+    )
+}
+
+pub async fn app_js() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        APP_JS,
+    )
+}
+
+/// What the web UI needs to know about the server before its first API call.
+#[derive(Serialize, Deserialize)]
+pub struct UiConfig {
+    /// Whether `GET /events/stream` is served; without it the UI does not listen for changes.
+    pub event_stream: bool,
+    /// Whether API calls need a bearer token, which the UI then asks for.
+    pub token_required: bool,
+}
+
+pub async fn ui_config(State(state): State<AppState>) -> Json<UiConfig> {
+    Json(UiConfig {
+        event_stream: state.features.event_stream,
+        token_required: state.auth.mode == AuthMode::Token,
+    })
+}
+
+pub async fn style_css() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        STYLE_CSS,
+    )
+}
@@ -0,0 +1,202 @@
+use axum::{
+    extract::{
+        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    http::StatusCode,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{
+    ApiError, AppState, CreateTimedEventRequest, EventChange, EventNotification, FieldError,
+    TimedEvent,
+};
+
+/// Messages a client sends over `/ws`. The optional `request_id` is echoed in the reply.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ClientMessage {
+    Start {
+        request_id: Option<String>,
+        task_id: i64,
+        #[serde(default)]
+        tag_ids: Vec<i64>,
+    },
+    Stop {
+        request_id: Option<String>,
+        event_id: i64,
+    },
+    Subscribe {
+        request_id: Option<String>,
+    },
+    Unsubscribe {
+        request_id: Option<String>,
+    },
+}
+
+/// Messages the server sends over `/ws`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ServerMessage {
+    /// The request succeeded; `body` is what the equivalent HTTP endpoint returns.
+    Ack {
+        request_id: Option<String>,
+        status: u16,
+        body: serde_json::Value,
+    },
+    /// The request failed with the status the equivalent HTTP endpoint would return.
+    Error {
+        request_id: Option<String>,
+        status: u16,
+        message: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        errors: Vec<FieldError>,
+    },
+    /// An event changed while the connection was subscribed.
+    Event {
+        change: EventChange,
+        event: TimedEvent,
+    },
+    /// Notifications were dropped because the client fell behind.
+    Lagged,
+}
+
+impl ServerMessage {
+    fn ack(request_id: Option<String>, status: StatusCode, body: impl Serialize) -> Self {
+        ServerMessage::Ack {
+            request_id,
+            status: status.as_u16(),
+            body: serde_json::to_value(body).unwrap_or_default(),
+        }
+    }
+
+    fn error(request_id: Option<String>, error: ApiError) -> Self {
+        let status = error.status();
+        let errors = match error {
+            ApiError::Validation(validation) => validation.errors,
+            ApiError::Status(_) => Vec::new(),
+        };
+        ServerMessage::Error {
+            request_id,
+            status: status.as_u16(),
+            message: status
+                .canonical_reason()
+                .unwrap_or("Unknown error")
+                .to_string(),
+            errors,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "events",
+    summary = "WebSocket for event notifications and commands",
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+        (status = 400, description = "Not a valid WebSocket handshake"),
+        (status = 426, description = "The connection cannot be upgraded"),
+    )
+)]
+pub async fn websocket(ws: WebSocketUpgrade, State(state): State<AppState>) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState) {
+    let mut notifications: Option<broadcast::Receiver<EventNotification>> = None;
+    let shutdown = state.shutdown.wait();
+    tokio::pin!(shutdown);
+
+    loop {
+        let reply = tokio::select! {
+            _ = &mut shutdown => {
+                let close = CloseFrame {
+                    code: close_code::AWAY,
+                    reason: "server shutting down".into(),
+                };
+                let _ = socket.send(Message::Close(Some(close))).await;
+                break;
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    handle_message(&state, &text, &mut notifications).await
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            notification = next_notification(&mut notifications) => match notification {
+                Ok(notification) => ServerMessage::Event {
+                    change: notification.change,
+                    event: notification.event,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "websocket subscriber lagged");
+                    ServerMessage::Lagged
+                }
+                Err(RecvError::Closed) => {
+                    notifications = None;
+                    continue;
+                }
+            },
+        };
+
+        let Ok(text) = serde_json::to_string(&reply) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn next_notification(
+    notifications: &mut Option<broadcast::Receiver<EventNotification>>,
+) -> Result<EventNotification, RecvError> {
+    match notifications {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn handle_message(
+    state: &AppState,
+    text: &str,
+    notifications: &mut Option<broadcast::Receiver<EventNotification>>,
+) -> ServerMessage {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(_) => return ServerMessage::error(None, StatusCode::BAD_REQUEST.into()),
+    };
+
+    match message {
+        ClientMessage::Start {
+            request_id,
+            task_id,
+            tag_ids,
+        } => {
+            let payload = CreateTimedEventRequest { tag_ids, task_id };
+            match state.tracker().start_event(payload).await {
+                Ok(response) => ServerMessage::ack(request_id, StatusCode::CREATED, response),
+                Err(error) => ServerMessage::error(request_id, error.into()),
+            }
+        }
+        ClientMessage::Stop {
+            request_id,
+            event_id,
+        } => match state.tracker().stop_event(event_id).await {
+            Ok(response) => ServerMessage::ack(request_id, StatusCode::OK, response),
+            Err(error) => ServerMessage::error(request_id, error.into()),
+        },
+        ClientMessage::Subscribe { request_id } => {
+            *notifications = Some(state.notifier.subscribe());
+            ServerMessage::ack(request_id, StatusCode::OK, serde_json::Value::Null)
+        }
+        ClientMessage::Unsubscribe { request_id } => {
+            *notifications = None;
+            ServerMessage::ack(request_id, StatusCode::OK, serde_json::Value::Null)
+        }
+    }
+}
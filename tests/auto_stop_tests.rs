@@ -0,0 +1,200 @@
+mod common;
+
+use std::time::Duration;
+
+use axum_test::TestServer;
+use chrono::{NaiveDateTime, Utc};
+use common::created;
+use rustimenator::{
+    AppState, AutoStopConfig, AutoStopReason, AutoStopRule, CreateTaskRequest,
+    CreateTimedEventRequest, EventChange, GetEventsResponse, TimeTracker, TimedEvent,
+    create_app_with_state, create_database_pool,
+};
+use serde_json::json;
+
+/// Starts an event and moves its start to `created_at`, returning its id.
+async fn start_at(
+    server: &TestServer,
+    task_id: i64,
+    tag_ids: &[i64],
+    created_at: NaiveDateTime,
+) -> i64 {
+    let started = server
+        .post("/events/start")
+        .json(&json!({"task_id": task_id, "tag_ids": tag_ids}))
+        .await
+        .json::<serde_json::Value>();
+    let id = started["id"].as_i64().unwrap();
+    server
+        .patch(&format!("/events/{id}"))
+        .json(&json!({"created_at": created_at}))
+        .await
+        .assert_status_ok();
+    id
+}
+
+async fn fetch(server: &TestServer, id: i64) -> TimedEvent {
+    server.get(&format!("/events/{id}")).await.json()
+}
+
+#[tokio::test]
+async fn test_auto_stop_applies_global_task_and_tag_limits() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_auto_stop(AutoStopConfig {
+        max_duration_seconds: Some(8 * 3600),
+        rules: vec![
+            AutoStopRule {
+                tag: Some("meeting".to_string()),
+                max_duration_seconds: Some(3600),
+                ..AutoStopRule::default()
+            },
+            AutoStopRule {
+                task: Some("call".to_string()),
+                daily_cutoff: Some("12:00".parse().unwrap()),
+                ..AutoStopRule::default()
+            },
+        ],
+        ..AutoStopConfig::default()
+    });
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let meeting = created(&server, "meeting", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    let call = created(&server, "call", "/task", "task").await;
+
+    let now = Utc::now().naive_utc();
+    let two_hours_ago = now - chrono::Duration::hours(2);
+    let yesterday_morning = (now - chrono::Duration::days(1))
+        .date()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let within_limits = start_at(&server, write, &[], two_hours_ago).await;
+    let long_meeting = start_at(&server, write, &[meeting], two_hours_ago).await;
+    let forgotten_call = start_at(&server, call, &[], yesterday_morning).await;
+    let overnight = start_at(&server, write, &[], now - chrono::Duration::hours(9)).await;
+
+    let stopped = state.tracker().auto_stop_events().await.unwrap();
+    assert_eq!(stopped.len(), 3);
+    assert!(state.tracker().auto_stop_events().await.unwrap().is_empty());
+
+    let running = fetch(&server, within_limits).await;
+    assert!(running.stopped_at.is_none());
+    assert!(running.auto_stop_reason.is_none());
+
+    let long_meeting = fetch(&server, long_meeting).await;
+    assert_eq!(
+        long_meeting.auto_stop_reason,
+        Some(AutoStopReason::MaxDuration)
+    );
+    assert_eq!(
+        long_meeting.stopped_at.unwrap() - long_meeting.created_at.unwrap(),
+        chrono::Duration::hours(1)
+    );
+
+    let forgotten_call = fetch(&server, forgotten_call).await;
+    assert_eq!(
+        forgotten_call.auto_stop_reason,
+        Some(AutoStopReason::DailyCutoff)
+    );
+    assert_eq!(
+        forgotten_call.stopped_at,
+        yesterday_morning.date().and_hms_opt(12, 0, 0)
+    );
+
+    let overnight = fetch(&server, overnight).await;
+    assert_eq!(
+        overnight.auto_stop_reason,
+        Some(AutoStopReason::MaxDuration)
+    );
+    assert_eq!(
+        overnight.stopped_at.unwrap() - overnight.created_at.unwrap(),
+        chrono::Duration::hours(8)
+    );
+
+    let review: GetEventsResponse = server
+        .get("/events")
+        .add_query_params(json!({"status": "all", "auto_stopped": true}))
+        .await
+        .json();
+    assert_eq!(review.count, 3);
+    assert!(
+        review
+            .events
+            .iter()
+            .all(|event| event.auto_stop_reason.is_some())
+    );
+    let manual: GetEventsResponse = server
+        .get("/events")
+        .add_query_params(json!({"status": "all", "auto_stopped": false}))
+        .await
+        .json();
+    assert_eq!(manual.count, 1);
+    assert_eq!(manual.events[0].id, within_limits);
+}
+
+#[tokio::test]
+async fn test_auto_stop_job_stops_forgotten_timers() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let tracker = TimeTracker::new(pool).with_auto_stop(AutoStopConfig {
+        interval_seconds: 1,
+        max_duration_seconds: Some(1),
+        ..AutoStopConfig::default()
+    });
+    let mut notifications = tracker.notifier.subscribe();
+
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "write".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+    let started = tracker
+        .start_event(CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        notifications.recv().await.unwrap().change,
+        EventChange::Started
+    );
+
+    tracker.spawn_auto_stop();
+    let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+        .await
+        .expect("the event was not stopped within 5 seconds")
+        .unwrap();
+    assert_eq!(notification.change, EventChange::Stopped);
+    assert_eq!(notification.event.id, started.id);
+    assert_eq!(
+        notification.event.auto_stop_reason,
+        Some(AutoStopReason::MaxDuration)
+    );
+    tracker.shutdown.trigger();
+}
+
+#[test]
+fn test_deadline_ignores_cutoffs_past_the_end_of_the_calendar() {
+    let config = AutoStopConfig {
+        daily_cutoff: Some("00:00".parse().unwrap()),
+        ..AutoStopConfig::default()
+    };
+    let event = TimedEvent {
+        id: 1,
+        task_id: 1,
+        task: None,
+        tags: vec![],
+        created_at: Some(NaiveDateTime::MAX),
+        stopped_at: None,
+        auto_stop_reason: None,
+    };
+    assert_eq!(config.deadline(&event), None);
+
+    let event = TimedEvent {
+        created_at: NaiveDateTime::MAX.date().and_hms_opt(0, 0, 0),
+        ..event
+    };
+    assert_eq!(config.deadline(&event), None);
+}
@@ -0,0 +1,258 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::NaiveDateTime;
+use common::{at, created, event_at};
+use rustimenator::{
+    AppState, Budget, BudgetPeriod, BudgetStatus, GetBudgetQuery, GetBudgetsQuery,
+    GetBudgetsResponse, Task, TaskEstimate, create_app_with_state, create_database_pool,
+};
+use serde_json::json;
+
+async fn budget(server: &TestServer, tag_id: i64, period: &str, seconds: i64) -> Budget {
+    server
+        .post("/budgets")
+        .json(&json!({"tag_id": tag_id, "period": period, "seconds": seconds}))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn test_task_estimates() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app_with_state(AppState::new(pool))).unwrap();
+
+    let response = server
+        .post("/task")
+        .json(&json!({"task": "write", "estimate_seconds": 7200}))
+        .await;
+    response.assert_status(StatusCode::CREATED);
+    let write = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+    let task: Task = server.get(&format!("/task/{write}")).await.json();
+    assert_eq!(task.estimate_seconds, Some(7200));
+    event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T09:00:00",
+        "2025-01-06T10:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-07T10:00:00",
+        "2025-01-07T11:30:00",
+    )
+    .await;
+
+    let estimate: TaskEstimate = server.get(&format!("/task/{write}/estimate")).await.json();
+    assert_eq!(estimate.consumed_seconds, 9000);
+    assert_eq!(estimate.remaining_seconds, Some(0));
+    assert!(estimate.over_estimate);
+
+    let task: Task = server
+        .put(&format!("/task/{write}/estimate"))
+        .json(&json!({"estimate_seconds": 10800}))
+        .await
+        .json();
+    assert_eq!(task.estimate_seconds, Some(10800));
+    let estimate: TaskEstimate = server.get(&format!("/task/{write}/estimate")).await.json();
+    assert_eq!(estimate.remaining_seconds, Some(1800));
+    assert!(!estimate.over_estimate);
+
+    // Without an estimate the tracked time is still reported.
+    let task: Task = server
+        .put(&format!("/task/{write}/estimate"))
+        .json(&json!({}))
+        .await
+        .json();
+    assert_eq!(task.estimate_seconds, None);
+    let estimate: TaskEstimate = server.get(&format!("/task/{write}/estimate")).await.json();
+    assert_eq!(estimate.consumed_seconds, 9000);
+    assert_eq!(estimate.remaining_seconds, None);
+    assert!(!estimate.over_estimate);
+
+    server
+        .put(&format!("/task/{write}/estimate"))
+        .json(&json!({"estimate_seconds": 0}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .post("/task")
+        .json(&json!({"task": "review", "estimate_seconds": -60}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .put("/task/99/estimate")
+        .json(&json!({"estimate_seconds": 60}))
+        .await
+        .assert_status_not_found();
+    server
+        .get("/task/99/estimate")
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_budgets_track_time_per_period() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app_with_state(AppState::new(pool))).unwrap();
+
+    let work = created(&server, "work", "/tag", "name").await;
+    let other = created(&server, "other", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-06T09:00:00",
+        "2025-01-06T11:00:00",
+    )
+    .await;
+    // Sunday night into Monday: an hour in each week.
+    event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-12T23:00:00",
+        "2025-01-13T01:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[work],
+        "2025-02-03T09:00:00",
+        "2025-02-03T10:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[other],
+        "2025-01-06T12:00:00",
+        "2025-01-06T15:00:00",
+    )
+    .await;
+
+    let day = budget(&server, work, "day", 3600).await;
+    let week = budget(&server, work, "week", 10800).await;
+    budget(&server, work, "month", 14400).await;
+    let total = budget(&server, work, "total", 18000).await;
+    assert_eq!(week.period, BudgetPeriod::Week);
+
+    server
+        .post("/budgets")
+        .json(&json!({"tag_id": work, "period": "week", "seconds": 60}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .post("/budgets")
+        .json(&json!({"tag_id": 99, "period": "week", "seconds": 60}))
+        .await
+        .assert_status_bad_request();
+    server
+        .post("/budgets")
+        .json(&json!({"tag_id": other, "period": "week", "seconds": 0}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .post("/budgets")
+        .json(&json!({"tag_id": other, "period": "year", "seconds": 60}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let budgets: GetBudgetsResponse = server
+        .get("/budgets")
+        .add_query_params(GetBudgetsQuery {
+            at: Some(at("2025-01-06T12:00:00")),
+            over_budget: None,
+        })
+        .await
+        .json();
+    assert_eq!(budgets.count, 4);
+    let status = |period: BudgetPeriod| {
+        budgets
+            .budgets
+            .iter()
+            .find(|status| status.budget.period == period)
+            .unwrap()
+            .clone()
+    };
+    let daily = status(BudgetPeriod::Day);
+    assert_eq!(daily.period_start, Some(at("2025-01-06T00:00:00")));
+    assert_eq!(daily.period_end, Some(at("2025-01-07T00:00:00")));
+    assert_eq!(daily.consumed_seconds, 7200);
+    assert_eq!(daily.remaining_seconds, 0);
+    assert!(daily.over_budget);
+    let weekly = status(BudgetPeriod::Week);
+    assert_eq!(weekly.period_end, Some(at("2025-01-13T00:00:00")));
+    assert_eq!(weekly.consumed_seconds, 10800);
+    assert!(!weekly.over_budget);
+    assert_eq!(status(BudgetPeriod::Month).consumed_seconds, 14400);
+    let all_time = status(BudgetPeriod::Total);
+    assert_eq!(all_time.period_start, None);
+    assert_eq!(all_time.consumed_seconds, 18000);
+
+    let over: GetBudgetsResponse = server
+        .get("/budgets")
+        .add_query_params(GetBudgetsQuery {
+            at: Some(at("2025-01-06T12:00:00")),
+            over_budget: Some(true),
+        })
+        .await
+        .json();
+    assert_eq!(over.count, 1);
+    assert_eq!(over.budgets[0].budget.id, day.id);
+
+    let next_week: BudgetStatus = server
+        .get(&format!("/budgets/{}", week.id))
+        .add_query_params(GetBudgetQuery {
+            at: Some(at("2025-01-13T12:00:00")),
+        })
+        .await
+        .json();
+    assert_eq!(next_week.period_start, Some(at("2025-01-13T00:00:00")));
+    assert_eq!(next_week.consumed_seconds, 3600);
+    assert_eq!(next_week.remaining_seconds, 7200);
+
+    // Running events count until now.
+    let running = server
+        .post("/events/start")
+        .json(&json!({"task_id": write, "tag_ids": [work]}))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap();
+    server
+        .patch(&format!("/events/{running}"))
+        .json(&json!({"created_at": "2025-03-03T09:00:00"}))
+        .await
+        .assert_status_ok();
+    let all_time: BudgetStatus = server.get(&format!("/budgets/{}", total.id)).await.json();
+    assert!(all_time.over_budget);
+
+    server
+        .get(&format!("/budgets/{}", week.id))
+        .add_query_params(GetBudgetQuery {
+            at: Some(NaiveDateTime::MAX),
+        })
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    server
+        .delete(&format!("/budgets/{}", day.id))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .get(&format!("/budgets/{}", day.id))
+        .await
+        .assert_status_not_found();
+    server
+        .delete(&format!("/budgets/{}", day.id))
+        .await
+        .assert_status_not_found();
+}
@@ -0,0 +1,48 @@
+//! Fixtures shared by the API test suites. Each suite is its own crate and uses only some of them.
+#![allow(dead_code)]
+
+use axum_test::TestServer;
+use chrono::NaiveDateTime;
+use serde_json::json;
+
+/// Creates a tag or task by posting `{field: name}` to `path`, returning its id.
+pub async fn created(server: &TestServer, name: &str, path: &str, field: &str) -> i64 {
+    server
+        .post(path)
+        .json(&json!({field: name}))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+pub async fn start(server: &TestServer, task_id: i64, tag_ids: &[i64]) -> i64 {
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": task_id, "tag_ids": tag_ids}))
+        .await
+        .json::<serde_json::Value>()["id"]
+        .as_i64()
+        .unwrap()
+}
+
+/// Starts an event and moves it to `created_at`..`stopped_at`, returning its id.
+pub async fn event_at(
+    server: &TestServer,
+    task_id: i64,
+    tag_ids: &[i64],
+    created_at: &str,
+    stopped_at: &str,
+) -> i64 {
+    let id = start(server, task_id, tag_ids).await;
+    server
+        .patch(&format!("/events/{id}"))
+        .json(&json!({"created_at": created_at, "stopped_at": stopped_at}))
+        .await
+        .assert_status_ok();
+    id
+}
+
+pub fn at(time: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S").unwrap()
+}
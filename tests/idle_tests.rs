@@ -0,0 +1,159 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use common::{created, start};
+use rustimenator::{
+    AppState, EventChange, GetEventsResponse, GetIdleGapsQuery, GetIdleGapsResponse,
+    HeartbeatResponse, IdleConfig, IdleResolution, ResolveIdleGapResponse, create_app_with_state,
+    create_database_pool,
+};
+use serde_json::json;
+
+async fn heartbeat(server: &TestServer, id: i64) -> HeartbeatResponse {
+    server.post(&format!("/events/{id}/heartbeat")).await.json()
+}
+
+async fn resolve(server: &TestServer, id: i64, resolution: &str) -> ResolveIdleGapResponse {
+    server
+        .post(&format!("/idle-gaps/{id}/resolve"))
+        .json(&json!({"resolution": resolution}))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn test_idle_gaps_are_flagged_and_resolved() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_idle(IdleConfig {
+        timeout_seconds: 1,
+        ..IdleConfig::default()
+    });
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+    let mut notifications = state.notifier.subscribe();
+
+    let work = created(&server, "work", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    let discarded = start(&server, write, &[work]).await;
+    let split = start(&server, write, &[work]).await;
+    let kept = start(&server, write, &[]).await;
+    let silent = start(&server, write, &[]).await;
+    for id in [discarded, split, kept] {
+        assert!(heartbeat(&server, id).await.idle_gap.is_none());
+    }
+    while notifications.try_recv().is_ok() {}
+
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let flagged = state.tracker().flag_idle_events().await.unwrap();
+    assert_eq!(flagged.len(), 3);
+    assert!(flagged.iter().all(|gap| gap.event_id != silent));
+    assert!(state.tracker().flag_idle_events().await.unwrap().is_empty());
+    let open: GetIdleGapsResponse = server.get("/idle-gaps").await.json();
+    assert_eq!(open.count, 3);
+    assert!(open.idle_gaps.iter().all(|gap| gap.ended_at.is_none()));
+
+    let gap_of = |event_id: i64| {
+        open.idle_gaps
+            .iter()
+            .find(|gap| gap.event_id == event_id)
+            .unwrap()
+            .clone()
+    };
+
+    // Back at the keyboard: the heartbeat ends the gap and hands it to the client.
+    let back = heartbeat(&server, discarded).await;
+    let gap = back.idle_gap.unwrap();
+    assert_eq!(gap.id, gap_of(discarded).id);
+    assert_eq!(gap.ended_at, Some(back.last_heartbeat_at));
+    let resolved = resolve(&server, gap.id, "discard").await;
+    assert_eq!(resolved.idle_gap.resolution, Some(IdleResolution::Discard));
+    assert_eq!(resolved.events.len(), 2);
+    assert_eq!(resolved.events[0].id, discarded);
+    assert_eq!(resolved.events[0].stopped_at, Some(gap.started_at));
+    let remainder = &resolved.events[1];
+    assert_eq!(remainder.created_at, gap.ended_at);
+    assert!(remainder.stopped_at.is_none());
+    assert_eq!(remainder.tags[0].id, work);
+    let changes: Vec<_> = (0..2)
+        .map(|_| notifications.try_recv().unwrap().change)
+        .collect();
+    assert_eq!(changes, [EventChange::Stopped, EventChange::Started]);
+
+    let gap = heartbeat(&server, split).await.idle_gap.unwrap();
+    let resolved = resolve(&server, gap.id, "split").await;
+    assert_eq!(resolved.events.len(), 3);
+    let idle = &resolved.events[1];
+    assert_eq!(
+        (idle.created_at, idle.stopped_at),
+        (Some(gap.started_at), gap.ended_at)
+    );
+    assert_eq!(idle.tags[0].id, work);
+    assert_eq!(resolved.events[2].created_at, gap.ended_at);
+
+    // Resolving a gap the client has not come back from yet ends it now.
+    let gap = gap_of(kept);
+    let resolved = resolve(&server, gap.id, "keep").await;
+    assert_eq!(resolved.events.len(), 1);
+    assert!(resolved.events[0].stopped_at.is_none());
+    assert!(resolved.idle_gap.ended_at.is_some());
+
+    server
+        .post(&format!("/idle-gaps/{}/resolve", gap.id))
+        .json(&json!({"resolution": "discard"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .post("/idle-gaps/99/resolve")
+        .json(&json!({"resolution": "keep"}))
+        .await
+        .assert_status_not_found();
+    server
+        .post(&format!("/events/{discarded}/heartbeat"))
+        .await
+        .assert_status_not_found();
+
+    let open: GetIdleGapsResponse = server.get("/idle-gaps").await.json();
+    assert_eq!(open.count, 0);
+    let resolved: GetIdleGapsResponse = server
+        .get("/idle-gaps")
+        .add_query_params(GetIdleGapsQuery {
+            resolved: Some(true),
+        })
+        .await
+        .json();
+    assert_eq!(resolved.count, 3);
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 4);
+}
+
+#[tokio::test]
+async fn test_idle_sweeper_flags_silent_clients() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_idle(IdleConfig {
+        timeout_seconds: 1,
+        interval_seconds: 1,
+    });
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let write = created(&server, "write", "/task", "task").await;
+    let event = start(&server, write, &[]).await;
+    heartbeat(&server, event).await;
+
+    let tracker = state.tracker();
+    tracker.spawn_idle_sweeper();
+    let gaps = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let gaps: GetIdleGapsResponse = server.get("/idle-gaps").await.json();
+            if gaps.count > 0 {
+                return gaps;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("the event was not flagged within 5 seconds");
+    assert_eq!(gaps.idle_gaps[0].event_id, event);
+    tracker.shutdown.trigger();
+}
@@ -1,7 +1,13 @@
+use axum::http::{StatusCode, header};
 use axum_test::TestServer;
 use rustimenator::{
-    CreateTagResponse, CreateTaskResponse, CreateTimedEventResponse, GetEventsResponse,
-    GetTagsResponse, GetTasksResponse, StopEventResponse, create_app, create_database_pool,
+    AppState, AuthConfig, AuthMode, CorsConfig, CreateTagResponse, CreateTaskResponse,
+    CreateTimedEventResponse, EventChange, FeatureConfig, GetEventCombinationsResponse,
+    GetEventsResponse, GetTagsResponse, GetTasksResponse, HealthResponse, IDEMPOTENCY_KEY,
+    IDEMPOTENT_REPLAYED, IdempotencyConfig, REQUEST_ID, ReadinessResponse, RunningEventsOnShutdown,
+    ServerMessage, StopEventResponse, Tag, TagCase, Task, TimedEvent, TimerMode, ValidationConfig,
+    ValidationErrors, create_app, create_app_with_state, create_database_pool,
+    finish_running_events, resume_checkpointed_events,
 };
 
 use serde_json::json;
@@ -17,7 +23,7 @@ async fn test_create_tag() {
         .json(&json!({"name": "database-tag"}))
         .await;
 
-    response.assert_status_ok();
+    response.assert_status(StatusCode::CREATED);
     let created_tag: CreateTagResponse = response.json();
 
     assert_eq!(created_tag.name, "database-tag");
@@ -49,7 +55,7 @@ async fn test_create_task() {
         .json(&json!({"task": "database-task"}))
         .await;
 
-    response.assert_status_ok();
+    response.assert_status(StatusCode::CREATED);
     let created_task: CreateTaskResponse = response.json();
 
     assert_eq!(created_task.task, "database-task");
@@ -77,14 +83,14 @@ async fn test_create_and_stop_event() {
     let server = TestServer::new(app).unwrap();
 
     let tag_response = server.post("/tag").json(&json!({"name": "work"})).await;
-    tag_response.assert_status_ok();
+    tag_response.assert_status(StatusCode::CREATED);
     let tag: CreateTagResponse = tag_response.json();
 
     let task_response = server
         .post("/task")
         .json(&json!({"task": "Write documentation"}))
         .await;
-    task_response.assert_status_ok();
+    task_response.assert_status(StatusCode::CREATED);
     let task: CreateTaskResponse = task_response.json();
 
     let event_response = server
@@ -94,7 +100,7 @@ async fn test_create_and_stop_event() {
             "tag_ids": [tag.id]
         }))
         .await;
-    event_response.assert_status_ok();
+    event_response.assert_status(StatusCode::CREATED);
     let event: CreateTimedEventResponse = event_response.json();
     assert_eq!(event.message, "Event started successfully");
     assert!(event.id > 0);
@@ -136,18 +142,18 @@ async fn test_create_event_with_multiple_tags() {
     let server = TestServer::new(app).unwrap();
 
     let tag1_response = server.post("/tag").json(&json!({"name": "urgent"})).await;
-    tag1_response.assert_status_ok();
+    tag1_response.assert_status(StatusCode::CREATED);
     let tag1: CreateTagResponse = tag1_response.json();
 
     let tag2_response = server.post("/tag").json(&json!({"name": "bug-fix"})).await;
-    tag2_response.assert_status_ok();
+    tag2_response.assert_status(StatusCode::CREATED);
     let tag2: CreateTagResponse = tag2_response.json();
 
     let task_response = server
         .post("/task")
         .json(&json!({"task": "Fix critical bug"}))
         .await;
-    task_response.assert_status_ok();
+    task_response.assert_status(StatusCode::CREATED);
     let task: CreateTaskResponse = task_response.json();
 
     let event_response = server
@@ -157,7 +163,7 @@ async fn test_create_event_with_multiple_tags() {
             "tag_ids": [tag1.id, tag2.id]
         }))
         .await;
-    event_response.assert_status_ok();
+    event_response.assert_status(StatusCode::CREATED);
 
     let event: CreateTimedEventResponse = event_response.json();
     assert_eq!(event.message, "Event started successfully");
@@ -184,7 +190,7 @@ async fn test_create_event_with_invalid_task() {
     let server = TestServer::new(app).unwrap();
 
     let tag_response = server.post("/tag").json(&json!({"name": "test-tag"})).await;
-    tag_response.assert_status_ok();
+    tag_response.assert_status(StatusCode::CREATED);
     let tag: CreateTagResponse = tag_response.json();
 
     let event_response = server
@@ -207,7 +213,7 @@ async fn test_create_event_with_invalid_tag() {
         .post("/task")
         .json(&json!({"task": "test-task"}))
         .await;
-    task_response.assert_status_ok();
+    task_response.assert_status(StatusCode::CREATED);
     let task: CreateTaskResponse = task_response.json();
 
     let event_response = server
@@ -237,14 +243,14 @@ async fn test_stop_already_stopped_event() {
     let server = TestServer::new(app).unwrap();
 
     let tag_response = server.post("/tag").json(&json!({"name": "test"})).await;
-    tag_response.assert_status_ok();
+    tag_response.assert_status(StatusCode::CREATED);
     let tag: CreateTagResponse = tag_response.json();
 
     let task_response = server
         .post("/task")
         .json(&json!({"task": "test task"}))
         .await;
-    task_response.assert_status_ok();
+    task_response.assert_status(StatusCode::CREATED);
     let task: CreateTaskResponse = task_response.json();
 
     let event_response = server
@@ -254,7 +260,7 @@ async fn test_stop_already_stopped_event() {
             "tag_ids": [tag.id]
         }))
         .await;
-    event_response.assert_status_ok();
+    event_response.assert_status(StatusCode::CREATED);
     let event: CreateTimedEventResponse = event_response.json();
 
     let stop_response = server.post(&format!("/events/stop/{}", event.id)).await;
@@ -274,25 +280,25 @@ async fn test_multiple_running_events() {
         .post("/tag")
         .json(&json!({"name": "development"}))
         .await;
-    tag1_response.assert_status_ok();
+    tag1_response.assert_status(StatusCode::CREATED);
     let tag1: CreateTagResponse = tag1_response.json();
 
     let tag2_response = server.post("/tag").json(&json!({"name": "testing"})).await;
-    tag2_response.assert_status_ok();
+    tag2_response.assert_status(StatusCode::CREATED);
     let tag2: CreateTagResponse = tag2_response.json();
 
     let task1_response = server
         .post("/task")
         .json(&json!({"task": "Implement feature X"}))
         .await;
-    task1_response.assert_status_ok();
+    task1_response.assert_status(StatusCode::CREATED);
     let task1: CreateTaskResponse = task1_response.json();
 
     let task2_response = server
         .post("/task")
         .json(&json!({"task": "Write unit tests"}))
         .await;
-    task2_response.assert_status_ok();
+    task2_response.assert_status(StatusCode::CREATED);
     let task2: CreateTaskResponse = task2_response.json();
 
     let event1_response = server
@@ -302,7 +308,7 @@ async fn test_multiple_running_events() {
             "tag_ids": [tag1.id]
         }))
         .await;
-    event1_response.assert_status_ok();
+    event1_response.assert_status(StatusCode::CREATED);
     let event1: CreateTimedEventResponse = event1_response.json();
 
     let event2_response = server
@@ -312,7 +318,7 @@ async fn test_multiple_running_events() {
             "tag_ids": [tag2.id]
         }))
         .await;
-    event2_response.assert_status_ok();
+    event2_response.assert_status(StatusCode::CREATED);
     let event2: CreateTimedEventResponse = event2_response.json();
 
     let get_events_response = server.get("/events").await;
@@ -329,3 +335,1568 @@ async fn test_multiple_running_events() {
     assert_eq!(events.count, 1);
     assert_eq!(events.events[0].id, event2.id);
 }
+
+#[tokio::test]
+async fn test_create_endpoints_return_location() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let tag_response = server.post("/tag").json(&json!({"name": "located"})).await;
+    tag_response.assert_status(StatusCode::CREATED);
+    let tag: CreateTagResponse = tag_response.json();
+    assert_eq!(
+        tag_response.header(header::LOCATION),
+        format!("/tag/{}", tag.id).as_str()
+    );
+
+    let task_response = server
+        .post("/task")
+        .json(&json!({"task": "Located task"}))
+        .await;
+    task_response.assert_status(StatusCode::CREATED);
+    let task: CreateTaskResponse = task_response.json();
+    assert_eq!(
+        task_response.header(header::LOCATION),
+        format!("/task/{}", task.id).as_str()
+    );
+
+    let event_response = server
+        .post("/events/start")
+        .json(&json!({
+            "task_id": task.id,
+            "tag_ids": [tag.id]
+        }))
+        .await;
+    event_response.assert_status(StatusCode::CREATED);
+    let event: CreateTimedEventResponse = event_response.json();
+    assert_eq!(
+        event_response.header(header::LOCATION),
+        format!("/events/{}", event.id).as_str()
+    );
+
+    let location = tag_response.header(header::LOCATION);
+    let fetched_tag: Tag = server.get(location.to_str().unwrap()).await.json();
+    assert_eq!(fetched_tag.id, tag.id);
+    assert_eq!(fetched_tag.name, "located");
+
+    let location = task_response.header(header::LOCATION);
+    let fetched_task: Task = server.get(location.to_str().unwrap()).await.json();
+    assert_eq!(fetched_task.id, task.id);
+    assert_eq!(fetched_task.task, "Located task");
+
+    let location = event_response.header(header::LOCATION);
+    let fetched_event: TimedEvent = server.get(location.to_str().unwrap()).await.json();
+    assert_eq!(fetched_event.id, event.id);
+    assert_eq!(fetched_event.task_id, task.id);
+    assert_eq!(fetched_event.tags.len(), 1);
+    assert_eq!(fetched_event.tags[0].name, "located");
+    assert!(fetched_event.stopped_at.is_none());
+}
+
+#[tokio::test]
+async fn test_get_stopped_event_by_id() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let task_response = server
+        .post("/task")
+        .json(&json!({"task": "Short task"}))
+        .await;
+    task_response.assert_status(StatusCode::CREATED);
+    let task: CreateTaskResponse = task_response.json();
+
+    let event_response = server
+        .post("/events/start")
+        .json(&json!({
+            "task_id": task.id,
+            "tag_ids": []
+        }))
+        .await;
+    event_response.assert_status(StatusCode::CREATED);
+    let event: CreateTimedEventResponse = event_response.json();
+
+    let stop_response = server.post(&format!("/events/stop/{}", event.id)).await;
+    stop_response.assert_status_ok();
+
+    let get_response = server.get(&format!("/events/{}", event.id)).await;
+    get_response.assert_status_ok();
+    let fetched_event: TimedEvent = get_response.json();
+    assert_eq!(fetched_event.id, event.id);
+    assert!(fetched_event.tags.is_empty());
+    assert!(fetched_event.stopped_at.is_some());
+}
+
+#[tokio::test]
+async fn test_get_missing_resources() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/tag/9999").await.assert_status_not_found();
+    server.get("/task/9999").await.assert_status_not_found();
+    server.get("/events/9999").await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_names_are_trimmed() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let tag_response = server
+        .post("/tag")
+        .json(&json!({"name": "  rust \n"}))
+        .await;
+    tag_response.assert_status(StatusCode::CREATED);
+    let tag: CreateTagResponse = tag_response.json();
+    assert_eq!(tag.name, "rust");
+
+    let task_response = server
+        .post("/task")
+        .json(&json!({"task": "\tReview PRs  "}))
+        .await;
+    task_response.assert_status(StatusCode::CREATED);
+    let task: CreateTaskResponse = task_response.json();
+    assert_eq!(task.task, "Review PRs");
+
+    let duplicate_response = server.post("/tag").json(&json!({"name": "rust  "})).await;
+    duplicate_response.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_invalid_names_are_rejected() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.post("/tag").json(&json!({"name": "   "})).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: ValidationErrors = response.json();
+    assert_eq!(errors.errors.len(), 1);
+    assert_eq!(errors.errors[0].field, "name");
+
+    let long_task = "x".repeat(1024 * 1024);
+    let response = server.post("/task").json(&json!({"task": long_task})).await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: ValidationErrors = response.json();
+    assert_eq!(errors.errors[0].field, "task");
+
+    let tags: GetTagsResponse = server.get("/tags").await.json();
+    assert_eq!(tags.count, 0);
+    let tasks: GetTasksResponse = server.get("/tasks").await.json();
+    assert_eq!(tasks.count, 0);
+}
+
+#[tokio::test]
+async fn test_event_validation_reports_all_violations() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .post("/events/start")
+        .json(&json!({
+            "task_id": 0,
+            "tag_ids": [-1, -2]
+        }))
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: ValidationErrors = response.json();
+    assert_eq!(errors.errors.len(), 3);
+    assert_eq!(errors.errors[0].field, "task_id");
+    assert_eq!(errors.errors[1].field, "tag_ids");
+    assert_eq!(errors.errors[2].field, "tag_ids");
+}
+
+#[tokio::test]
+async fn test_duplicate_tag_ids_are_deduplicated() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let tag: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "dup"}))
+        .await
+        .json();
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Dedup task"}))
+        .await
+        .json();
+
+    let event_response = server
+        .post("/events/start")
+        .json(&json!({
+            "task_id": task.id,
+            "tag_ids": [tag.id, tag.id, tag.id]
+        }))
+        .await;
+    event_response.assert_status(StatusCode::CREATED);
+    let event: CreateTimedEventResponse = event_response.json();
+
+    let fetched_event: TimedEvent = server.get(&format!("/events/{}", event.id)).await.json();
+    assert_eq!(fetched_event.tags.len(), 1);
+    assert_eq!(fetched_event.tags[0].id, tag.id);
+}
+
+#[tokio::test]
+async fn test_tag_case_policy() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_validation(ValidationConfig {
+        tag_case: TagCase::Lowercase,
+        ..ValidationConfig::default()
+    });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let tag_response = server.post("/tag").json(&json!({"name": "Rust"})).await;
+    tag_response.assert_status(StatusCode::CREATED);
+    let tag: CreateTagResponse = tag_response.json();
+    assert_eq!(tag.name, "rust");
+
+    let duplicate_response = server.post("/tag").json(&json!({"name": "RUST"})).await;
+    duplicate_response.assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_idempotent_event_start() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Retried task"}))
+        .await
+        .json();
+
+    let first_response = server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-1")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await;
+    first_response.assert_status(StatusCode::CREATED);
+    let first: CreateTimedEventResponse = first_response.json();
+
+    let retry_response = server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-1")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await;
+    retry_response.assert_status(StatusCode::CREATED);
+    assert_eq!(retry_response.header(IDEMPOTENT_REPLAYED), "true");
+    assert_eq!(
+        retry_response.header(header::LOCATION),
+        first_response.header(header::LOCATION)
+    );
+    let retry: CreateTimedEventResponse = retry_response.json();
+    assert_eq!(retry.id, first.id);
+
+    let events: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(events.count, 1);
+
+    let other_key_response = server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-2")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await;
+    other_key_response.assert_status(StatusCode::CREATED);
+
+    let events: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(events.count, 2);
+}
+
+#[tokio::test]
+async fn test_idempotent_event_stop() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Stopped twice"}))
+        .await
+        .json();
+    let event: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .json();
+
+    let path = format!("/events/stop/{}", event.id);
+    let first_response = server
+        .post(&path)
+        .add_header(IDEMPOTENCY_KEY, "stop-1")
+        .await;
+    first_response.assert_status_ok();
+    let first: StopEventResponse = first_response.json();
+
+    let retry_response = server
+        .post(&path)
+        .add_header(IDEMPOTENCY_KEY, "stop-1")
+        .await;
+    retry_response.assert_status_ok();
+    let retry: StopEventResponse = retry_response.json();
+    assert_eq!(retry.id, first.id);
+    assert_eq!(retry.duration_seconds, first.duration_seconds);
+
+    server.post(&path).await.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_failed_requests_are_not_replayed() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/events/stop/1")
+        .add_header(IDEMPOTENCY_KEY, "too-early")
+        .await
+        .assert_status_not_found();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Late start"}))
+        .await
+        .json();
+    let event: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .json();
+    assert_eq!(event.id, 1);
+
+    server
+        .post("/events/stop/1")
+        .add_header(IDEMPOTENCY_KEY, "too-early")
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_idempotency_key_reused_with_another_body() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "tag-1")
+        .json(&json!({"name": "first"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let response = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "tag-1")
+        .json(&json!({"name": "second"}))
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: ValidationErrors = response.json();
+    assert_eq!(errors.errors[0].field, "idempotency-key");
+
+    let tags: GetTagsResponse = server.get("/tags").await.json();
+    assert_eq!(tags.count, 1);
+}
+
+#[tokio::test]
+async fn test_abandoned_idempotency_keys_are_freed_and_purged() {
+    let path = std::env::temp_dir().join(format!(
+        "rustimenator-idempotency-{}.db",
+        std::process::id()
+    ));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let pool = create_database_pool(&url).await.unwrap();
+    let sqlite = &sqlx::SqlitePool::connect(&url).await.unwrap();
+    // Keys claimed by requests that never finished, as after a crash.
+    for (key, age) in [("recent", "-1 seconds"), ("stale", "-2 minutes")] {
+        sqlx::query(
+            "INSERT INTO idempotency_keys (idempotency_key, route, created_at)
+             VALUES (?, 'POST /tag', datetime('now', ?))",
+        )
+        .bind(key)
+        .bind(age)
+        .execute(sqlite)
+        .await
+        .unwrap();
+    }
+    let state = AppState::new(pool.clone()).with_idempotency(IdempotencyConfig {
+        window_seconds: 3600,
+        pending_seconds: 60,
+        purge_interval_seconds: 3600,
+    });
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let waiting = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "recent")
+        .json(&json!({"name": "waiting"}))
+        .await;
+    waiting.assert_status(StatusCode::CONFLICT);
+    assert_eq!(waiting.header(header::RETRY_AFTER), "1");
+    server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "stale")
+        .json(&json!({"name": "retried"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let retry = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "stale")
+        .json(&json!({"name": "retried"}))
+        .await;
+    assert_eq!(retry.header(IDEMPOTENT_REPLAYED), "true");
+
+    // A key past the window is not replayed, even before the purge deletes it.
+    let expire = || async {
+        sqlx::query("UPDATE idempotency_keys SET created_at = datetime('now', '-2 hours')")
+            .execute(sqlite)
+            .await
+            .unwrap();
+    };
+    expire().await;
+    let renewed = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "stale")
+        .json(&json!({"name": "renewed"}))
+        .await;
+    renewed.assert_status(StatusCode::CREATED);
+    assert!(renewed.maybe_header(IDEMPOTENT_REPLAYED).is_none());
+
+    // Expired keys are deleted by the background purge, not by requests.
+    expire().await;
+    let remaining = || async {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM idempotency_keys")
+            .fetch_one(sqlite)
+            .await
+            .unwrap()
+    };
+    assert_eq!(remaining().await, 2);
+    let tracker = state.tracker();
+    tracker.spawn_idempotency_purge();
+    for _ in 0..50 {
+        if remaining().await == 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    tracker.shutdown.trigger();
+    assert_eq!(remaining().await, 0);
+
+    sqlite.close().await;
+    pool.close().await;
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_idempotent_tag_create() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let first_response = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "tag-1")
+        .json(&json!({"name": "once"}))
+        .await;
+    first_response.assert_status(StatusCode::CREATED);
+
+    let retry_response = server
+        .post("/tag")
+        .add_header(IDEMPOTENCY_KEY, "tag-1")
+        .json(&json!({"name": "once"}))
+        .await;
+    retry_response.assert_status(StatusCode::CREATED);
+    let first: CreateTagResponse = first_response.json();
+    let retry: CreateTagResponse = retry_response.json();
+    assert_eq!(retry.id, first.id);
+
+    server
+        .post("/tag")
+        .json(&json!({"name": "once"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_single_timer_mode_switches_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_timer_mode(TimerMode::Single);
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let task1: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "First focus"}))
+        .await
+        .json();
+    let task2: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Second focus"}))
+        .await
+        .json();
+
+    let event1_response = server
+        .post("/events/start")
+        .json(&json!({"task_id": task1.id, "tag_ids": []}))
+        .await;
+    event1_response.assert_status(StatusCode::CREATED);
+    let event1: CreateTimedEventResponse = event1_response.json();
+    assert!(event1.stopped_events.is_empty());
+
+    let event2_response = server
+        .post("/events/start")
+        .json(&json!({"task_id": task2.id, "tag_ids": []}))
+        .await;
+    event2_response.assert_status(StatusCode::CREATED);
+    let event2: CreateTimedEventResponse = event2_response.json();
+    assert_eq!(event2.stopped_events.len(), 1);
+    assert_eq!(event2.stopped_events[0].id, event1.id);
+    assert!(event2.stopped_events[0].duration_seconds >= 0);
+
+    let events: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(events.count, 1);
+    assert_eq!(events.events[0].id, event2.id);
+
+    let stopped: TimedEvent = server.get(&format!("/events/{}", event1.id)).await.json();
+    assert!(stopped.stopped_at.is_some());
+}
+
+#[tokio::test]
+async fn test_single_timer_mode_keeps_running_event_on_failed_start() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_timer_mode(TimerMode::Single);
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Keep running"}))
+        .await
+        .json();
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": [9999]}))
+        .await
+        .assert_status_bad_request();
+
+    let events: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(events.count, 1);
+}
+
+#[tokio::test]
+async fn test_continue_event() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let tag1: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "deep-work"}))
+        .await
+        .json();
+    let tag2: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "client"}))
+        .await
+        .json();
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Yesterday's task"}))
+        .await
+        .json();
+    let event: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": [tag1.id, tag2.id]}))
+        .await
+        .json();
+
+    server
+        .post(&format!("/events/{}/continue", event.id))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    server
+        .post(&format!("/events/stop/{}", event.id))
+        .await
+        .assert_status_ok();
+
+    let continue_response = server.post(&format!("/events/{}/continue", event.id)).await;
+    continue_response.assert_status(StatusCode::CREATED);
+    let continued: CreateTimedEventResponse = continue_response.json();
+    assert_ne!(continued.id, event.id);
+    assert_eq!(
+        continue_response.header(header::LOCATION),
+        format!("/events/{}", continued.id).as_str()
+    );
+
+    let fetched_event: TimedEvent = server
+        .get(&format!("/events/{}", continued.id))
+        .await
+        .json();
+    assert_eq!(fetched_event.task_id, task.id);
+    assert!(fetched_event.stopped_at.is_none());
+    let mut tag_ids: Vec<i64> = fetched_event.tags.iter().map(|t| t.id).collect();
+    tag_ids.sort();
+    assert_eq!(tag_ids, vec![tag1.id, tag2.id]);
+
+    server
+        .post("/events/9999/continue")
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_recent_event_combinations() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let tag1: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "meeting"}))
+        .await
+        .json();
+    let tag2: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "internal"}))
+        .await
+        .json();
+    let task1: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Standup"}))
+        .await
+        .json();
+    let task2: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Code review"}))
+        .await
+        .json();
+
+    for tag_ids in [
+        vec![tag1.id, tag2.id],
+        vec![tag2.id, tag1.id],
+        vec![tag1.id, tag2.id],
+    ] {
+        server
+            .post("/events/start")
+            .json(&json!({"task_id": task1.id, "tag_ids": tag_ids}))
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": task2.id, "tag_ids": []}))
+        .await
+        .assert_status(StatusCode::CREATED);
+
+    let response = server.get("/events/combinations").await;
+    response.assert_status_ok();
+    let combinations: GetEventCombinationsResponse = response.json();
+    assert_eq!(combinations.count, 2);
+
+    let top = &combinations.combinations[0];
+    assert_eq!(top.task_id, task1.id);
+    assert_eq!(top.uses, 3);
+    assert_eq!(top.tag_ids, vec![tag1.id, tag2.id]);
+    assert_eq!(top.tags.len(), 2);
+    assert_eq!(top.task.as_ref().unwrap().task, "Standup");
+
+    let second = &combinations.combinations[1];
+    assert_eq!(second.task_id, task2.id);
+    assert_eq!(second.uses, 1);
+    assert!(second.tag_ids.is_empty());
+
+    let limited: GetEventCombinationsResponse =
+        server.get("/events/combinations?limit=1").await.json();
+    assert_eq!(limited.count, 1);
+}
+
+#[tokio::test]
+async fn test_event_mutations_are_broadcast() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_timer_mode(TimerMode::Single);
+    let mut notifications = state.notifier.subscribe();
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let tag: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "live"}))
+        .await
+        .json();
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Broadcast task"}))
+        .await
+        .json();
+
+    let event1: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": [tag.id]}))
+        .await
+        .json();
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Started);
+    assert_eq!(notification.event.id, event1.id);
+    assert_eq!(notification.event.tags.len(), 1);
+    assert_eq!(notification.event.task.unwrap().task, "Broadcast task");
+
+    let event2: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .json();
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Stopped);
+    assert_eq!(notification.event.id, event1.id);
+    assert!(notification.event.stopped_at.is_some());
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Started);
+    assert_eq!(notification.event.id, event2.id);
+
+    server
+        .post(&format!("/events/stop/{}", event2.id))
+        .await
+        .assert_status_ok();
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Stopped);
+    assert_eq!(notification.event.id, event2.id);
+
+    server
+        .post(&format!("/events/{}/continue", event2.id))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Started);
+    assert_eq!(notification.event.task_id, task.id);
+}
+
+#[tokio::test]
+async fn test_event_stream_pushes_server_sent_events() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/events/stream").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    let mut body = response.into_body();
+
+    let task_response = app
+        .clone()
+        .oneshot(
+            Request::post("/task")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"task": "Streamed task"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(task_response.status(), StatusCode::CREATED);
+
+    let start_response = app
+        .clone()
+        .oneshot(
+            Request::post("/events/start")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"task_id": 1, "tag_ids": []}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(start_response.status(), StatusCode::CREATED);
+
+    let frame = tokio::time::timeout(tokio::time::Duration::from_secs(5), body.frame())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let chunk = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+    assert!(chunk.starts_with("event: started\n"));
+    let data = chunk
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .unwrap();
+    let event: TimedEvent = serde_json::from_str(data).unwrap();
+    assert_eq!(event.id, 1);
+    assert_eq!(event.task.unwrap().task, "Streamed task");
+}
+
+#[tokio::test]
+async fn test_websocket_start_stop_and_subscribe() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::builder().http_transport().build(app).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Tray task"}))
+        .await
+        .json();
+
+    let mut socket = server.get_websocket("/ws").await.into_websocket().await;
+
+    socket
+        .send_json(&json!({"type": "subscribe", "request_id": "sub"}))
+        .await;
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Ack {
+            request_id, status, ..
+        } => {
+            assert_eq!(request_id.as_deref(), Some("sub"));
+            assert_eq!(status, 200);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+
+    socket
+        .send_json(&json!({"type": "start", "request_id": "s1", "task_id": task.id}))
+        .await;
+    let event_id = match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Ack {
+            request_id,
+            status,
+            body,
+        } => {
+            assert_eq!(request_id.as_deref(), Some("s1"));
+            assert_eq!(status, 201);
+            let started: CreateTimedEventResponse = serde_json::from_value(body).unwrap();
+            assert_eq!(started.message, "Event started successfully");
+            started.id
+        }
+        other => panic!("unexpected message: {:?}", other),
+    };
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Event { change, event } => {
+            assert_eq!(change, EventChange::Started);
+            assert_eq!(event.id, event_id);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+
+    socket
+        .send_json(&json!({"type": "stop", "request_id": "s2", "event_id": event_id}))
+        .await;
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Ack {
+            request_id, body, ..
+        } => {
+            assert_eq!(request_id.as_deref(), Some("s2"));
+            let stopped: StopEventResponse = serde_json::from_value(body).unwrap();
+            assert_eq!(stopped.id, event_id);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Event { change, event } => {
+            assert_eq!(change, EventChange::Stopped);
+            assert!(event.stopped_at.is_some());
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+
+    let events: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(events.count, 0);
+}
+
+#[tokio::test]
+async fn test_websocket_errors_match_http_statuses() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::builder().http_transport().build(app).unwrap();
+
+    let mut socket = server.get_websocket("/ws").await.into_websocket().await;
+
+    socket
+        .send_json(&json!({"type": "stop", "request_id": "missing", "event_id": 9999}))
+        .await;
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Error {
+            request_id, status, ..
+        } => {
+            assert_eq!(request_id.as_deref(), Some("missing"));
+            assert_eq!(status, 404);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+
+    socket
+        .send_json(
+            &json!({"type": "start", "request_id": "invalid", "task_id": 0, "tag_ids": [-1]}),
+        )
+        .await;
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Error { status, errors, .. } => {
+            assert_eq!(status, 422);
+            assert_eq!(errors.len(), 2);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+
+    socket.send_text("not json").await;
+    match socket.receive_json::<ServerMessage>().await {
+        ServerMessage::Error {
+            request_id, status, ..
+        } => {
+            assert!(request_id.is_none());
+            assert_eq!(status, 400);
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_get_events_filters() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let app = create_app(pool);
+    let server = TestServer::new(app).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "History task"}))
+        .await
+        .json();
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let event: CreateTimedEventResponse = server
+            .post("/events/start")
+            .json(&json!({"task_id": task.id, "tag_ids": []}))
+            .await
+            .json();
+        ids.push(event.id);
+    }
+    server
+        .post(&format!("/events/stop/{}", ids[0]))
+        .await
+        .assert_status_ok();
+
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 2);
+
+    let stopped: GetEventsResponse = server.get("/events?status=stopped").await.json();
+    assert_eq!(stopped.count, 1);
+    assert_eq!(stopped.events[0].id, ids[0]);
+
+    let all: GetEventsResponse = server.get("/events?status=all").await.json();
+    assert_eq!(all.count, 3);
+
+    let limited: GetEventsResponse = server.get("/events?status=all&limit=2").await.json();
+    assert_eq!(limited.count, 2);
+
+    let future: GetEventsResponse = server
+        .get("/events?status=all&since=2999-01-01T00:00:00")
+        .await
+        .json();
+    assert_eq!(future.count, 0);
+
+    let past: GetEventsResponse = server
+        .get("/events?status=all&until=2000-01-01T00:00:00")
+        .await
+        .json();
+    assert_eq!(past.count, 0);
+}
+
+#[tokio::test]
+async fn test_update_and_delete_event() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let mut notifications = state.notifier.subscribe();
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let tag: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "edited"}))
+        .await
+        .json();
+    let task1: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Original task"}))
+        .await
+        .json();
+    let task2: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Corrected task"}))
+        .await
+        .json();
+    let event: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task1.id, "tag_ids": []}))
+        .await
+        .json();
+    notifications.recv().await.unwrap();
+
+    let response = server
+        .patch(&format!("/events/{}", event.id))
+        .json(&json!({
+            "task_id": task2.id,
+            "tag_ids": [tag.id],
+            "created_at": "2025-01-01T09:00:00",
+            "stopped_at": "2025-01-01T10:30:00"
+        }))
+        .await;
+    response.assert_status_ok();
+    let updated: TimedEvent = response.json();
+    assert_eq!(updated.task_id, task2.id);
+    assert_eq!(updated.task.unwrap().task, "Corrected task");
+    assert_eq!(updated.tags.len(), 1);
+    assert_eq!(updated.tags[0].id, tag.id);
+    assert_eq!(
+        updated.stopped_at.unwrap().to_string(),
+        "2025-01-01 10:30:00"
+    );
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Edited);
+    assert_eq!(notification.event.id, event.id);
+
+    let response = server
+        .patch(&format!("/events/{}", event.id))
+        .json(&json!({"stopped_at": "2024-12-31T23:00:00"}))
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let errors: ValidationErrors = response.json();
+    assert_eq!(errors.errors[0].field, "stopped_at");
+
+    server
+        .patch(&format!("/events/{}", event.id))
+        .json(&json!({"task_id": 999}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .patch("/events/999")
+        .json(&json!({"task_id": task1.id}))
+        .await
+        .assert_status_not_found();
+
+    server
+        .delete(&format!("/events/{}", event.id))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    let notification = notifications.recv().await.unwrap();
+    assert_eq!(notification.change, EventChange::Deleted);
+    assert_eq!(notification.event.id, event.id);
+
+    server
+        .get(&format!("/events/{}", event.id))
+        .await
+        .assert_status_not_found();
+    server
+        .delete(&format!("/events/{}", event.id))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_web_ui_is_served() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    let response = server.get("/").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header(header::CONTENT_TYPE)
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    assert!(response.text().contains("/ui/app.js"));
+
+    let response = server.get("/ui/app.js").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header(header::CONTENT_TYPE)
+            .to_str()
+            .unwrap()
+            .starts_with("text/javascript")
+    );
+
+    let response = server.get("/ui/style.css").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header(header::CONTENT_TYPE)
+            .to_str()
+            .unwrap()
+            .starts_with("text/css")
+    );
+
+    let config: serde_json::Value = server.get("/ui/config.json").await.json();
+    assert_eq!(config["event_stream"], true);
+}
+
+#[tokio::test]
+async fn test_web_ui_config_reports_disabled_event_stream() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_features(FeatureConfig {
+        event_stream: false,
+        ..FeatureConfig::default()
+    });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let config: serde_json::Value = server.get("/ui/config.json").await.json();
+    assert_eq!(config["event_stream"], false);
+}
+
+#[tokio::test]
+async fn test_disabled_features_are_not_routed() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_features(FeatureConfig {
+        web_ui: false,
+        event_stream: false,
+        websocket: false,
+        metrics: false,
+        api_docs: false,
+    });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    server.get("/").await.assert_status_not_found();
+    server.get("/ui/app.js").await.assert_status_not_found();
+    server
+        .get("/ui/config.json")
+        .await
+        .assert_status_not_found();
+    // Without its own route the path falls through to `/events/{id}`.
+    assert!(
+        server
+            .get("/events/stream")
+            .await
+            .status_code()
+            .is_client_error()
+    );
+    server.get("/ws").await.assert_status_not_found();
+    server.get("/metrics").await.assert_status_not_found();
+    server.get("/openapi.json").await.assert_status_not_found();
+    server.get("/docs").await.assert_status_not_found();
+    server.get("/events").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_token_auth() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_auth(AuthConfig {
+        mode: AuthMode::Token,
+        tokens: vec!["first".to_string(), "second".to_string()],
+    });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let response = server.get("/tags").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+    assert_eq!(response.header(header::WWW_AUTHENTICATE), "Bearer");
+    server
+        .post("/tag")
+        .authorization_bearer("wrong")
+        .json(&json!({"name": "locked"}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    server
+        .post("/tag")
+        .authorization_bearer("second")
+        .json(&json!({"name": "unlocked"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    let tags: GetTagsResponse = server
+        .get("/tags")
+        .authorization_bearer("first")
+        .await
+        .json();
+    assert_eq!(tags.count, 1);
+
+    server.get("/").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_web_ui_with_token_auth() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_auth(AuthConfig {
+        mode: AuthMode::Token,
+        tokens: vec!["first".to_string()],
+    });
+    let app = create_app_with_state(state);
+    let server = TestServer::new(app.clone()).unwrap();
+
+    // The page and its bootstrap are public and tell the UI to ask for a token.
+    server.get("/").await.assert_status_ok();
+    server.get("/ui/app.js").await.assert_status_ok();
+    let config: serde_json::Value = server.get("/ui/config.json").await.json();
+    assert_eq!(config["token_required"], true);
+    assert_eq!(config["event_stream"], true);
+
+    // Its API calls carry the token as a bearer header.
+    server
+        .get("/events")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .post("/task")
+        .authorization_bearer("first")
+        .json(&json!({"task": "from the browser"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    server
+        .get("/events")
+        .authorization_bearer("first")
+        .await
+        .assert_status_ok();
+
+    // The event stream takes it as a query parameter instead.
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/events/stream?token=first")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+    server
+        .get("/events/stream?token=wrong")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/events/stream")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    // Other routes do not accept the query parameter.
+    server
+        .get("/tasks?token=first")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_cors_allowed_origins() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_cors(CorsConfig {
+        allowed_origins: vec!["http://allowed.test".to_string()],
+    });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let response = server
+        .get("/tags")
+        .add_header(header::ORIGIN, "http://allowed.test")
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+        "http://allowed.test"
+    );
+
+    let response = server
+        .get("/tags")
+        .add_header(header::ORIGIN, "http://other.test")
+        .await;
+    assert!(
+        response
+            .maybe_header(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none()
+    );
+}
+
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_requests_are_traced() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    let response = server
+        .get("/tag/42")
+        .add_header(REQUEST_ID, "trace-me")
+        .await;
+    response.assert_status_not_found();
+    assert_eq!(response.header(REQUEST_ID), "trace-me");
+
+    let response = server.get("/tags").await;
+    response.assert_status_ok();
+    assert!(!response.header(REQUEST_ID).is_empty());
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let completed = logs
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|line| line["span"]["request_id"] == "trace-me")
+        .expect("request log line");
+    assert_eq!(completed["fields"]["message"], "request completed");
+    assert_eq!(completed["fields"]["status"], 404);
+    assert!(completed["fields"]["latency_ms"].is_number());
+    assert_eq!(completed["span"]["method"], "GET");
+    assert_eq!(completed["span"]["route"], "/tag/{id}");
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Measured task"}))
+        .await
+        .json();
+    for _ in 0..2 {
+        server
+            .post("/events/start")
+            .json(&json!({"task_id": task.id, "tag_ids": []}))
+            .await
+            .assert_status(StatusCode::CREATED);
+    }
+    server.get("/task/1").await.assert_status_ok();
+    server.get("/task/999").await.assert_status_not_found();
+
+    let response = server.get("/metrics").await;
+    response.assert_status_ok();
+    assert!(
+        response
+            .header(header::CONTENT_TYPE)
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain")
+    );
+    let text = response.text();
+    for line in [
+        r#"rustimenator_http_requests_total{method="POST",route="/events/start",status="201"} 2"#,
+        r#"rustimenator_http_requests_total{method="GET",route="/task/{id}",status="200"} 1"#,
+        r#"rustimenator_http_requests_total{method="GET",route="/task/{id}",status="404"} 1"#,
+        r#"rustimenator_http_request_duration_seconds_count{method="POST",route="/task"} 1"#,
+        "rustimenator_running_events 2",
+    ] {
+        assert!(
+            text.lines().any(|l| l == line),
+            "missing `{line}` in\n{text}"
+        );
+    }
+    assert!(text.contains("rustimenator_db_pool_max_connections 10"));
+    assert!(text.contains("rustimenator_tracked_seconds "));
+}
+
+#[tokio::test]
+async fn test_health_and_readiness() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    let response = server.get("/healthz").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<HealthResponse>().status, "ok");
+
+    let response = server.get("/readyz").await;
+    response.assert_status_ok();
+    let readiness: ReadinessResponse = response.json();
+    assert_eq!(readiness.status, "ready");
+    assert!(readiness.checks.database.ok);
+    assert!(readiness.checks.writable.ok);
+    assert!(readiness.checks.migrations.ok);
+    assert!(readiness.checks.migrations.expected > 0);
+    assert_eq!(
+        readiness.checks.migrations.applied,
+        readiness.checks.migrations.expected
+    );
+}
+
+#[tokio::test]
+async fn test_not_ready_without_migrations() {
+    let pool = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+    let server = TestServer::new(create_app(pool)).unwrap();
+
+    server.get("/healthz").await.assert_status_ok();
+    let response = server.get("/readyz").await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    let readiness: ReadinessResponse = response.json();
+    assert_eq!(readiness.status, "unavailable");
+    assert!(readiness.checks.database.ok);
+    assert!(!readiness.checks.migrations.ok);
+    assert_eq!(readiness.checks.migrations.applied, 0);
+    assert_eq!(
+        readiness.checks.migrations.pending.len(),
+        readiness.checks.migrations.expected
+    );
+}
+
+#[tokio::test]
+async fn test_not_ready_when_read_only() {
+    let path =
+        std::env::temp_dir().join(format!("rustimenator-readonly-{}.db", std::process::id()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    create_database_pool(&url).await.unwrap().close().await;
+
+    let options = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(&path)
+        .read_only(true);
+    let pool = sqlx::SqlitePool::connect_with(options).await.unwrap();
+    let server = TestServer::new(create_app(pool.clone())).unwrap();
+
+    let response = server.get("/readyz").await;
+    response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    let readiness: ReadinessResponse = response.json();
+    assert!(readiness.checks.database.ok);
+    assert!(readiness.checks.migrations.ok);
+    assert!(!readiness.checks.writable.ok);
+    assert!(readiness.checks.writable.error.is_some());
+
+    pool.close().await;
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_shutdown_closes_long_lived_connections() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let app = create_app_with_state(state.clone());
+
+    let response = app
+        .clone()
+        .oneshot(Request::get("/events/stream").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let mut body = response.into_body();
+
+    let server = TestServer::builder().http_transport().build(app).unwrap();
+    let mut socket = server.get_websocket("/ws").await.into_websocket().await;
+
+    state.shutdown.trigger();
+
+    let frame = tokio::time::timeout(tokio::time::Duration::from_secs(5), body.frame())
+        .await
+        .unwrap();
+    assert!(frame.is_none());
+    match socket.receive_message().await {
+        axum_test::WsMessage::Close(Some(frame)) => assert_eq!(u16::from(frame.code), 1001),
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_running_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Shutdown task"}))
+        .await
+        .json();
+    let event: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .json();
+
+    let kept = finish_running_events(&state, RunningEventsOnShutdown::Keep)
+        .await
+        .unwrap();
+    assert!(kept.is_empty());
+
+    let stopped = finish_running_events(&state, RunningEventsOnShutdown::Stop)
+        .await
+        .unwrap();
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0].id, event.id);
+
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 0);
+    assert!(resume_checkpointed_events(&state).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_shutdown_checkpoint_resumes_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let tag: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "resumed"}))
+        .await
+        .json();
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "Checkpointed task"}))
+        .await
+        .json();
+    let mut started = Vec::new();
+    for tag_ids in [vec![tag.id], vec![]] {
+        let event: CreateTimedEventResponse = server
+            .post("/events/start")
+            .json(&json!({"task_id": task.id, "tag_ids": tag_ids}))
+            .await
+            .json();
+        started.push(event.id);
+    }
+
+    let stopped = finish_running_events(&state, RunningEventsOnShutdown::Checkpoint)
+        .await
+        .unwrap();
+    assert_eq!(stopped.len(), 2);
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 0);
+
+    let resumed = resume_checkpointed_events(&state).await.unwrap();
+    assert_eq!(resumed.len(), 2);
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 2);
+    let resumed_tagged = fetch(&server, resumed[0].id).await;
+    assert!(!started.contains(&resumed_tagged.id));
+    assert_eq!(resumed_tagged.task_id, task.id);
+    assert_eq!(resumed_tagged.tags.len(), 1);
+    assert_eq!(resumed_tagged.tags[0].id, tag.id);
+    assert!(fetch(&server, resumed[1].id).await.tags.is_empty());
+
+    // Checkpoints are consumed by the first resume.
+    assert!(resume_checkpointed_events(&state).await.unwrap().is_empty());
+}
+
+async fn fetch(server: &TestServer, id: i64) -> TimedEvent {
+    server.get(&format!("/events/{}", id)).await.json()
+}
@@ -0,0 +1,167 @@
+use axum::http::{Method, StatusCode, header};
+use axum_test::TestServer;
+use rustimenator::{
+    AppState, AuthConfig, AuthMode, FeatureConfig, create_app_with_state, create_database_pool,
+};
+use serde_json::{Value, json};
+
+/// Resolves a `$ref` into `#/components/schemas`.
+fn resolve<'a>(spec: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(reference) => {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            &spec["components"]["schemas"][name]
+        }
+        None => schema,
+    }
+}
+
+/// Every operation in the document as `(method, path template)`, deletions last so the other
+/// operations still find the seeded event.
+fn operations(spec: &Value) -> Vec<(Method, String)> {
+    let mut operations = Vec::new();
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for method in item.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            operations.push((method, path.clone()));
+        }
+    }
+    operations.sort_by_key(|(method, _)| *method == Method::DELETE);
+    operations
+}
+
+/// Calls every documented operation and checks that the route exists and answers with a
+/// documented status and, on success, a body carrying the documented fields. Handlers and
+/// their documentation drifting apart fails here.
+#[tokio::test]
+async fn test_openapi_matches_routes() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    server
+        .post("/tag")
+        .json(&json!({"name": "spec"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    server
+        .post("/task")
+        .json(&json!({"task": "spec"}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": 1, "tag_ids": [1]}))
+        .await
+        .assert_status(StatusCode::CREATED);
+    server.post("/events/stop/1").await.assert_status_ok();
+    // Ends the event stream right away so it can be read like any other response.
+    state.shutdown.trigger();
+
+    let response = server.get("/openapi.json").await;
+    response.assert_status_ok();
+    let spec: Value = response.json();
+    assert_eq!(spec["openapi"], "3.1.0");
+
+    let operations = operations(&spec);
+    assert_eq!(operations.len(), 32);
+    for (method, template) in &operations {
+        let operation = &spec["paths"][template][method.as_str().to_lowercase()];
+        let path = template.replace("{id}", "1");
+        let mut request = server.method(method.clone(), &path);
+        if operation.get("requestBody").is_some() {
+            request = request.json(&json!({}));
+        }
+        let response = request.await;
+
+        let status = response.status_code();
+        let documented = &operation["responses"][status.as_str()];
+        assert!(
+            !documented.is_null(),
+            "{method} {template} answered {status}, which is not documented"
+        );
+        if !status.is_success() {
+            continue;
+        }
+        let content = &documented["content"];
+        let Some((content_type, media)) = content.as_object().and_then(|c| c.iter().next()) else {
+            assert!(
+                response.as_bytes().is_empty(),
+                "{method} {template} has a body"
+            );
+            continue;
+        };
+        let actual_type = response.header(header::CONTENT_TYPE);
+        assert!(
+            actual_type
+                .to_str()
+                .unwrap()
+                .starts_with(content_type.as_str()),
+            "{method} {template} answered {actual_type:?}, documented {content_type}"
+        );
+        if content_type == "application/json" {
+            let body: Value = response.json();
+            let schema = resolve(&spec, &media["schema"]);
+            for field in schema["required"].as_array().into_iter().flatten() {
+                let field = field.as_str().unwrap();
+                assert!(
+                    body.get(field).is_some(),
+                    "{method} {template} response is missing `{field}`"
+                );
+            }
+        }
+    }
+
+    // Requests are labelled with the route that handled them, so this catches a documented
+    // path that is served by a different route, e.g. `/events/stream` by `/events/{id}`.
+    let metrics = server.get("/metrics").await.text();
+    for (method, template) in &operations {
+        let label = format!("method=\"{method}\",route=\"{template}\"");
+        assert!(
+            metrics.contains(&label),
+            "{method} {template} was not handled by its own route"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_follows_configuration() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool)
+        .with_features(FeatureConfig {
+            event_stream: false,
+            websocket: false,
+            metrics: false,
+            ..FeatureConfig::default()
+        })
+        .with_auth(AuthConfig {
+            mode: AuthMode::Token,
+            tokens: vec!["secret".to_string()],
+        });
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let response = server.get("/openapi.json").await;
+    response.assert_status_ok();
+    let spec: Value = response.json();
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(paths.contains_key("/events"));
+    assert!(paths.contains_key("/healthz"));
+    assert!(!paths.contains_key("/events/stream"));
+    assert!(!paths.contains_key("/ws"));
+    assert!(!paths.contains_key("/metrics"));
+
+    assert_eq!(
+        spec["components"]["securitySchemes"]["bearer"]["scheme"],
+        "bearer"
+    );
+    assert_eq!(spec["security"], json!([{"bearer": []}]));
+    assert_eq!(paths["/healthz"]["get"]["security"], json!([{}]));
+
+    let response = server.get("/docs").await;
+    response.assert_status_ok();
+    assert!(response.text().contains("openapi.json"));
+    assert!(!response.text().contains("https://"));
+    for asset in ["/docs/swagger-ui-bundle.js", "/docs/swagger-ui.css"] {
+        server.get(asset).await.assert_status_ok();
+    }
+}
@@ -0,0 +1,344 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use common::{at, created, event_at, start};
+use rustimenator::{
+    AppState, GetOverlapsQuery, GetOverlapsResponse, OverlapPolicy, ResolveOverlapsResponse,
+    TimedEvent, TimerMode, create_app_with_state, create_database_pool,
+};
+use serde_json::json;
+
+async fn overlaps(server: &TestServer, tag_id: Option<i64>) -> GetOverlapsResponse {
+    server
+        .get("/events/overlaps")
+        .add_query_params(GetOverlapsQuery { tag_id })
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn test_overlapping_events_are_found_and_resolved() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+    let mut notifications = state.notifier.subscribe();
+
+    let work = created(&server, "work", "/tag", "name").await;
+    let call = created(&server, "call", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    let review = created(&server, "review", "/task", "task").await;
+    let long = event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-06T09:00:00",
+        "2025-01-06T12:00:00",
+    )
+    .await;
+    let inner = event_at(
+        &server,
+        review,
+        &[work],
+        "2025-01-06T10:00:00",
+        "2025-01-06T11:00:00",
+    )
+    .await;
+    let late = event_at(
+        &server,
+        review,
+        &[],
+        "2025-01-06T11:30:00",
+        "2025-01-06T13:00:00",
+    )
+    .await;
+
+    let all = overlaps(&server, None).await;
+    assert_eq!(all.count, 2);
+    assert_eq!(all.overlapping_seconds, 5400);
+    assert_eq!(
+        (all.overlaps[0].event_id, all.overlaps[0].other_event_id),
+        (long, inner)
+    );
+    assert_eq!(all.overlaps[0].started_at, at("2025-01-06T10:00:00"));
+    assert_eq!(all.overlaps[0].ended_at, at("2025-01-06T11:00:00"));
+    assert_eq!(all.overlaps[1].other_event_id, late);
+    assert_eq!(all.overlaps[1].overlap_seconds, 1800);
+    let tagged = overlaps(&server, Some(work)).await;
+    assert_eq!(tagged.count, 1);
+    assert_eq!(tagged.overlaps[0].other_event_id, inner);
+    while notifications.try_recv().is_ok() {}
+
+    // Splitting keeps the hour of `long` after `inner` as a new event with its task and tags.
+    let split: ResolveOverlapsResponse = server
+        .post("/events/overlaps/resolve")
+        .json(&json!({"action": "split", "tag_id": work}))
+        .await
+        .json();
+    assert_eq!(split.count, 2);
+    assert_eq!(split.events[0].id, long);
+    assert_eq!(split.events[0].stopped_at, Some(at("2025-01-06T10:00:00")));
+    let rest = &split.events[1];
+    assert_eq!(rest.task_id, write);
+    assert_eq!(rest.tags[0].id, work);
+    assert_eq!(rest.created_at, Some(at("2025-01-06T11:00:00")));
+    assert_eq!(rest.stopped_at, Some(at("2025-01-06T12:00:00")));
+    assert_eq!(notifications.try_recv().unwrap().event.id, long);
+    assert_eq!(notifications.try_recv().unwrap().event.id, rest.id);
+
+    let all = overlaps(&server, None).await;
+    assert_eq!(all.count, 1);
+    assert_eq!(
+        (all.overlaps[0].event_id, all.overlaps[0].other_event_id),
+        (rest.id, late)
+    );
+
+    let trimmed: ResolveOverlapsResponse = server
+        .post("/events/overlaps/resolve")
+        .json(&json!({"action": "trim"}))
+        .await
+        .json();
+    assert_eq!(trimmed.count, 1);
+    assert_eq!(trimmed.events[0].id, rest.id);
+    assert_eq!(
+        trimmed.events[0].stopped_at,
+        Some(at("2025-01-06T11:30:00"))
+    );
+    assert_eq!(overlaps(&server, None).await.count, 0);
+
+    // Parallel timers overlap until now; trimming stops the one that started first.
+    let first = start(&server, write, &[call]).await;
+    server
+        .patch(&format!("/events/{first}"))
+        .json(&json!({"created_at": "2025-01-07T09:00:00"}))
+        .await
+        .assert_status_ok();
+    let second = start(&server, review, &[call]).await;
+    assert_eq!(overlaps(&server, Some(call)).await.count, 1);
+    let trimmed: ResolveOverlapsResponse = server
+        .post("/events/overlaps/resolve")
+        .json(&json!({"action": "trim", "tag_id": call}))
+        .await
+        .json();
+    assert_eq!(trimmed.events[0].id, first);
+    let second: TimedEvent = server.get(&format!("/events/{second}")).await.json();
+    assert_eq!(trimmed.events[0].stopped_at, second.created_at);
+    assert!(second.stopped_at.is_none());
+
+    server
+        .post("/events/overlaps/resolve")
+        .json(&json!({"action": "merge"}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+async fn resolve(server: &TestServer, action: &str) -> ResolveOverlapsResponse {
+    server
+        .post("/events/overlaps/resolve")
+        .json(&json!({"action": action}))
+        .await
+        .json()
+}
+
+#[tokio::test]
+async fn test_split_off_time_gives_way_to_later_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app_with_state(AppState::new(pool))).unwrap();
+    let write = created(&server, "write", "/task", "task").await;
+
+    // The hours of `long` after `short` run into `nested`, which stays as it is.
+    let long = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T00:00:00",
+        "2025-01-06T10:00:00",
+    )
+    .await;
+    let short = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T02:00:00",
+        "2025-01-06T04:00:00",
+    )
+    .await;
+    let nested = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T03:00:00",
+        "2025-01-06T05:00:00",
+    )
+    .await;
+    let split = resolve(&server, "split").await;
+    assert_eq!(split.count, 3);
+    assert_eq!(
+        (split.events[0].id, split.events[0].stopped_at),
+        (long, Some(at("2025-01-06T02:00:00")))
+    );
+    assert_eq!(
+        (split.events[1].id, split.events[1].stopped_at),
+        (short, Some(at("2025-01-06T03:00:00")))
+    );
+    assert_eq!(split.events[2].created_at, Some(at("2025-01-06T05:00:00")));
+    assert_eq!(split.events[2].stopped_at, Some(at("2025-01-06T10:00:00")));
+    let nested: TimedEvent = server.get(&format!("/events/{nested}")).await.json();
+    assert_eq!(nested.created_at, Some(at("2025-01-06T03:00:00")));
+    assert_eq!(nested.stopped_at, Some(at("2025-01-06T05:00:00")));
+    assert_eq!(overlaps(&server, None).await.count, 0);
+
+    // Split-off time that is covered entirely is not kept as an empty event.
+    let long = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-07T00:00:00",
+        "2025-01-07T10:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-07T02:00:00",
+        "2025-01-07T04:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-07T04:00:00",
+        "2025-01-07T10:00:00",
+    )
+    .await;
+    let split = resolve(&server, "split").await;
+    assert_eq!(split.count, 1);
+    assert_eq!(split.events[0].id, long);
+    assert_eq!(overlaps(&server, None).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_events_starting_together_are_not_resolved() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let server = TestServer::new(create_app_with_state(AppState::new(pool))).unwrap();
+    let write = created(&server, "write", "/task", "task").await;
+
+    let first = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T09:00:00",
+        "2025-01-06T11:00:00",
+    )
+    .await;
+    event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T09:00:00",
+        "2025-01-06T11:30:00",
+    )
+    .await;
+    let later = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T10:30:00",
+        "2025-01-06T12:00:00",
+    )
+    .await;
+    for action in ["trim", "split"] {
+        server
+            .post("/events/overlaps/resolve")
+            .json(&json!({"action": action}))
+            .await
+            .assert_status(StatusCode::CONFLICT);
+    }
+    let first: TimedEvent = server.get(&format!("/events/{first}")).await.json();
+    assert_eq!(first.stopped_at, Some(at("2025-01-06T11:00:00")));
+    assert_eq!(overlaps(&server, None).await.count, 3);
+
+    server
+        .patch(&format!("/events/{}", first.id))
+        .json(&json!({"created_at": "2025-01-06T08:00:00"}))
+        .await
+        .assert_status_ok();
+    let trimmed = resolve(&server, "trim").await;
+    assert_eq!(trimmed.count, 2);
+    assert_eq!(
+        trimmed.events[0].stopped_at,
+        Some(at("2025-01-06T09:00:00"))
+    );
+    let later: TimedEvent = server.get(&format!("/events/{later}")).await.json();
+    assert_eq!(later.stopped_at, Some(at("2025-01-06T12:00:00")));
+    assert_eq!(overlaps(&server, None).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_reject_policy_refuses_overlapping_starts_and_edits() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_overlap_policy(OverlapPolicy::Reject);
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let write = created(&server, "write", "/task", "task").await;
+    let first = start(&server, write, &[]).await;
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": write, "tag_ids": []}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .post(&format!("/events/stop/{first}"))
+        .await
+        .assert_status_ok();
+    let second = start(&server, write, &[]).await;
+    server
+        .post(&format!("/events/{first}/continue"))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    server
+        .post(&format!("/events/stop/{second}"))
+        .await
+        .assert_status_ok();
+    let past = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T09:00:00",
+        "2025-01-06T10:00:00",
+    )
+    .await;
+
+    server
+        .patch(&format!("/events/{first}"))
+        .json(&json!({"created_at": "2025-01-06T09:30:00", "stopped_at": "2025-01-06T11:00:00"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    server
+        .patch(&format!("/events/{past}"))
+        .json(&json!({"stopped_at": "2025-01-06T10:30:00"}))
+        .await
+        .assert_status_ok();
+    server
+        .patch(&format!("/events/{first}"))
+        .json(&json!({"created_at": "2025-01-06T10:30:00", "stopped_at": "2025-01-06T11:00:00"}))
+        .await
+        .assert_status_ok();
+
+    // Switching timers stops the running event, so it never overlaps.
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool)
+        .with_timer_mode(TimerMode::Single)
+        .with_overlap_policy(OverlapPolicy::Reject);
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+    let write = created(&server, "write", "/task", "task").await;
+    start(&server, write, &[]).await;
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": write, "tag_ids": []}))
+        .await
+        .assert_status(StatusCode::CREATED);
+}
@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use rustimenator::{
+    AppState, CreateTaskRequest, CreateTimedEventRequest, CreateTimedEventResponse, EventChange,
+    EventNotification, PomodoroConfig, PomodoroPhase, Storage, TimeTracker, TimedEvent, TimerMode,
+    create_app_with_state, create_database_pool,
+};
+use serde_json::json;
+use tokio::sync::broadcast;
+
+/// One-second intervals, with a long break after every second work interval.
+fn quick() -> PomodoroConfig {
+    PomodoroConfig {
+        work_seconds: 1,
+        short_break_seconds: 1,
+        long_break_seconds: 1,
+        long_break_every: 2,
+        ..PomodoroConfig::default()
+    }
+}
+
+async fn next(notifications: &mut broadcast::Receiver<EventNotification>) -> (EventChange, i64) {
+    let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+        .await
+        .expect("no notification within 5 seconds")
+        .unwrap();
+    (notification.change, notification.event.id)
+}
+
+#[tokio::test]
+async fn test_pomodoro_alternates_work_and_breaks() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool.clone())
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(quick());
+    let mut notifications = state.notifier.subscribe();
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let task = server.post("/task").json(&json!({"task": "write"})).await;
+    let task_id = task.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+    for round in 1..=2 {
+        let response = server
+            .post("/events/start")
+            .json(&json!({"task_id": task_id, "tag_ids": []}))
+            .await;
+        response.assert_status(StatusCode::CREATED);
+        let work: CreateTimedEventResponse = response.json();
+        let timer = work.pomodoro.expect("work interval has a timer");
+        assert_eq!(timer.phase, PomodoroPhase::Work);
+        assert_eq!(
+            next(&mut notifications).await,
+            (EventChange::Started, work.id)
+        );
+
+        // The server stops the work interval when it ends and starts a break.
+        assert_eq!(
+            next(&mut notifications).await,
+            (EventChange::Stopped, work.id)
+        );
+        let stopped: TimedEvent = server.get(&format!("/events/{}", work.id)).await.json();
+        assert_eq!(stopped.stopped_at, Some(timer.ends_at));
+
+        let (change, break_id) = next(&mut notifications).await;
+        assert_eq!(change, EventChange::Started);
+        let on_break: TimedEvent = server.get(&format!("/events/{break_id}")).await.json();
+        assert_eq!(on_break.task.unwrap().task, "Break");
+        let pending = pool.pending_pomodoros().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].event_id, break_id);
+        let expected = if round == 2 {
+            PomodoroPhase::LongBreak
+        } else {
+            PomodoroPhase::ShortBreak
+        };
+        assert_eq!(pending[0].phase, expected);
+
+        // Breaks end on their own too, without another break after them.
+        assert_eq!(
+            next(&mut notifications).await,
+            (EventChange::Stopped, break_id)
+        );
+        assert!(pool.pending_pomodoros().await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn test_stopping_a_pomodoro_early_skips_the_break() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let tracker = TimeTracker::new(pool.clone())
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(quick());
+    let mut notifications = tracker.notifier.subscribe();
+
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "write".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+    let work = tracker
+        .start_event(CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    tracker.stop_event(work.id).await.unwrap();
+    assert_eq!(
+        next(&mut notifications).await,
+        (EventChange::Started, work.id)
+    );
+    assert_eq!(
+        next(&mut notifications).await,
+        (EventChange::Stopped, work.id)
+    );
+
+    let quiet = tokio::time::timeout(Duration::from_secs(2), notifications.recv()).await;
+    assert!(quiet.is_err(), "a break started after an early stop");
+    assert!(pool.pending_pomodoros().await.unwrap().is_empty());
+    assert_eq!(tracker.tasks().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_pomodoro_timers_survive_a_restart() {
+    let path =
+        std::env::temp_dir().join(format!("rustimenator-pomodoro-{}.db", std::process::id()));
+    let url = format!("sqlite:{}?mode=rwc", path.display());
+    let config = PomodoroConfig {
+        breaks: false,
+        ..quick()
+    };
+
+    // The first server shuts down before the work interval ends.
+    let pool = create_database_pool(&url).await.unwrap();
+    let tracker = TimeTracker::new(pool.clone())
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(config.clone());
+    tracker.shutdown.trigger();
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "write".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+    let work = tracker
+        .start_event(CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    let timer = work.pomodoro.unwrap();
+    pool.close().await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    // The next one stops it at the time it was due.
+    let pool = create_database_pool(&url).await.unwrap();
+    let tracker = TimeTracker::new(pool.clone())
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(config);
+    let mut notifications = tracker.notifier.subscribe();
+    assert_eq!(tracker.resume_pomodoros().await.unwrap(), 1);
+    assert_eq!(
+        next(&mut notifications).await,
+        (EventChange::Stopped, work.id)
+    );
+    let event = tracker.event(work.id).await.unwrap();
+    assert_eq!(event.stopped_at, Some(timer.ends_at));
+    assert_eq!(tracker.resume_pomodoros().await.unwrap(), 0);
+
+    pool.close().await;
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_checkpointed_pomodoro_resumes_its_timer() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let tracker = TimeTracker::new(pool)
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(PomodoroConfig {
+            work_seconds: 3,
+            breaks: false,
+            ..quick()
+        });
+    let mut notifications = tracker.notifier.subscribe();
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "write".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+    let work = tracker
+        .start_event(CreateTimedEventRequest {
+            task_id: task.id,
+            tag_ids: vec![],
+        })
+        .await
+        .unwrap();
+    let timer = work.pomodoro.unwrap();
+
+    // Checkpointing cancels the timer, and resuming starts the rest of the interval.
+    tracker.checkpoint_running_events().await.unwrap();
+    assert_eq!(tracker.resume_pomodoros().await.unwrap(), 0);
+    let resumed = tracker.resume_checkpointed_events().await.unwrap();
+    assert_eq!(resumed.len(), 1);
+    let resumed_timer = resumed[0].pomodoro.clone().expect("the timer is resumed");
+    assert_eq!(resumed_timer.phase, PomodoroPhase::Work);
+    assert!(resumed_timer.ends_at <= timer.ends_at + chrono::Duration::seconds(1));
+    let event = tracker.event(resumed[0].id).await.unwrap();
+    assert_eq!(event.task_id, task.id);
+
+    while notifications.try_recv().is_ok() {}
+    assert_eq!(
+        next(&mut notifications).await,
+        (EventChange::Stopped, resumed[0].id)
+    );
+}
@@ -0,0 +1,202 @@
+use rustimenator::{
+    BudgetPeriod, CreateBudgetRequest, CreateTagRequest, CreateTaskRequest,
+    CreateTimedEventRequest, EventChange, EventCombinationsQuery, EventStatus, GetBudgetQuery,
+    GetEventsQuery, TimeTracker, TimerMode, TrackerError, UpdateTimedEventRequest,
+    create_database_pool,
+};
+
+async fn tracker() -> TimeTracker {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    TimeTracker::new(pool)
+}
+
+fn start(task_id: i64, tag_ids: Vec<i64>) -> CreateTimedEventRequest {
+    CreateTimedEventRequest { tag_ids, task_id }
+}
+
+#[tokio::test]
+async fn test_tracker_runs_events_without_a_server() {
+    let tracker = tracker().await.with_timer_mode(TimerMode::Single);
+    let mut notifications = tracker.notifier.subscribe();
+
+    let tag = tracker
+        .create_tag(CreateTagRequest {
+            name: "deep-work".to_string(),
+        })
+        .await
+        .unwrap();
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "write report".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+
+    let first = tracker
+        .start_event(start(task.id, vec![tag.id]))
+        .await
+        .unwrap();
+    let second = tracker.start_event(start(task.id, vec![])).await.unwrap();
+    assert_eq!(second.stopped_events.len(), 1);
+    assert_eq!(second.stopped_events[0].id, first.id);
+
+    let running = tracker
+        .list_events(&GetEventsQuery {
+            status: Some(EventStatus::Running),
+            ..GetEventsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].id, second.id);
+
+    let stopped = tracker.stop_event(second.id).await.unwrap();
+    assert!(stopped.duration_seconds >= 0);
+    let all = tracker
+        .list_events(&GetEventsQuery {
+            status: Some(EventStatus::All),
+            ..GetEventsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 2);
+    assert!(all.iter().all(|event| event.stopped_at.is_some()));
+
+    let combinations = tracker
+        .event_combinations(&EventCombinationsQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(combinations.len(), 2);
+
+    let changes: Vec<_> = std::iter::from_fn(|| notifications.try_recv().ok())
+        .map(|notification| (notification.change, notification.event.id))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (EventChange::Started, first.id),
+            (EventChange::Stopped, first.id),
+            (EventChange::Started, second.id),
+            (EventChange::Stopped, second.id),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_tracker_errors_name_what_failed() {
+    let tracker = tracker().await;
+
+    let tag = tracker
+        .create_tag(CreateTagRequest {
+            name: "work".to_string(),
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        tracker
+            .create_tag(CreateTagRequest {
+                name: "work".to_string(),
+            })
+            .await,
+        Err(TrackerError::DuplicateTag(name)) if name == "work"
+    ));
+    assert!(matches!(
+        tracker
+            .create_tag(CreateTagRequest {
+                name: " ".to_string(),
+            })
+            .await,
+        Err(TrackerError::Invalid(_))
+    ));
+    assert!(matches!(
+        tracker.tag(99).await,
+        Err(TrackerError::TagNotFound(99))
+    ));
+    assert!(matches!(
+        tracker.task(99).await,
+        Err(TrackerError::TaskNotFound(99))
+    ));
+
+    let task = tracker
+        .create_task(CreateTaskRequest {
+            task: "review".to_string(),
+            estimate_seconds: None,
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        tracker
+            .create_task(CreateTaskRequest {
+                task: "review".to_string(),
+                estimate_seconds: None,
+            })
+            .await,
+        Err(TrackerError::DuplicateTask(_))
+    ));
+    assert!(matches!(
+        tracker.start_event(start(task.id, vec![99])).await,
+        Err(TrackerError::UnknownReference)
+    ));
+    let budget = CreateBudgetRequest {
+        tag_id: tag.id,
+        period: BudgetPeriod::Week,
+        seconds: 3600,
+    };
+    let created = tracker.create_budget(budget).await.unwrap();
+    let budget = CreateBudgetRequest {
+        tag_id: tag.id,
+        period: BudgetPeriod::Week,
+        seconds: 7200,
+    };
+    assert!(matches!(
+        tracker.create_budget(budget).await,
+        Err(TrackerError::DuplicateBudget(id, BudgetPeriod::Week)) if id == tag.id
+    ));
+    tracker.delete_budget(created.id).await.unwrap();
+    assert!(matches!(
+        tracker.budget(created.id, &GetBudgetQuery::default()).await,
+        Err(TrackerError::BudgetNotFound(id)) if id == created.id
+    ));
+
+    let event = tracker
+        .start_event(start(task.id, vec![tag.id]))
+        .await
+        .unwrap();
+    assert!(matches!(
+        tracker.continue_event(event.id).await,
+        Err(TrackerError::EventStillRunning(id)) if id == event.id
+    ));
+    tracker.stop_event(event.id).await.unwrap();
+    assert!(matches!(
+        tracker.stop_event(event.id).await,
+        Err(TrackerError::EventNotRunning(id)) if id == event.id
+    ));
+    assert!(matches!(
+        tracker.continue_event(99).await,
+        Err(TrackerError::EventNotFound(99))
+    ));
+    assert!(matches!(
+        tracker
+            .update_event(
+                event.id,
+                UpdateTimedEventRequest {
+                    stopped_at: Some(chrono::NaiveDate::MIN.and_hms_opt(0, 0, 0).unwrap()),
+                    ..UpdateTimedEventRequest::default()
+                },
+            )
+            .await,
+        Err(TrackerError::Invalid(_))
+    ));
+
+    let deleted = tracker.delete_event(event.id).await.unwrap();
+    assert_eq!(deleted.tags.len(), 1);
+    assert!(matches!(
+        tracker.event(event.id).await,
+        Err(TrackerError::EventNotFound(_))
+    ));
+    assert!(matches!(
+        tracker.delete_event(event.id).await,
+        Err(TrackerError::EventNotFound(_))
+    ));
+}
@@ -0,0 +1,354 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use common::{at, created, event_at, start};
+use rustimenator::{
+    AppState, EventChange, HeartbeatResponse, JoinEventsResponse, OverlapPolicy, PomodoroConfig,
+    SplitEventResponse, Storage, TimedEvent, TimerMode, create_app_with_state,
+    create_database_pool,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_split_event() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+    let mut notifications = state.notifier.subscribe();
+
+    let work = created(&server, "work", "/tag", "name").await;
+    let call = created(&server, "call", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    let review = created(&server, "review", "/task", "task").await;
+    let event = event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-06T09:00:00",
+        "2025-01-06T12:00:00",
+    )
+    .await;
+    while notifications.try_recv().is_ok() {}
+
+    // Without a task or tags the second half keeps the event's.
+    let split: SplitEventResponse = server
+        .post(&format!("/events/{event}/split"))
+        .json(&json!({"at": "2025-01-06T10:00:00"}))
+        .await
+        .json();
+    assert_eq!(split.first.id, event);
+    assert_eq!(split.first.stopped_at, Some(at("2025-01-06T10:00:00")));
+    assert_eq!(split.second.task_id, write);
+    assert_eq!(split.second.tags[0].id, work);
+    assert_eq!(split.second.created_at, Some(at("2025-01-06T10:00:00")));
+    assert_eq!(split.second.stopped_at, Some(at("2025-01-06T12:00:00")));
+    let changes: Vec<_> = (0..2)
+        .map(|_| notifications.try_recv().unwrap().change)
+        .collect();
+    assert_eq!(changes, [EventChange::Edited, EventChange::Started]);
+
+    let split: SplitEventResponse = server
+        .post(&format!("/events/{}/split", split.second.id))
+        .json(&json!({"at": "2025-01-06T11:00:00", "task_id": review, "tag_ids": [call]}))
+        .await
+        .json();
+    assert_eq!(split.first.tags[0].id, work);
+    assert_eq!(split.second.task_id, review);
+    assert_eq!(split.second.tags.len(), 1);
+    assert_eq!(split.second.tags[0].id, call);
+
+    // A running event splits into a stopped first half and a running second half.
+    let running = start(&server, write, &[work]).await;
+    let created_at = server
+        .get(&format!("/events/{running}"))
+        .await
+        .json::<TimedEvent>()
+        .created_at
+        .unwrap();
+    let split_at = created_at - chrono::Duration::seconds(1);
+    server
+        .patch(&format!("/events/{running}"))
+        .json(&json!({"created_at": created_at - chrono::Duration::hours(1)}))
+        .await
+        .assert_status_ok();
+    while notifications.try_recv().is_ok() {}
+    let split: SplitEventResponse = server
+        .post(&format!("/events/{running}/split"))
+        .json(&json!({"at": split_at}))
+        .await
+        .json();
+    assert_eq!(split.first.stopped_at, Some(split_at));
+    assert!(split.second.stopped_at.is_none());
+    assert_eq!(
+        notifications.try_recv().unwrap().change,
+        EventChange::Stopped
+    );
+
+    for time in [
+        "2025-01-06T09:00:00",
+        "2025-01-06T10:00:00",
+        "2030-01-01T00:00:00",
+    ] {
+        server
+            .post(&format!("/events/{event}/split"))
+            .json(&json!({"at": time}))
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    server
+        .post(&format!("/events/{event}/split"))
+        .json(&json!({"at": "2025-01-06T09:30:00", "task_id": 99}))
+        .await
+        .assert_status_bad_request();
+    server
+        .post("/events/99/split")
+        .json(&json!({"at": "2025-01-06T09:30:00"}))
+        .await
+        .assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_join_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+    let mut notifications = state.notifier.subscribe();
+
+    let work = created(&server, "work", "/tag", "name").await;
+    let call = created(&server, "call", "/tag", "name").await;
+    let write = created(&server, "write", "/task", "task").await;
+    let review = created(&server, "review", "/task", "task").await;
+    let morning = event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-06T09:00:00",
+        "2025-01-06T10:00:00",
+    )
+    .await;
+    let noon = event_at(
+        &server,
+        write,
+        &[call],
+        "2025-01-06T11:00:00",
+        "2025-01-06T12:00:00",
+    )
+    .await;
+    let afternoon = event_at(
+        &server,
+        write,
+        &[work],
+        "2025-01-06T13:00:00",
+        "2025-01-06T14:00:00",
+    )
+    .await;
+    let other = event_at(
+        &server,
+        review,
+        &[],
+        "2025-01-06T15:00:00",
+        "2025-01-06T16:00:00",
+    )
+    .await;
+
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [morning, afternoon]}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [afternoon, other]}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [morning]}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [morning, 99]}))
+        .await
+        .assert_status_not_found();
+    while notifications.try_recv().is_ok() {}
+
+    // The order of the ids does not matter; the earliest event is kept with every tag.
+    let joined: JoinEventsResponse = server
+        .post("/events/join")
+        .json(&json!({"event_ids": [afternoon, morning, noon]}))
+        .await
+        .json();
+    assert_eq!(joined.event.id, morning);
+    assert_eq!(joined.event.created_at, Some(at("2025-01-06T09:00:00")));
+    assert_eq!(joined.event.stopped_at, Some(at("2025-01-06T14:00:00")));
+    let mut tags: Vec<_> = joined.event.tags.iter().map(|tag| tag.id).collect();
+    tags.sort();
+    assert_eq!(tags, [work, call]);
+    let mut deleted = joined.deleted_event_ids.clone();
+    deleted.sort();
+    assert_eq!(deleted, [noon, afternoon]);
+    let changes: Vec<_> = (0..3)
+        .map(|_| notifications.try_recv().unwrap().change)
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            EventChange::Edited,
+            EventChange::Deleted,
+            EventChange::Deleted
+        ]
+    );
+    server
+        .get(&format!("/events/{noon}"))
+        .await
+        .assert_status_not_found();
+
+    // The latest event may be running, and the joined event then keeps running.
+    let running = start(&server, review, &[]).await;
+    let joined: JoinEventsResponse = server
+        .post("/events/join")
+        .json(&json!({"event_ids": [other, running]}))
+        .await
+        .json();
+    assert_eq!(joined.event.id, other);
+    assert!(joined.event.stopped_at.is_none());
+}
+
+#[tokio::test]
+async fn test_reject_policy_refuses_joins_over_other_events() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool).with_overlap_policy(OverlapPolicy::Reject);
+    let server = TestServer::new(create_app_with_state(state)).unwrap();
+
+    let write = created(&server, "write", "/task", "task").await;
+    let review = created(&server, "review", "/task", "task").await;
+    let first = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T09:00:00",
+        "2025-01-06T10:00:00",
+    )
+    .await;
+    let between = event_at(
+        &server,
+        review,
+        &[],
+        "2025-01-06T10:00:00",
+        "2025-01-06T11:00:00",
+    )
+    .await;
+    let second = event_at(
+        &server,
+        write,
+        &[],
+        "2025-01-06T11:00:00",
+        "2025-01-06T12:00:00",
+    )
+    .await;
+
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [first, second]}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    server
+        .delete(&format!("/events/{between}"))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .post("/events/join")
+        .json(&json!({"event_ids": [first, second]}))
+        .await
+        .assert_status_ok();
+}
+
+/// A running event's heartbeat, idle gaps and Pomodoro timer follow it through splits and joins.
+#[tokio::test]
+async fn test_split_and_join_keep_a_running_event_watched() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    let state = AppState::new(pool)
+        .with_timer_mode(TimerMode::Pomodoro)
+        .with_pomodoro(PomodoroConfig {
+            work_seconds: 8,
+            breaks: false,
+            ..PomodoroConfig::default()
+        });
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+    let pool = &state.pool;
+    let mut notifications = state.notifier.subscribe();
+    let heartbeat = |id: i64| server.post(&format!("/events/{id}/heartbeat"));
+
+    let write = created(&server, "write", "/task", "task").await;
+    let event = start(&server, write, &[]).await;
+    let created_at = server
+        .get(&format!("/events/{event}"))
+        .await
+        .json::<TimedEvent>()
+        .created_at
+        .unwrap();
+    server
+        .patch(&format!("/events/{event}"))
+        .json(&json!({"created_at": created_at - chrono::Duration::hours(1)}))
+        .await
+        .assert_status_ok();
+    let beat: HeartbeatResponse = heartbeat(event).await.json();
+
+    // The running second half is watched from the event's last heartbeat.
+    let split: SplitEventResponse = server
+        .post(&format!("/events/{event}/split"))
+        .json(&json!({"at": beat.last_heartbeat_at - chrono::Duration::seconds(1)}))
+        .await
+        .json();
+    let second = split.second.id;
+    heartbeat(event).await.assert_status_not_found();
+    assert_eq!(pool.pending_pomodoros().await.unwrap()[0].event_id, second);
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let gaps = pool.flag_idle_events(0).await.unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(
+        (gaps[0].event_id, gaps[0].started_at),
+        (second, beat.last_heartbeat_at)
+    );
+
+    // Joining moves the open gap and the timer onto the surviving event.
+    let joined: JoinEventsResponse = server
+        .post("/events/join")
+        .json(&json!({"event_ids": [event, second]}))
+        .await
+        .json();
+    assert_eq!(joined.event.id, event);
+    assert!(joined.event.stopped_at.is_none());
+    assert!(pool.flag_idle_events(0).await.unwrap().is_empty());
+    assert_eq!(pool.pending_pomodoros().await.unwrap()[0].event_id, event);
+    let beat: HeartbeatResponse = heartbeat(event).await.json();
+    assert_eq!(beat.idle_gap.unwrap().id, gaps[0].id);
+
+    // Splitting moves gaps opened after the split point onto the second half.
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let gaps = pool.flag_idle_events(0).await.unwrap();
+    assert_eq!(gaps[0].event_id, event);
+    let split: SplitEventResponse = server
+        .post(&format!("/events/{event}/split"))
+        .json(&json!({"at": beat.last_heartbeat_at}))
+        .await
+        .json();
+    let second = split.second.id;
+    let beat: HeartbeatResponse = heartbeat(second).await.json();
+    assert_eq!(beat.idle_gap.unwrap().id, gaps[0].id);
+
+    // The moved timer still stops the event when it ends.
+    while notifications.try_recv().is_ok() {}
+    let notification =
+        tokio::time::timeout(std::time::Duration::from_secs(10), notifications.recv())
+            .await
+            .expect("the timer did not stop the event")
+            .unwrap();
+    assert_eq!(
+        (notification.change, notification.event.id),
+        (EventChange::Stopped, second)
+    );
+}
@@ -0,0 +1,628 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::NaiveDateTime;
+use rustimenator::{
+    AppState, AutoStopReason, BudgetPeriod, CreateTagResponse, CreateTaskResponse,
+    CreateTimedEventResponse, DatabasePool, EventStatus, GetEventCombinationsResponse,
+    GetEventsQuery, GetEventsResponse, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED, IdleResolution,
+    Overlap, OverlapAction, OverlapPolicy, PomodoroPhase, ReadinessResponse,
+    RunningEventsOnShutdown, StopEventResponse, Storage, StorageError, StoredResponse, TimedEvent,
+    TimerMode, UpdateTimedEventRequest, ValidationErrors, create_app_with_state,
+    create_database_pool, finish_running_events, resume_checkpointed_events,
+};
+use serde_json::json;
+
+/// Drives every storage operation through the API, so each backend is held to the same
+/// behaviour.
+async fn exercise_storage(pool: DatabasePool) {
+    let state = AppState::new(pool).with_timer_mode(TimerMode::Single);
+    let server = TestServer::new(create_app_with_state(state.clone())).unwrap();
+
+    let work: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "work"}))
+        .await
+        .json();
+    server
+        .post("/tag")
+        .json(&json!({"name": "work"}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    // Ids come from the responses, as Postgres sequences skip the rejected insert.
+    let urgent: CreateTagResponse = server
+        .post("/tag")
+        .json(&json!({"name": "urgent"}))
+        .await
+        .json();
+    let task: CreateTaskResponse = server
+        .post("/task")
+        .json(&json!({"task": "review"}))
+        .await
+        .json();
+    assert_eq!(
+        server.get("/tags").await.json::<serde_json::Value>()["count"],
+        2
+    );
+    server.get("/tag/99").await.assert_status_not_found();
+    server
+        .get(&format!("/task/{}", task.id))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": [99]}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    let first: CreateTimedEventResponse = server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-1")
+        .json(&json!({"task_id": task.id, "tag_ids": [urgent.id, work.id]}))
+        .await
+        .json();
+    let replayed = server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-1")
+        .json(&json!({"task_id": task.id, "tag_ids": [urgent.id, work.id]}))
+        .await;
+    replayed.assert_status(StatusCode::CREATED);
+    assert_eq!(replayed.header(IDEMPOTENT_REPLAYED), "true");
+    assert_eq!(replayed.json::<CreateTimedEventResponse>().id, first.id);
+    server
+        .post("/events/start")
+        .add_header(IDEMPOTENCY_KEY, "start-1")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Single timer mode stops the first event.
+    let second: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": []}))
+        .await
+        .json();
+    assert_eq!(second.stopped_events.len(), 1);
+    assert_eq!(second.stopped_events[0].id, first.id);
+
+    let running: GetEventsResponse = server.get("/events").await.json();
+    assert_eq!(running.count, 1);
+    assert_eq!(running.events[0].id, second.id);
+    let all: GetEventsResponse = server.get("/events?status=all&limit=1").await.json();
+    assert_eq!(all.count, 1);
+    let event: TimedEvent = server.get(&format!("/events/{}", first.id)).await.json();
+    assert_eq!(event.tags.len(), 2);
+    assert_eq!(event.task.unwrap().task, "review");
+
+    server
+        .post(&format!("/events/{}/continue", second.id))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+    let continued: CreateTimedEventResponse = server
+        .post(&format!("/events/{}/continue", first.id))
+        .await
+        .json();
+    let stopped: StopEventResponse = server
+        .post(&format!("/events/stop/{}", continued.id))
+        .await
+        .json();
+    assert!(stopped.duration_seconds >= 0);
+    server
+        .post(&format!("/events/stop/{}", continued.id))
+        .await
+        .assert_status_not_found();
+
+    let combinations: GetEventCombinationsResponse =
+        server.get("/events/combinations").await.json();
+    assert_eq!(combinations.count, 2);
+    assert_eq!(
+        combinations.combinations[0].tag_ids,
+        vec![work.id, urgent.id]
+    );
+    assert_eq!(combinations.combinations[0].uses, 2);
+
+    let rejected = server
+        .patch(&format!("/events/{}", first.id))
+        .json(&json!({"tag_ids": [work.id], "stopped_at": "2000-01-01T00:00:00"}))
+        .await;
+    rejected.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        rejected.json::<ValidationErrors>().errors[0].field,
+        "stopped_at"
+    );
+    let unchanged: TimedEvent = server.get(&format!("/events/{}", first.id)).await.json();
+    assert_eq!(unchanged.tags.len(), 2);
+    let updated: TimedEvent = server
+        .patch(&format!("/events/{}", first.id))
+        .json(&json!({"tag_ids": [work.id]}))
+        .await
+        .json();
+    assert_eq!(updated.tags.len(), 1);
+    server
+        .delete(&format!("/events/{}", continued.id))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+    server
+        .get(&format!("/events/{}", continued.id))
+        .await
+        .assert_status_not_found();
+
+    let third: CreateTimedEventResponse = server
+        .post("/events/start")
+        .json(&json!({"task_id": task.id, "tag_ids": [urgent.id]}))
+        .await
+        .json();
+    let checkpointed = finish_running_events(&state, RunningEventsOnShutdown::Checkpoint)
+        .await
+        .unwrap();
+    assert_eq!(checkpointed.len(), 1);
+    assert_eq!(checkpointed[0].id, third.id);
+    let resumed = resume_checkpointed_events(&state).await.unwrap();
+    assert_eq!(resumed.len(), 1);
+    assert!(resume_checkpointed_events(&state).await.unwrap().is_empty());
+
+    let metrics = server.get("/metrics").await.text();
+    assert!(metrics.contains("rustimenator_running_events 1"));
+    let readiness: ReadinessResponse = server.get("/readyz").await.json();
+    assert_eq!(readiness.status, "ready");
+
+    let pool = &state.pool;
+    let pomodoro = pool
+        .start_pomodoro(
+            task.id,
+            &[urgent.id],
+            PomodoroPhase::Work,
+            60,
+            OverlapPolicy::Allow,
+        )
+        .await
+        .unwrap();
+    assert_eq!(pomodoro.stopped_events.len(), 1);
+    let timer = pomodoro.pomodoro.unwrap();
+    let pending = pool.pending_pomodoros().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].ends_at, timer.ends_at);
+    assert_eq!(pool.completed_pomodoros().await.unwrap(), 0);
+    let finished = pool.finish_pomodoro(pomodoro.id).await.unwrap().unwrap();
+    assert_eq!(finished.id, pomodoro.id);
+    assert_eq!(finished.duration_seconds, 60);
+    assert!(pool.finish_pomodoro(pomodoro.id).await.unwrap().is_none());
+    assert!(pool.pending_pomodoros().await.unwrap().is_empty());
+    assert_eq!(pool.completed_pomodoros().await.unwrap(), 1);
+    let interrupted = pool
+        .start_pomodoro(
+            task.id,
+            &[],
+            PomodoroPhase::ShortBreak,
+            60,
+            OverlapPolicy::Allow,
+        )
+        .await
+        .unwrap();
+    assert_eq!(pool.checkpoint_running_events().await.unwrap().len(), 1);
+    assert!(pool.pending_pomodoros().await.unwrap().is_empty());
+    let resumed = pool.resume_checkpoints(Some(1500)).await.unwrap();
+    let timer = resumed[0].pomodoro.clone().unwrap();
+    assert_eq!(timer.phase, PomodoroPhase::ShortBreak);
+    assert!(timer.ends_at <= interrupted.pomodoro.unwrap().ends_at + chrono::Duration::seconds(1));
+    assert_eq!(pool.pending_pomodoros().await.unwrap().len(), 1);
+    pool.stop_running_events().await.unwrap();
+    assert!(pool.finish_pomodoro(resumed[0].id).await.unwrap().is_none());
+
+    let forgotten = pool
+        .start_event(TimerMode::Parallel, OverlapPolicy::Allow, task.id, &[])
+        .await
+        .unwrap();
+    let started_at = pool.event(forgotten.id).await.unwrap().created_at.unwrap();
+    let cutoff = started_at + chrono::Duration::hours(1);
+    let stopped = pool
+        .auto_stop_event(forgotten.id, cutoff, AutoStopReason::DailyCutoff)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stopped.duration_seconds, 3600);
+    assert!(
+        pool.auto_stop_event(forgotten.id, cutoff, AutoStopReason::DailyCutoff)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let auto_stopped = pool
+        .events(&GetEventsQuery {
+            status: Some(EventStatus::All),
+            auto_stopped: Some(true),
+            ..GetEventsQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(auto_stopped.len(), 1);
+    assert_eq!(auto_stopped[0].stopped_at, Some(cutoff));
+    assert_eq!(
+        auto_stopped[0].auto_stop_reason,
+        Some(AutoStopReason::DailyCutoff)
+    );
+
+    let watched = pool
+        .start_event(
+            TimerMode::Parallel,
+            OverlapPolicy::Allow,
+            task.id,
+            &[urgent.id],
+        )
+        .await
+        .unwrap();
+    assert!(pool.heartbeat(watched.id).await.unwrap().idle_gap.is_none());
+    assert!(matches!(
+        pool.heartbeat(forgotten.id).await,
+        Err(StorageError::NotFound)
+    ));
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let flagged = pool.flag_idle_events(0).await.unwrap();
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0].event_id, watched.id);
+    assert!(pool.flag_idle_events(0).await.unwrap().is_empty());
+    assert_eq!(pool.idle_gaps(false).await.unwrap().len(), 1);
+    let heartbeat = pool.heartbeat(watched.id).await.unwrap();
+    let gap = heartbeat.idle_gap.unwrap();
+    assert_eq!(gap.ended_at, Some(heartbeat.last_heartbeat_at));
+
+    let resolved = pool
+        .resolve_idle_gap(gap.id, IdleResolution::Split, OverlapPolicy::Allow)
+        .await
+        .unwrap();
+    assert!(resolved.stopped);
+    assert_eq!(resolved.idle_gap.resolution, Some(IdleResolution::Split));
+    assert_eq!(resolved.created_event_ids.len(), 2);
+    assert_eq!(
+        pool.event(watched.id).await.unwrap().stopped_at,
+        Some(gap.started_at)
+    );
+    let idle = pool.event(resolved.created_event_ids[0]).await.unwrap();
+    assert_eq!(
+        (idle.created_at, idle.stopped_at),
+        (Some(gap.started_at), gap.ended_at)
+    );
+    let remainder = pool.event(resolved.created_event_ids[1]).await.unwrap();
+    assert_eq!(remainder.created_at, gap.ended_at);
+    assert!(remainder.stopped_at.is_none());
+    assert_eq!(remainder.tags[0].id, urgent.id);
+    assert!(matches!(
+        pool.resolve_idle_gap(gap.id, IdleResolution::Keep, OverlapPolicy::Allow)
+            .await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(pool.idle_gaps(false).await.unwrap().is_empty());
+    assert_eq!(pool.idle_gaps(true).await.unwrap().len(), 1);
+
+    let overlap = pool.create_tag("overlap").await.unwrap();
+    let at = |time: &str| {
+        format!("2025-01-06T{time}")
+            .parse::<NaiveDateTime>()
+            .unwrap()
+    };
+    let mut spans = Vec::new();
+    for (created_at, stopped_at) in [("09:00:00", "12:00:00"), ("10:00:00", "11:00:00")] {
+        let started = pool
+            .start_event(
+                TimerMode::Parallel,
+                OverlapPolicy::Allow,
+                task.id,
+                &[overlap],
+            )
+            .await
+            .unwrap();
+        let update = UpdateTimedEventRequest {
+            created_at: Some(at(created_at)),
+            stopped_at: Some(at(stopped_at)),
+            ..UpdateTimedEventRequest::default()
+        };
+        pool.update_event(started.id, &update, OverlapPolicy::Allow)
+            .await
+            .unwrap();
+        spans.push(started.id);
+    }
+    let overlaps = pool.overlaps(Some(overlap)).await.unwrap();
+    assert_eq!(
+        overlaps,
+        [Overlap::new(
+            spans[0],
+            spans[1],
+            at("10:00:00"),
+            at("11:00:00")
+        )]
+    );
+    let overlapping = pool
+        .overlapping_events(at("11:30:00"), Some(at("13:00:00")), None)
+        .await
+        .unwrap();
+    assert_eq!(overlapping, [spans[0]]);
+    let resolved = pool
+        .resolve_overlaps(Some(overlap), OverlapAction::Split)
+        .await
+        .unwrap();
+    assert_eq!(resolved.edited, [spans[0]]);
+    assert_eq!(resolved.created_event_ids.len(), 1);
+    let rest = pool.event(resolved.created_event_ids[0]).await.unwrap();
+    assert_eq!(
+        (rest.created_at, rest.stopped_at),
+        (Some(at("11:00:00")), Some(at("12:00:00")))
+    );
+    assert_eq!(rest.tags[0].id, overlap);
+    assert!(pool.overlaps(Some(overlap)).await.unwrap().is_empty());
+
+    assert!(matches!(
+        pool.join_events(&[spans[0], rest.id], OverlapPolicy::Allow)
+            .await,
+        Err(StorageError::Invalid(_))
+    ));
+    let joined = pool
+        .join_events(&[rest.id, spans[0], spans[1]], OverlapPolicy::Allow)
+        .await
+        .unwrap();
+    assert_eq!(joined, spans[0]);
+    let event = pool.event(joined).await.unwrap();
+    assert_eq!(
+        (event.created_at, event.stopped_at),
+        (Some(at("09:00:00")), Some(at("12:00:00")))
+    );
+    assert!(matches!(
+        pool.event(rest.id).await,
+        Err(StorageError::NotFound)
+    ));
+    let second = pool
+        .split_event(
+            joined,
+            at("10:30:00"),
+            None,
+            Some(&[urgent.id]),
+            OverlapPolicy::Allow,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        pool.event(joined).await.unwrap().stopped_at,
+        Some(at("10:30:00"))
+    );
+    let second = pool.event(second).await.unwrap();
+    assert_eq!(
+        (second.task_id, second.created_at, second.stopped_at),
+        (task.id, Some(at("10:30:00")), Some(at("12:00:00")))
+    );
+    assert_eq!(second.tags.len(), 1);
+    assert_eq!(second.tags[0].id, urgent.id);
+    assert!(matches!(
+        pool.split_event(joined, at("13:00:00"), None, None, OverlapPolicy::Allow)
+            .await,
+        Err(StorageError::Invalid(_))
+    ));
+    assert!(matches!(
+        pool.split_event(9999, at("10:00:00"), None, None, OverlapPolicy::Allow)
+            .await,
+        Err(StorageError::NotFound)
+    ));
+    let widened = UpdateTimedEventRequest {
+        created_at: Some(at("10:00:00")),
+        ..UpdateTimedEventRequest::default()
+    };
+    assert!(matches!(
+        pool.update_event(second.id, &widened, OverlapPolicy::Reject).await,
+        Err(StorageError::Overlap(ids)) if ids == [joined]
+    ));
+    assert_eq!(
+        pool.event(second.id).await.unwrap().created_at,
+        Some(at("10:30:00"))
+    );
+
+    let estimated = pool.create_task("estimated", Some(600)).await.unwrap();
+    assert_eq!(
+        pool.task(estimated).await.unwrap().estimate_seconds,
+        Some(600)
+    );
+    let cleared = pool.set_task_estimate(estimated, None).await.unwrap();
+    assert_eq!(cleared.estimate_seconds, None);
+    assert!(matches!(
+        pool.set_task_estimate(9999, Some(60)).await,
+        Err(StorageError::NotFound)
+    ));
+    let window = (Some(at("10:00:00")), Some(at("11:00:00")));
+    let tagged = pool
+        .event_times(Some(task.id), Some(overlap), window.0, window.1)
+        .await
+        .unwrap();
+    assert_eq!(tagged, [(at("09:00:00"), Some(at("10:30:00")))]);
+    let untagged = pool
+        .event_times(Some(task.id), None, window.0, window.1)
+        .await
+        .unwrap();
+    assert_eq!(untagged.len(), 2);
+
+    let budget = pool
+        .create_budget(overlap, BudgetPeriod::Day, 3600)
+        .await
+        .unwrap();
+    assert_eq!(
+        (budget.tag_id, budget.period, budget.seconds),
+        (overlap, BudgetPeriod::Day, 3600)
+    );
+    assert!(matches!(
+        pool.create_budget(overlap, BudgetPeriod::Day, 60).await,
+        Err(StorageError::Conflict)
+    ));
+    assert!(matches!(
+        pool.create_budget(9999, BudgetPeriod::Day, 60).await,
+        Err(StorageError::UnknownReference)
+    ));
+    assert_eq!(
+        pool.budget(budget.id).await.unwrap().period,
+        BudgetPeriod::Day
+    );
+    assert_eq!(pool.budgets().await.unwrap().len(), 1);
+    pool.delete_budget(budget.id).await.unwrap();
+    assert!(matches!(
+        pool.delete_budget(budget.id).await,
+        Err(StorageError::NotFound)
+    ));
+
+    // A pending key is only taken over once it is older than the pending window.
+    assert!(
+        pool.reserve_idempotency_key("pending", "POST /tag", "first", 60, 3600)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !pool
+            .reserve_idempotency_key("pending", "POST /tag", "second", 60, 3600)
+            .await
+            .unwrap()
+    );
+    let pending = pool
+        .idempotent_response("pending", "POST /tag", 3600)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pending.status, None);
+    assert_eq!(pending.request_hash.as_deref(), Some("first"));
+    assert!(
+        pool.reserve_idempotency_key("pending", "POST /tag", "second", -60, 3600)
+            .await
+            .unwrap()
+    );
+    let taken_over = pool
+        .idempotent_response("pending", "POST /tag", 3600)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(taken_over.request_hash.as_deref(), Some("second"));
+
+    // A stored response is replayed within the window only, and then its key is taken over.
+    let stored = StoredResponse {
+        status: Some(201),
+        location: None,
+        body: b"{}".to_vec(),
+        request_hash: None,
+    };
+    pool.store_idempotent_response("pending", "POST /tag", &stored)
+        .await
+        .unwrap();
+    assert!(
+        !pool
+            .reserve_idempotency_key("pending", "POST /tag", "third", -60, 3600)
+            .await
+            .unwrap()
+    );
+    assert!(
+        pool.idempotent_response("pending", "POST /tag", -60)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        pool.reserve_idempotency_key("pending", "POST /tag", "third", 60, -60)
+            .await
+            .unwrap()
+    );
+    let renewed = pool
+        .idempotent_response("pending", "POST /tag", 3600)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(renewed.status, None);
+    assert_eq!(renewed.request_hash.as_deref(), Some("third"));
+
+    // Clear the way for a race between two starts: earlier steps left events running or
+    // stopped in the future.
+    pool.stop_running_events().await.unwrap();
+    let now = chrono::Utc::now().naive_utc();
+    for id in pool.overlapping_events(now, None, None).await.unwrap() {
+        pool.delete_event(id).await.unwrap();
+    }
+    let racer = pool.create_task("racer", None).await.unwrap();
+    let (first, second) = tokio::join!(
+        pool.start_event(TimerMode::Parallel, OverlapPolicy::Reject, racer, &[]),
+        pool.start_event(TimerMode::Parallel, OverlapPolicy::Reject, racer, &[]),
+    );
+    let started = match (first, second) {
+        (Ok(started), Err(StorageError::Overlap(ids)))
+        | (Err(StorageError::Overlap(ids)), Ok(started)) => {
+            assert_eq!(ids, [started.id]);
+            started
+        }
+        (first, second) => panic!(
+            "expected exactly one start to win, got {:?} and {:?}",
+            first.err(),
+            second.err()
+        ),
+    };
+    let running = pool.events(&GetEventsQuery::default()).await.unwrap();
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].id, started.id);
+
+    // Splitting and joining the running event carries its heartbeat and idle gaps along.
+    let beat = pool.heartbeat(started.id).await.unwrap();
+    let backdated = UpdateTimedEventRequest {
+        created_at: Some(beat.last_heartbeat_at - chrono::Duration::hours(1)),
+        ..UpdateTimedEventRequest::default()
+    };
+    pool.update_event(started.id, &backdated, OverlapPolicy::Allow)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let gap = pool.flag_idle_events(0).await.unwrap().remove(0);
+    let second = pool
+        .split_event(
+            started.id,
+            beat.last_heartbeat_at,
+            None,
+            None,
+            OverlapPolicy::Allow,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        pool.heartbeat(started.id).await,
+        Err(StorageError::NotFound)
+    ));
+    assert!(pool.flag_idle_events(0).await.unwrap().is_empty());
+    let joined = pool
+        .join_events(&[started.id, second], OverlapPolicy::Allow)
+        .await
+        .unwrap();
+    assert_eq!(joined, started.id);
+    assert!(pool.flag_idle_events(0).await.unwrap().is_empty());
+    let beat = pool.heartbeat(joined).await.unwrap();
+    assert_eq!(beat.idle_gap.unwrap().id, gap.id);
+}
+
+#[tokio::test]
+async fn test_sqlite_storage() {
+    let pool = create_database_pool(":memory:").await.unwrap();
+    assert!(matches!(pool, DatabasePool::Sqlite(_)));
+    exercise_storage(pool).await;
+}
+
+/// Runs against the Postgres database in `TEST_POSTGRES_URL`, which is wiped first. Ignored by
+/// default as it needs a server; run it with
+/// `TEST_POSTGRES_URL=postgres://... cargo test --test storage_tests -- --ignored`.
+#[tokio::test]
+#[ignore = "needs a PostgreSQL server in TEST_POSTGRES_URL"]
+async fn test_postgres_storage() {
+    let url = std::env::var("TEST_POSTGRES_URL")
+        .expect("TEST_POSTGRES_URL must name a scratch PostgreSQL database");
+    let reset = sqlx::PgPool::connect(&url).await.unwrap();
+    sqlx::raw_sql("DROP SCHEMA public CASCADE; CREATE SCHEMA public")
+        .execute(&reset)
+        .await
+        .unwrap();
+    reset.close().await;
+
+    let pool = create_database_pool(&url).await.unwrap();
+    assert!(matches!(pool, DatabasePool::Postgres(_)));
+    exercise_storage(pool).await;
+}
+
+#[tokio::test]
+async fn test_unsupported_database_url_is_rejected() {
+    let error = create_database_pool("mysql://localhost/rustimenator")
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("unsupported database URL"));
+}